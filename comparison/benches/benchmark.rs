@@ -1,42 +1,94 @@
+use ahash::random_state::RandomState;
 use criterion::*;
 use itertools::iproduct;
 
-use comparison::constants::{CARDINALITIES, DATA_SIZE_MULTIPLIES, PRECISIONS};
+use comparison::baselines::{Exact, KMV, LinearCounting, LogLogBeta};
+use comparison::constants::{CARDINALITIES, DATA_SIZE_MULTIPLIES, KEY, PRECISIONS};
+use gen_data::keys::{Email, Key, KeyKind, Url, Uuid};
+use gumbel_estimation::{Fixed, GHLL, GHLLPlus, GHLLReal, ULL};
+use hyperloglogplus::{HyperLogLogPF, HyperLogLogPlus};
 
 mod common;
 
-use crate::common::{bench_hll, bench_ghll, bench_ghllplus, load_data};
+use crate::common::{bench_construction, bench_end_to_end, bench_estimation, bench_insertion, bench_merge, load_data};
+
+// run a benchmark function for every estimator
+macro_rules! for_each_estimator {
+    ($bench:ident::<$key:ty>($($arg:expr),*)) => {
+        $bench::<$key, HyperLogLogPF<$key, RandomState>, _>($($arg),*);
+        $bench::<$key, GHLL<RandomState>, _>($($arg),*);
+        $bench::<$key, GHLLReal<RandomState>, _>($($arg),*);
+        $bench::<$key, GHLLReal<RandomState, f64>, _>($($arg),*);
+        $bench::<$key, GHLLReal<RandomState, Fixed<8>>, _>($($arg),*);
+        $bench::<$key, GHLLReal<RandomState, Fixed<12>>, _>($($arg),*);
+        $bench::<$key, GHLLReal<RandomState, Fixed<16>>, _>($($arg),*);
+        $bench::<$key, GHLLPlus<RandomState>, _>($($arg),*);
+        $bench::<$key, ULL<RandomState>, _>($($arg),*);
+        $bench::<$key, HyperLogLogPlus<$key, RandomState>, _>($($arg),*);
+        $bench::<$key, LogLogBeta<RandomState>, _>($($arg),*);
+        $bench::<$key, LinearCounting<RandomState>, _>($($arg),*);
+        $bench::<$key, KMV<RandomState>, _>($($arg),*);
+        $bench::<$key, Exact<$key, RandomState>, _>($($arg),*);
+    };
+}
 
 fn benchmark(c: &mut Criterion) {
-    
+    match KEY {
+        KeyKind::U64 => benchmark_key::<u64>(c),
+        KeyKind::Uuid => benchmark_key::<Uuid>(c),
+        KeyKind::Url => benchmark_key::<Url>(c),
+        KeyKind::Email => benchmark_key::<Email>(c),
+        KeyKind::Tuple => benchmark_key::<(u32, u64)>(c),
+    }
+}
+
+fn benchmark_key<K: Key>(c: &mut Criterion) {
+
+    // the construction does not depend on the data
+
+    let mut group = c.benchmark_group("Construction");
+    for prec in PRECISIONS {
+        for_each_estimator!(bench_construction::<K>(&mut group, prec));
+    }
+    group.finish();
+
     let data_sizes: Vec<_> = iproduct!(CARDINALITIES, DATA_SIZE_MULTIPLIES).filter(|(card, mult)| card * mult <= 1_000_000_000).collect();
 
-    for prec in PRECISIONS { 
-        let mut group = c.benchmark_group("Cardinality Estimation");
+    for (card, mult) in &data_sizes {
 
-        for (card, mult) in &data_sizes {
+        // read data from file
 
-            group.throughput(Throughput::Elements((*card) as u64 * (*mult) as u64));
+        let data: Vec<K> = load_data(*card, card * mult)
+            .unwrap_or_else(|e| panic!("{}", e));
 
-            // read data from file
+        for prec in PRECISIONS {
 
-            let data: Vec<u64> = load_data(*card, card * mult)
-                .unwrap_or_else(|e| panic!("{}", e));
+            // feeding the data to a created estimator
 
-            // perform Hyperloglog benchmark
+            let mut group = c.benchmark_group("Insertion");
+            group.throughput(Throughput::Elements(data.len() as u64));
+            for_each_estimator!(bench_insertion::<K>(&mut group, prec, *card, &data));
+            group.finish();
 
-            bench_hll(&mut group, prec, *card, &data);
+            // acquiring the estimates from a fed estimator
 
-            // perform Gumbel benchmark
+            let mut group = c.benchmark_group("Estimation");
+            for_each_estimator!(bench_estimation::<K>(&mut group, prec, *card, &data));
+            group.finish();
 
-            bench_ghll(&mut group, prec, *card, &data);
+            // merging estimators fed with the halves of the data
 
-            // perform GumbelLazy benchmark
+            let mut group = c.benchmark_group("Merge");
+            for_each_estimator!(bench_merge::<K>(&mut group, prec, *card, &data));
+            group.finish();
 
-            bench_ghllplus(&mut group, prec, *card, &data);
-        }
+            // the whole estimation
 
-        group.finish();
+            let mut group = c.benchmark_group("Cardinality Estimation");
+            group.throughput(Throughput::Elements(data.len() as u64));
+            for_each_estimator!(bench_end_to_end::<K>(&mut group, prec, *card, &data));
+            group.finish();
+        }
     }
 }
 
@@ -1,63 +1,408 @@
 use ahash::random_state::RandomState;
+use comparison::baselines::{Exact, KMV, LinearCounting, LogLogBeta};
+use comparison::constants::DATA_FORMAT;
+use comparison::dataset::Dataset;
 use criterion::*;
 use criterion::measurement::Measurement;
-use gumbel_estimation::{GHLL, GHLLPlus};
-use hyperloglogplus::{HyperLogLog, HyperLogLogPF};
-use std::fs::File;
+use gen_data::keys::Key;
+use gumbel_estimation::{Fixed, GHLL, GHLLPlus, GHLLReal, GumbelSketch, Strategy, ULL};
+use hyperloglogplus::{HyperLogLog, HyperLogLogPF, HyperLogLogPlus};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
 use std::hash::Hash;
 use std::io;
-use std::io::{BufRead, BufReader};
 
-pub fn load_data(card: usize, size: usize) -> Result<Vec<u64>, io::Error>
+pub fn load_data<K: Key>(card: usize, size: usize) -> Result<Vec<K>, io::Error>
 {
-    let file = File::open(format!("../data/data_{}_{}.txt", card, size))?;
-    let reader = BufReader::new(file);
+    Dataset::<K>::open(card, size, DATA_FORMAT)?.load()
+}
+
+// the seed of the hash builders and of the initial register values; estimators created
+// from the same seed can be merged
+const SEED: u64 = 0;
+
+/// A named algorithm computing an estimate from an estimator
+pub type Estimate<E> = (&'static str, fn(&mut E) -> f64);
+
+/// An estimator benchmarked by the suite
+pub trait Estimator<T>: Clone {
+    const NAME: &'static str;
+
+    /// The names of the algorithms computed from the estimator along with their estimates
+    fn estimates() -> Vec<Estimate<Self>>;
+
+    fn new(prec: u8) -> Self;
+
+    fn add(&mut self, value: &T);
+
+    fn merge(&mut self, other: &Self);
+}
+
+fn builder() -> RandomState {
+    RandomState::with_seed(SEED as usize)
+}
+
+fn rng() -> StdRng {
+    StdRng::seed_from_u64(SEED)
+}
+
+impl<T: Hash + Clone> Estimator<T> for HyperLogLogPF<T, RandomState> {
+    const NAME: &'static str = "HLL";
+    fn estimates() -> Vec<Estimate<Self>> {
+        vec![("HLL", |e| e.count())]
+    }
+
+    fn new(prec: u8) -> Self {
+        HyperLogLogPF::new(prec, builder()).unwrap()
+    }
+
+    fn add(&mut self, value: &T) {
+        self.insert(value);
+    }
+
+    fn merge(&mut self, other: &Self) {
+        HyperLogLogPF::merge(self, other).unwrap();
+    }
+}
+
+impl<T: Hash + Clone> Estimator<T> for HyperLogLogPlus<T, RandomState> {
+    const NAME: &'static str = "HLLPlus";
+    fn estimates() -> Vec<Estimate<Self>> {
+        vec![("HLLPlus", |e| e.count())]
+    }
+
+    fn new(prec: u8) -> Self {
+        HyperLogLogPlus::new(prec, builder()).unwrap()
+    }
+
+    fn add(&mut self, value: &T) {
+        self.insert(value);
+    }
+
+    fn merge(&mut self, other: &Self) {
+        HyperLogLogPlus::merge(self, other).unwrap();
+    }
+}
+
+impl<T: Hash> Estimator<T> for GHLL<RandomState> {
+    const NAME: &'static str = "GHLL";
+    fn estimates() -> Vec<Estimate<Self>> {
+        vec![
+            ("GHLLGeo", |e| e.count_geo()),
+            ("GHLLHar", |e| e.count_har()),
+            ("GHLLML", |e| e.estimate(Strategy::MaximumLikelihood)),
+            ("GHLLBias", |e| e.estimate(Strategy::BiasCorrected)),
+        ]
+    }
+
+    fn new(prec: u8) -> Self {
+        GHLL::with_precision_and_rng(prec, builder(), &mut rng()).unwrap()
+    }
+
+    fn add(&mut self, value: &T) {
+        GumbelSketch::add(self, value);
+    }
+
+    fn merge(&mut self, other: &Self) {
+        GumbelSketch::merge(self, other).unwrap();
+    }
+}
+
+impl<T: Hash> Estimator<T> for GHLLReal<RandomState> {
+    const NAME: &'static str = "GHLLReal";
+    fn estimates() -> Vec<Estimate<Self>> {
+        vec![
+            ("GHLLRealGeo", |e| e.count_geo()),
+            ("GHLLRealHar", |e| e.count_har()),
+        ]
+    }
+
+    fn new(prec: u8) -> Self {
+        GHLLReal::with_precision_and_rng(prec, builder(), &mut rng()).unwrap()
+    }
+
+    fn add(&mut self, value: &T) {
+        GHLLReal::add(self, value);
+    }
+
+    fn merge(&mut self, other: &Self) {
+        GHLLReal::merge(self, other).unwrap();
+    }
+}
+
+impl<T: Hash> Estimator<T> for GHLLReal<RandomState, f64> {
+    const NAME: &'static str = "GHLLReal64";
+    fn estimates() -> Vec<Estimate<Self>> {
+        vec![
+            ("GHLLReal64Geo", |e| e.count_geo()),
+            ("GHLLReal64Har", |e| e.count_har()),
+        ]
+    }
+
+    fn new(prec: u8) -> Self {
+        GHLLReal::with_precision_and_rng(prec, builder(), &mut rng()).unwrap()
+    }
+
+    fn add(&mut self, value: &T) {
+        GHLLReal::add(self, value);
+    }
+
+    fn merge(&mut self, other: &Self) {
+        GHLLReal::merge(self, other).unwrap();
+    }
+}
+
+impl<T: Hash> Estimator<T> for GHLLReal<RandomState, Fixed<8>> {
+    const NAME: &'static str = "GHLLFixed8";
+    fn estimates() -> Vec<Estimate<Self>> {
+        vec![
+            ("GHLLFixed8Geo", |e| e.count_geo()),
+            ("GHLLFixed8Har", |e| e.count_har()),
+        ]
+    }
+
+    fn new(prec: u8) -> Self {
+        GHLLReal::with_precision_and_rng(prec, builder(), &mut rng()).unwrap()
+    }
+
+    fn add(&mut self, value: &T) {
+        GHLLReal::add(self, value);
+    }
+
+    fn merge(&mut self, other: &Self) {
+        GHLLReal::merge(self, other).unwrap();
+    }
+}
+
+impl<T: Hash> Estimator<T> for GHLLReal<RandomState, Fixed<12>> {
+    const NAME: &'static str = "GHLLFixed12";
+    fn estimates() -> Vec<Estimate<Self>> {
+        vec![
+            ("GHLLFixed12Geo", |e| e.count_geo()),
+            ("GHLLFixed12Har", |e| e.count_har()),
+        ]
+    }
+
+    fn new(prec: u8) -> Self {
+        GHLLReal::with_precision_and_rng(prec, builder(), &mut rng()).unwrap()
+    }
+
+    fn add(&mut self, value: &T) {
+        GHLLReal::add(self, value);
+    }
+
+    fn merge(&mut self, other: &Self) {
+        GHLLReal::merge(self, other).unwrap();
+    }
+}
 
-    reader.lines().map(|l| {
-        l.and_then(|l| l.trim().parse::<u64>()
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
-        )
-    }).collect()
+impl<T: Hash> Estimator<T> for GHLLReal<RandomState, Fixed<16>> {
+    const NAME: &'static str = "GHLLFixed16";
+    fn estimates() -> Vec<Estimate<Self>> {
+        vec![
+            ("GHLLFixed16Geo", |e| e.count_geo()),
+            ("GHLLFixed16Har", |e| e.count_har()),
+        ]
+    }
+
+    fn new(prec: u8) -> Self {
+        GHLLReal::with_precision_and_rng(prec, builder(), &mut rng()).unwrap()
+    }
+
+    fn add(&mut self, value: &T) {
+        GHLLReal::add(self, value);
+    }
+
+    fn merge(&mut self, other: &Self) {
+        GHLLReal::merge(self, other).unwrap();
+    }
+}
+
+impl<T: Hash> Estimator<T> for GHLLPlus<RandomState> {
+    const NAME: &'static str = "GHLLPlus";
+    fn estimates() -> Vec<Estimate<Self>> {
+        vec![("GHLLPlus", |e| e.count())]
+    }
+
+    fn new(prec: u8) -> Self {
+        GHLLPlus::with_precision_and_rng(prec, builder(), &mut rng()).unwrap()
+    }
+
+    fn add(&mut self, value: &T) {
+        GumbelSketch::add(self, value);
+    }
+
+    fn merge(&mut self, other: &Self) {
+        GumbelSketch::merge(self, other).unwrap();
+    }
+}
+
+impl<T: Hash> Estimator<T> for ULL<RandomState> {
+    const NAME: &'static str = "ULL";
+    fn estimates() -> Vec<Estimate<Self>> {
+        vec![("ULL", |e| e.count())]
+    }
+
+    fn new(prec: u8) -> Self {
+        ULL::with_precision(prec, builder()).unwrap()
+    }
+
+    fn add(&mut self, value: &T) {
+        ULL::add(self, value);
+    }
+
+    fn merge(&mut self, other: &Self) {
+        ULL::merge(self, other).unwrap();
+    }
+}
+
+impl<T: Hash> Estimator<T> for LogLogBeta<RandomState> {
+    const NAME: &'static str = "LogLogBeta";
+    fn estimates() -> Vec<Estimate<Self>> {
+        vec![("LogLogBeta", |e| e.count())]
+    }
+
+    fn new(prec: u8) -> Self {
+        LogLogBeta::with_precision(prec, builder()).unwrap()
+    }
+
+    fn add(&mut self, value: &T) {
+        LogLogBeta::add(self, value);
+    }
+
+    fn merge(&mut self, other: &Self) {
+        LogLogBeta::merge(self, other).unwrap();
+    }
+}
+
+impl<T: Hash> Estimator<T> for LinearCounting<RandomState> {
+    const NAME: &'static str = "LinearCounting";
+    fn estimates() -> Vec<Estimate<Self>> {
+        vec![("LinearCounting", |e| e.count())]
+    }
+
+    fn new(prec: u8) -> Self {
+        LinearCounting::with_precision(prec, builder()).unwrap()
+    }
+
+    fn add(&mut self, value: &T) {
+        LinearCounting::add(self, value);
+    }
+
+    fn merge(&mut self, other: &Self) {
+        LinearCounting::merge(self, other).unwrap();
+    }
+}
+
+impl<T: Hash> Estimator<T> for KMV<RandomState> {
+    const NAME: &'static str = "KMV";
+    fn estimates() -> Vec<Estimate<Self>> {
+        vec![("KMV", |e| e.count())]
+    }
+
+    fn new(prec: u8) -> Self {
+        KMV::with_precision(prec, builder()).unwrap()
+    }
+
+    fn add(&mut self, value: &T) {
+        KMV::add(self, value);
+    }
+
+    fn merge(&mut self, other: &Self) {
+        KMV::merge(self, other).unwrap();
+    }
+}
+
+impl<T: Hash + Eq + Clone> Estimator<T> for Exact<T, RandomState> {
+    const NAME: &'static str = "Exact";
+    fn estimates() -> Vec<Estimate<Self>> {
+        vec![("Exact", |e| e.count())]
+    }
+
+    fn new(_prec: u8) -> Self {
+        Exact::new(builder())
+    }
+
+    fn add(&mut self, value: &T) {
+        Exact::add(self, value);
+    }
+
+    fn merge(&mut self, other: &Self) {
+        Exact::merge(self, other);
+    }
+}
+
+fn fill<T, E: Estimator<T>>(prec: u8, data: &[T]) -> E {
+    let mut estimator = E::new(prec);
+    for d in data {
+        estimator.add(d);
+    }
+
+    estimator
 }
 
-pub fn bench_hll<T, M>(g: &mut BenchmarkGroup<M>, prec: u8, card: usize, data: &[T])
+/// Measures the creation of an estimator, including the allocation and initialisation of its registers
+pub fn bench_construction<T, E, M>(g: &mut BenchmarkGroup<M>, prec: u8)
 where
-    T: Hash,
+    E: Estimator<T>,
     M: Measurement,
 {
-    g.bench_with_input(BenchmarkId::new("HyperLogLog", format!("{}/{}/{}", prec, card, data.len())), data, |b, data| b.iter(|| {
-        let mut estimator = HyperLogLogPF::<T, _>::new(prec, RandomState::new()).unwrap();
-        for d in data {
-            estimator.insert(d);
-        }
-        let _estimate = estimator.count();
-    }));
+    g.bench_function(BenchmarkId::new(E::NAME, prec), |b| b.iter(|| E::new(prec)));
 }
 
-pub fn bench_ghll<T, M>(g: &mut BenchmarkGroup<M>, prec: u8, card: usize, data: &[T])
+/// Measures feeding the data to an already created estimator
+pub fn bench_insertion<T, E, M>(g: &mut BenchmarkGroup<M>, prec: u8, card: usize, data: &[T])
 where
-    T: Hash,
+    E: Estimator<T>,
     M: Measurement,
 {
-    g.bench_with_input(BenchmarkId::new("GumbelHyperLogLog", format!("{}/{}/{}", prec, card, data.len())), data, |b, data| b.iter(|| {
-        let mut estimator = GHLL::<_>::with_precision(prec, RandomState::new()).unwrap();
-        for d in data {
+    g.bench_with_input(BenchmarkId::new(E::NAME, format!("{}/{}/{}", prec, card, data.len())), data, |b, data| b.iter_batched_ref(
+        || E::new(prec),
+        |estimator| for d in data {
             estimator.add(d);
-        }
-        let _estimate = estimator.count_geo();
-    }));
+        },
+        BatchSize::LargeInput,
+    ));
 }
 
-pub fn bench_ghllplus<T, M>(g: &mut BenchmarkGroup<M>, prec: u8, card: usize, data: &[T])
+/// Measures acquiring each estimate from an estimator fed with the data
+pub fn bench_estimation<T, E, M>(g: &mut BenchmarkGroup<M>, prec: u8, card: usize, data: &[T])
 where
-    T: Hash,
+    E: Estimator<T>,
     M: Measurement,
 {
-    g.bench_with_input(BenchmarkId::new("GumbelHyperLogLog+", format!("{}/{}/{}", prec, card, data.len())), data, |b, data| b.iter(|| {
-        let mut estimator = GHLLPlus::<_>::with_precision(prec, RandomState::new()).unwrap();
-        for d in data {
-            estimator.add(d);
-        }
-        let _estimate = estimator.count();
-    }));
+    let mut estimator: E = fill(prec, data);
+    for (name, estimate) in E::estimates() {
+        g.bench_function(BenchmarkId::new(name, format!("{}/{}/{}", prec, card, data.len())), |b| b.iter(|| estimate(&mut estimator)));
+    }
+}
+
+/// Measures merging two estimators fed with the halves of the data
+pub fn bench_merge<T, E, M>(g: &mut BenchmarkGroup<M>, prec: u8, card: usize, data: &[T])
+where
+    E: Estimator<T>,
+    M: Measurement,
+{
+    let (first, second) = data.split_at(data.len() / 2);
+    let (first, second): (E, E) = (fill(prec, first), fill(prec, second));
+
+    g.bench_function(BenchmarkId::new(E::NAME, format!("{}/{}/{}", prec, card, data.len())), |b| b.iter_batched_ref(
+        || first.clone(),
+        |estimator| estimator.merge(&second),
+        BatchSize::LargeInput,
+    ));
+}
+
+/// Measures the whole estimation: creating an estimator, feeding it the data and acquiring each estimate
+pub fn bench_end_to_end<T, E, M>(g: &mut BenchmarkGroup<M>, prec: u8, card: usize, data: &[T])
+where
+    E: Estimator<T>,
+    M: Measurement,
+{
+    for (name, estimate) in E::estimates() {
+        g.bench_with_input(BenchmarkId::new(name, format!("{}/{}/{}", prec, card, data.len())), data, |b, data| b.iter(|| {
+            let mut estimator: E = fill(prec, data);
+            estimate(&mut estimator)
+        }));
+    }
 }
@@ -0,0 +1,302 @@
+use gen_data::Format;
+use gen_data::keys::{Key, KeyKind};
+use itertools::iproduct;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io;
+use std::io::Write;
+
+use crate::ALGORITHMS;
+use crate::constants::{CARDINALITIES, DATA_SIZE_MULTIPLIES, HASHES, PRECISIONS};
+use crate::dataset::Dataset;
+use crate::hashes::HashFunction;
+use crate::results::{Layout, Record, output_path, table_path};
+
+/// A single (algorithm, hash, precision, cardinality, size) configuration of the gathered results
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Configuration {
+    pub algorithm: String,
+    pub hash: String,
+    pub precision: u8,
+    pub cardinality: usize,
+    pub size: usize,
+}
+
+/// The results of a single configuration, ordered by iteration
+#[derive(Debug, Clone, Default)]
+pub struct Samples {
+    /// the iteration of each estimate
+    pub iterations: Vec<usize>,
+    pub estimates: Vec<f64>,
+    /// the elapsed times in seconds; empty for results without recorded times
+    pub elapsed: Vec<f64>,
+}
+
+/// The results of every configuration
+pub type Estimates = BTreeMap<Configuration, Samples>;
+
+/// Error statistics of a single algorithm on a single dataset configuration
+///
+/// All relative values are divided by the true cardinality, and the quantiles
+/// are those of the relative error `estimate / card - 1`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Summary {
+    pub algorithm: String,
+    pub hash: String,
+    pub precision: u8,
+    pub cardinality: usize,
+    pub size: usize,
+    pub iterations: usize,
+    pub mean: f64,
+    pub bias: f64,
+    pub relative_bias: f64,
+    pub rse: f64,
+    pub rmse: f64,
+    pub relative_rmse: f64,
+    pub mae: f64,
+    pub relative_mae: f64,
+    pub q05: f64,
+    pub q25: f64,
+    pub median: f64,
+    pub q75: f64,
+    pub q95: f64,
+    /// the number of elements fed to the estimator per second, including its construction
+    /// and the estimation; `NaN` if the times were not recorded
+    pub throughput: f64,
+}
+
+impl Summary {
+    const CSV_HEADER: &'static str = "algorithm,hash,precision,cardinality,size,iterations,mean,bias,relative_bias,rse,rmse,relative_rmse,mae,relative_mae,q05,q25,median,q75,q95,throughput";
+
+    /// Computes the statistics of the `samples` of a configuration against the true cardinality `card`
+    pub fn new(configuration: &Configuration, card: usize, samples: &Samples) -> Self {
+        let Configuration { algorithm, hash, precision, size, .. } = configuration.clone();
+        let estimates = &samples.estimates;
+        let n = estimates.len() as f64;
+        let truth = card as f64;
+
+        let mean = estimates.iter().sum::<f64>() / n;
+        let variance = estimates.iter()
+            .map(|e| (e - mean).powi(2))
+            .sum::<f64>() / (n - 1.0);
+        let mse = estimates.iter()
+            .map(|e| (e - truth).powi(2))
+            .sum::<f64>() / n;
+        let mae = estimates.iter()
+            .map(|e| (e - truth).abs())
+            .sum::<f64>() / n;
+
+        let mut errors: Vec<_> = estimates.iter()
+            .map(|e| e / truth - 1.0)
+            .collect();
+        errors.sort_by(f64::total_cmp);
+
+        let elapsed = samples.elapsed.iter().sum::<f64>() / samples.elapsed.len() as f64;
+
+        Self {
+            algorithm,
+            hash,
+            precision,
+            cardinality: card,
+            size,
+            iterations: estimates.len(),
+            mean,
+            bias: mean - truth,
+            relative_bias: mean / truth - 1.0,
+            rse: variance.sqrt() / truth,
+            rmse: mse.sqrt(),
+            relative_rmse: mse.sqrt() / truth,
+            mae,
+            relative_mae: mae / truth,
+            q05: quantile(&errors, 0.05),
+            q25: quantile(&errors, 0.25),
+            median: quantile(&errors, 0.5),
+            q75: quantile(&errors, 0.75),
+            q95: quantile(&errors, 0.95),
+            throughput: size as f64 / elapsed,
+        }
+    }
+
+    fn write_csv<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            self.algorithm, self.hash, self.precision, self.cardinality, self.size, self.iterations,
+            self.mean, self.bias, self.relative_bias, self.rse, self.rmse, self.relative_rmse,
+            self.mae, self.relative_mae, self.q05, self.q25, self.median, self.q75, self.q95,
+            self.throughput,
+        )
+    }
+}
+
+/// Computes the `q`-th quantile of sorted values, interpolating linearly between the closest
+/// order statistics (the default method of R's `quantile`)
+pub fn quantile(sorted: &[f64], q: f64) -> f64 {
+    if sorted.is_empty() {
+        return f64::NAN;
+    }
+
+    let position = q * (sorted.len() - 1) as f64;
+    let (lower, upper) = (position.floor() as usize, position.ceil() as usize);
+    let weight = position - lower as f64;
+
+    sorted[lower] + weight * (sorted[upper] - sorted[lower])
+}
+
+/// Reads the gathered estimates of the given key kind saved in the given layout
+pub fn read_results(layout: Layout, key: KeyKind) -> Result<Estimates, io::Error> {
+    match layout {
+        Layout::Table => read_table(&table_path(key)),
+        Layout::Files => read_files(key),
+    }
+}
+
+/// Reads the estimates saved in a results table
+///
+/// The columns are found by their names in the header, so that the tables written by earlier versions
+/// can still be read: those without the `hash` column were gathered with ahash, and those without
+/// the `elapsed` column give no times.
+pub fn read_table(path: &str) -> Result<Estimates, io::Error> {
+    let content = fs::read_to_string(path)
+        .map_err(|err| io::Error::new(err.kind(), format!("failed to read file {}", path)))?;
+
+    let mut lines = content.lines();
+    let header: Vec<_> = lines.next().unwrap_or_default().split(',').collect();
+    let column = |name: &str| header.iter().position(|&column| column == name);
+    let required = |name: &str| column(name).ok_or_else(|| io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("file {} has no column {}; expected a results table with the header {}", path, name, Record::CSV_HEADER),
+    ));
+    let algorithm = required("algorithm")?;
+    let precision = required("precision")?;
+    let cardinality = required("cardinality")?;
+    let size = required("size")?;
+    let iteration = required("iteration")?;
+    let estimate = required("estimate")?;
+    let (hash, elapsed) = (column("hash"), column("elapsed"));
+
+    let mut rows: BTreeMap<Configuration, Vec<(usize, f64, Option<f64>)>> = BTreeMap::new();
+    for line in lines {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("invalid row {} in file {}", line, path));
+        let fields: Vec<_> = line.split(',').collect();
+        if fields.len() != header.len() {
+            return Err(invalid());
+        }
+
+        let configuration = Configuration {
+            algorithm: fields[algorithm].to_owned(),
+            hash: hash.map_or(HashFunction::AHash.name(), |hash| fields[hash]).to_owned(),
+            precision: fields[precision].parse().map_err(|_| invalid())?,
+            cardinality: fields[cardinality].parse().map_err(|_| invalid())?,
+            size: fields[size].parse().map_err(|_| invalid())?,
+        };
+        let iteration = fields[iteration].parse().map_err(|_| invalid())?;
+        let estimate = fields[estimate].parse().map_err(|_| invalid())?;
+        let elapsed = elapsed.map(|elapsed| fields[elapsed].parse()).transpose().map_err(|_| invalid())?;
+
+        rows.entry(configuration).or_default().push((iteration, estimate, elapsed));
+    }
+
+    Ok(rows.into_iter().map(|(configuration, mut rows)| {
+        rows.sort_by_key(|&(iteration, _, _)| iteration);
+        let samples = Samples {
+            iterations: rows.iter().map(|&(iteration, _, _)| iteration).collect(),
+            estimates: rows.iter().map(|&(_, estimate, _)| estimate).collect(),
+            elapsed: rows.iter().filter_map(|&(_, _, elapsed)| elapsed).collect(),
+        };
+        (configuration, samples)
+    }).collect())
+}
+
+/// Reads the estimates saved in one file per configuration, skipping
+/// the configurations from constants.rs that have not been gathered
+pub fn read_files(key: KeyKind) -> Result<Estimates, io::Error> {
+    let data_sizes = iproduct!(CARDINALITIES, DATA_SIZE_MULTIPLIES).filter(|(card, mult)| card * mult <= 1_000_000_000);
+
+    let mut estimates = BTreeMap::new();
+    for ((card, mult), prec, hash, alg) in iproduct!(data_sizes, PRECISIONS, HASHES, ALGORITHMS) {
+        let path = output_path(alg, key, hash, prec, card, card * mult);
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(io::Error::new(e.kind(), format!("failed to read file {}", path))),
+        };
+
+        let values = content.lines()
+            .map(|l| l.trim().parse::<f64>()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            )
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let configuration = Configuration {
+            algorithm: alg.to_owned(),
+            hash: hash.name().to_owned(),
+            precision: prec,
+            cardinality: card,
+            size: card * mult,
+        };
+        estimates.insert(configuration, Samples { iterations: (0..values.len()).collect(), estimates: values, elapsed: Vec::new() });
+    }
+
+    Ok(estimates)
+}
+
+/// Counts the distinct elements of a dataset exactly
+pub fn exact_cardinality<K: Key>(card: usize, size: usize, format: Format) -> Result<usize, io::Error> {
+    let mut distinct = HashSet::with_capacity(card);
+    Dataset::<K>::open(card, size, format)?.for_each(|value| {
+        distinct.insert(value);
+    })?;
+
+    Ok(distinct.len())
+}
+
+pub fn write_csv(path: &str, summaries: &[Summary]) -> Result<(), io::Error> {
+    let mut out = io::BufWriter::new(fs::File::create(path)?);
+    writeln!(out, "{}", Summary::CSV_HEADER)?;
+    for summary in summaries {
+        summary.write_csv(&mut out)?;
+    }
+
+    out.flush()
+}
+
+pub fn write_json(path: &str, summaries: &[Summary]) -> Result<(), io::Error> {
+    let mut out = io::BufWriter::new(fs::File::create(path)?);
+    serde_json::to_writer_pretty(&mut out, summaries)?;
+
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(name: &str, content: &str) -> Result<Estimates, io::Error> {
+        let path = std::env::temp_dir().join(format!("comparison_{}_{}.csv", name, std::process::id()));
+        fs::write(&path, content).unwrap();
+        let estimates = read_table(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        estimates
+    }
+
+    #[test]
+    fn reads_tables_of_every_format() {
+        let current = read("current", &format!("{}\nHLL,xxh3,4,10,100,1,7,11.5,0.25,12\nHLL,xxh3,4,10,100,0,5,9.5,0.5,12\n", Record::CSV_HEADER)).unwrap();
+        let (configuration, samples) = current.into_iter().next().unwrap();
+        assert_eq!(configuration.hash, "xxh3");
+        assert_eq!((samples.iterations, samples.estimates, samples.elapsed), (vec![0, 1], vec![9.5, 11.5], vec![0.5, 0.25]));
+
+        // the tables written before the memory and hash columns were added
+        let old = read("old", "algorithm,precision,cardinality,size,iteration,seed,estimate,elapsed\nHLL,4,10,100,0,5,9.5,0.5\n").unwrap();
+        let (configuration, samples) = old.into_iter().next().unwrap();
+        assert_eq!((configuration.hash.as_str(), configuration.precision), ("ahash", 4));
+        assert_eq!((samples.estimates, samples.elapsed), (vec![9.5], vec![0.5]));
+    }
+
+    #[test]
+    fn rejects_tables_of_unknown_formats() {
+        let error = read("unknown", "algorithm,precision,cardinality,size\nHLL,4,10,100\n").unwrap_err();
+        assert!(error.to_string().contains("no column iteration"), "{}", error);
+        assert!(read("short", &format!("{}\nHLL,xxh3,4,10,100\n", Record::CSV_HEADER)).is_err());
+    }
+}
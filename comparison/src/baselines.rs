@@ -0,0 +1,326 @@
+use std::collections::{BTreeSet, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+
+/// The minimal accepted precision
+pub const MIN_PRECISION: u8 = 4;
+
+/// The maximal accepted precision
+pub const MAX_PRECISION: u8 = 18;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BaselineError {
+    InvalidPrecision,
+    IncompatibleMerge,
+}
+
+impl fmt::Display for BaselineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BaselineError::InvalidPrecision => {
+                write!(f, "precision is out of the range {}..={}", MIN_PRECISION, MAX_PRECISION)
+            }
+            BaselineError::IncompatibleMerge => write!(f, "cannot merge estimators of different precision"),
+        }
+    }
+}
+
+impl Error for BaselineError {}
+
+fn check_precision(precision: u8) -> Result<(), BaselineError> {
+    if (MIN_PRECISION..=MAX_PRECISION).contains(&precision) {
+        Ok(())
+    } else {
+        Err(BaselineError::InvalidPrecision)
+    }
+}
+
+/// The LogLog-Beta estimator: HyperLogLog registers with a single formula
+/// for all cardinalities, see: Qin, Jason et al. "LogLog-Beta and More: A New Algorithm
+/// for Cardinality Estimation Based on LogLog Counting." arXiv:1612.02284 (2016).
+#[derive(Clone)]
+pub struct LogLogBeta<B: BuildHasher> {
+    builder: B,
+    precision: u8,
+    registers: Vec<u8>,
+}
+
+impl<B: BuildHasher> LogLogBeta<B> {
+    // the coefficients of the bias correction for the precisions 4 to 18, in the form of the paper,
+    // which only gives those of precision 14. They are fitted by `cargo run --release --bin fit_loglog_beta`,
+    // minimising the mean squared bias of simulated estimates over cardinalities from 1 to `20 * 2^precision`
+    // on a logarithmic scale; the bias stays within 0.4% of the cardinality at every precision.
+    const BETA: [[f64; 8]; (MAX_PRECISION - MIN_PRECISION + 1) as usize] = [
+        [19.81833766, -15.05147624, -27.30667046, 20.24855551, -17.28019622, 6.006533609, -1.21101406, 0.08370960543], // precision 4
+        [0.2955183121, -0.2721734056, -0.684734356, 0.2298535761, -0.1305060999, -0.005994158044, 0.006846085923, -0.00143265088], // precision 5
+        [-3.719837977, 3.562163498, 1.992092789, 0.2816608991, 0.2302060444, 0.05790173798, -0.01644395807, 0.004232879958], // precision 6
+        [2.564514597, -1.562116586, -3.74340068, 1.273642426, -0.5718391067, -0.05527319641, 0.03106369333, -0.005543860542], // precision 7
+        [0.2137734147, -0.3008791637, 0.1302621033, -0.735002618, 0.4798433279, -0.1847714848, 0.03183926582, -0.002566133966], // precision 8
+        [-0.2245602413, -0.1645699541, 0.5365326225, -0.4158951811, 0.2090125697, -0.06039634596, 0.009436473973, -0.0006773031218], // precision 9
+        [-0.1520914879, 0.6300222703, -1.073767266, 0.8258389977, -0.2726891889, 0.02833969701, 0.002476275933, -0.000594193401], // precision 10
+        [-0.4231708874, -1.245937892, 2.695323341, -1.227189846, 0.2584501339, -0.007200998742, -0.002651546679, 0.0003499087188], // precision 11
+        [-0.3860091232, -3.905556872, 4.935046046, -1.951491767, 0.3500421259, -0.008027922309, -0.003358700292, 0.0003667399935], // precision 12
+        [-0.4013282201, 1.661072347, -0.470890034, -0.1438071155, 0.1418134143, -0.01068196565, -0.001779012807, 0.0003705600975], // precision 13
+        [-0.3760791796, -3.185551303, 2.084746414, -0.5938376155, 0.1491722497, -0.009245997538, -0.001104074006, 0.0002923957907], // precision 14
+        [-0.3731205403, 6.835952405, -6.43585266, 1.852923369, -0.06132355738, -0.01755924289, 1.946052088e-5, 0.0003388408506], // precision 15
+        [-0.3518208931, -3.312355362, -0.3518990351, 0.8681739034, -0.09797240985, -0.006820711716, 0.0009081040514, 0.0002244093847], // precision 16
+        [-0.3437373818, -16.03422294, 8.394830753, -0.2236364241, -0.1392327388, -0.001483518673, 0.001495253597, 0.000220134604], // precision 17
+        [-0.3382668217, -63.83024561, 34.29573661, -3.565922631, -0.1957690757, 0.01082868992, 0.002492818436, 0.0002143465682], // precision 18
+    ];
+
+    /// Creates a new estimator with `2^precision` registers; the accepted
+    /// precisions lie in the range {4, 5, ..., 18}
+    pub fn with_precision(precision: u8, builder: B) -> Result<Self, BaselineError> {
+        check_precision(precision)?;
+
+        Ok(Self {
+            builder,
+            precision,
+            registers: vec![0; 1 << precision],
+        })
+    }
+
+    pub fn add<H: Hash + ?Sized>(&mut self, value: &H) {
+        let hash = self.builder.hash_one(value);
+
+        // the first `precision` bits choose the register and the rest
+        // gives the position of the leftmost 1-bit
+        let index = (hash >> (64 - self.precision)) as usize;
+        let rest = hash << self.precision;
+        let rank = u8::min(rest.leading_zeros() as u8, 64 - self.precision) + 1;
+
+        self.registers[index] = u8::max(self.registers[index], rank);
+    }
+
+    /// Merges the `other` estimator with the same precision and hash builder into this one
+    pub fn merge(&mut self, other: &Self) -> Result<(), BaselineError> {
+        if self.precision != other.precision {
+            return Err(BaselineError::IncompatibleMerge);
+        }
+
+        for (register, &value) in self.registers.iter_mut().zip(&other.registers) {
+            *register = u8::max(*register, value);
+        }
+
+        Ok(())
+    }
+
+    /// The number of bytes taken by the registers
+    pub fn memory_bytes(&self) -> usize {
+        self.registers.len()
+    }
+
+    pub fn count(&self) -> f64 {
+        let m = self.registers.len() as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+
+        let zeros = self.registers.iter().filter(|&&r| r == 0).count() as f64;
+        let sum = self.registers.iter()
+            .map(|&r| f64::powi(2.0, -(r as i32)))
+            .sum::<f64>();
+
+        // the bias correction `beta_0 * zeros + sum(beta_i * ln(zeros + 1)^i)`
+        let zl = f64::ln(zeros + 1.0);
+        let coefficients = &Self::BETA[(self.precision - MIN_PRECISION) as usize];
+        let beta = coefficients[0] * zeros
+            + coefficients[1..].iter().rev().fold(0.0, |acc, b| acc * zl + b) * zl;
+
+        alpha * m * (m - zeros) / (beta + sum)
+    }
+}
+
+/// Linear counting over a bitmap of `2^precision` bits, see: Whang, Kyu-Young et al.
+/// "A linear-time probabilistic counting algorithm for database applications."
+/// ACM Transactions on Database Systems 15.2 (1990): 208-229.
+#[derive(Clone)]
+pub struct LinearCounting<B: BuildHasher> {
+    builder: B,
+    precision: u8,
+    bitmap: Vec<u64>,
+}
+
+impl<B: BuildHasher> LinearCounting<B> {
+    /// Creates a new estimator with a bitmap of `2^precision` bits; the accepted
+    /// precisions lie in the range {4, 5, ..., 18}
+    pub fn with_precision(precision: u8, builder: B) -> Result<Self, BaselineError> {
+        check_precision(precision)?;
+
+        Ok(Self {
+            builder,
+            precision,
+            bitmap: vec![0; usize::max((1 << precision) / 64, 1)],
+        })
+    }
+
+    pub fn add<H: Hash + ?Sized>(&mut self, value: &H) {
+        let index = (self.builder.hash_one(value) >> (64 - self.precision)) as usize;
+        self.bitmap[index / 64] |= 1 << (index % 64);
+    }
+
+    /// Merges the `other` estimator with the same precision and hash builder into this one
+    pub fn merge(&mut self, other: &Self) -> Result<(), BaselineError> {
+        if self.precision != other.precision {
+            return Err(BaselineError::IncompatibleMerge);
+        }
+
+        for (word, &other) in self.bitmap.iter_mut().zip(&other.bitmap) {
+            *word |= other;
+        }
+
+        Ok(())
+    }
+
+    /// The number of bytes taken by the bitmap
+    pub fn memory_bytes(&self) -> usize {
+        self.bitmap.len() * std::mem::size_of::<u64>()
+    }
+
+    pub fn count(&self) -> f64 {
+        let m = (1_usize << self.precision) as f64;
+        let ones = self.bitmap.iter().map(|word| word.count_ones()).sum::<u32>() as f64;
+
+        // a full bitmap carries no information beyond the cardinality being large;
+        // report the estimate for a single empty bit
+        let zeros = f64::max(m - ones, 1.0);
+
+        m * f64::ln(m / zeros)
+    }
+}
+
+/// The k minimum values (bottom-k) estimator keeping the `k = 2^precision` smallest hashes,
+/// see: Bar-Yossef, Ziv et al. "Counting distinct elements in a data stream."
+/// RANDOM 2002: 1-10.
+#[derive(Clone)]
+pub struct KMV<B: BuildHasher> {
+    builder: B,
+    k: usize,
+    minimums: BTreeSet<u64>,
+}
+
+impl<B: BuildHasher> KMV<B> {
+    /// Creates a new estimator keeping `2^precision` hashes; the accepted
+    /// precisions lie in the range {4, 5, ..., 18}
+    pub fn with_precision(precision: u8, builder: B) -> Result<Self, BaselineError> {
+        check_precision(precision)?;
+
+        Ok(Self {
+            builder,
+            k: 1 << precision,
+            minimums: BTreeSet::new(),
+        })
+    }
+
+    pub fn add<H: Hash + ?Sized>(&mut self, value: &H) {
+        let hash = self.builder.hash_one(value);
+
+        if self.minimums.len() < self.k {
+            self.minimums.insert(hash);
+        } else if hash < *self.minimums.last().unwrap() && self.minimums.insert(hash) {
+            self.minimums.pop_last();
+        }
+    }
+
+    /// Merges the `other` estimator with the same precision and hash builder into this one
+    pub fn merge(&mut self, other: &Self) -> Result<(), BaselineError> {
+        if self.k != other.k {
+            return Err(BaselineError::IncompatibleMerge);
+        }
+
+        // the `k` smallest hashes of the union are among the `k` smallest hashes of each set
+        self.minimums.extend(&other.minimums);
+        while self.minimums.len() > self.k {
+            self.minimums.pop_last();
+        }
+
+        Ok(())
+    }
+
+    /// The number of bytes taken by the `k` hashes of a full estimator
+    pub fn memory_bytes(&self) -> usize {
+        self.k * std::mem::size_of::<u64>()
+    }
+
+    pub fn count(&self) -> f64 {
+        // fewer than `k` distinct hashes are counted exactly
+        if self.minimums.len() < self.k {
+            return self.minimums.len() as f64;
+        }
+
+        // the unbiased estimator `(k - 1) / h_k` with `h_k` the k-th smallest hash in [0, 1)
+        let kth = *self.minimums.last().unwrap() as f64 / 2.0_f64.powi(64);
+
+        (self.k - 1) as f64 / kth
+    }
+}
+
+/// Exact counting of the distinct values in a hash set
+#[derive(Clone)]
+pub struct Exact<K, B: BuildHasher> {
+    values: HashSet<K, B>,
+}
+
+impl<K: Hash + Eq + Clone, B: BuildHasher> Exact<K, B> {
+    pub fn new(builder: B) -> Self {
+        Self {
+            values: HashSet::with_hasher(builder),
+        }
+    }
+
+    pub fn add(&mut self, value: &K) {
+        if !self.values.contains(value) {
+            self.values.insert(value.clone());
+        }
+    }
+
+    pub fn merge(&mut self, other: &Self) {
+        for value in &other.values {
+            self.add(value);
+        }
+    }
+
+    /// The number of bytes taken by the stored values, excluding their heap allocations
+    pub fn memory_bytes(&self) -> usize {
+        self.values.len() * std::mem::size_of::<K>()
+    }
+
+    pub fn count(&self) -> f64 {
+        self.values.len() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ahash::RandomState;
+
+    #[test]
+    fn loglog_beta_is_unbiased_for_small_cardinalities() {
+        for precision in MIN_PRECISION..=MAX_PRECISION {
+            let m = 1_usize << precision;
+            let mut checkpoints = vec![1, m / 8, m / 2, m, 2 * m];
+            checkpoints.dedup();
+
+            // enough estimators for a standard error of the mean of about 0.4%
+            let trials = usize::max(4, (1 << 16) / m);
+            let mut sums = vec![0.0; checkpoints.len()];
+            for trial in 0..trials {
+                let builder = RandomState::with_seeds(crate::splitmix64(trial as u64), crate::splitmix64(precision as u64), 3, 4);
+                let mut estimator = LogLogBeta::with_precision(precision, builder).unwrap();
+                let mut added = 0;
+                for (sum, &n) in sums.iter_mut().zip(&checkpoints) {
+                    for value in added..n as u64 {
+                        estimator.add(&value);
+                    }
+                    added = n as u64;
+                    *sum += estimator.count() / n as f64;
+                }
+            }
+
+            for (sum, n) in sums.iter().zip(&checkpoints) {
+                let bias = sum / trials as f64 - 1.0;
+                assert!(bias.abs() < 0.02, "bias {} at precision {} and cardinality {}", bias, precision, n);
+            }
+        }
+    }
+}
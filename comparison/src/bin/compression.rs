@@ -0,0 +1,86 @@
+use ahash::RandomState;
+use rand::{Rng, SeedableRng, thread_rng};
+use rand::rngs::StdRng;
+use rayon::prelude::*;
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+
+use comparison::constants::{CARDINALITIES, COMPRESSION_RUNS, PRECISIONS, SMALL_CARDINALITIES};
+use gumbel_estimation::{GumbelError, GumbelSketchBuilder};
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let run_seed: u64 = thread_rng().gen();
+
+    println!("Measuring compressed sizes with seed {}...", run_seed);
+
+    let checkpoints: Vec<usize> = SMALL_CARDINALITIES.iter().chain(CARDINALITIES.iter()).copied()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+
+    // the compressed sizes of every precision and run at every checkpoint
+    let units: Vec<_> = PRECISIONS.iter()
+        .flat_map(|&precision| (0..COMPRESSION_RUNS).map(move |run| (precision, run)))
+        .collect();
+    let sizes = units.par_iter()
+        .map(|&(precision, run)| compressed_sizes(precision, run_seed.wrapping_add(run as u64), &checkpoints))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut out = io::BufWriter::new(fs::File::create("../results/compression.csv")?);
+    writeln!(out, "precision,cardinality,runs,raw_bytes,compressed_bytes,ratio,bits_per_register")?;
+    for &precision in &PRECISIONS {
+        let runs: Vec<_> = units.iter()
+            .zip(&sizes)
+            .filter(|((p, _), _)| *p == precision)
+            .map(|(_, (raw, compressed))| (raw, compressed))
+            .collect();
+        let raw = *runs[0].0;
+
+        // the range of the mean size over the cardinalities
+        let (mut smallest, mut largest) = (f64::INFINITY, 0.0f64);
+        for (i, &cardinality) in checkpoints.iter().enumerate() {
+            let compressed = runs.iter().map(|(_, compressed)| compressed[i] as f64).sum::<f64>() / runs.len() as f64;
+            let bits = compressed * 8.0 / (1 << precision) as f64;
+            writeln!(out, "{},{},{},{},{},{},{}", precision, cardinality, runs.len(), raw, compressed, compressed / raw as f64, bits)?;
+
+            smallest = smallest.min(compressed);
+            largest = largest.max(compressed);
+        }
+
+        println!("- precision {}: {} raw bytes, {:.0} to {:.0} compressed bytes ({:.2} to {:.2} bits/register)",
+            precision, raw, smallest, largest,
+            smallest * 8.0 / (1 << precision) as f64, largest * 8.0 / (1 << precision) as f64);
+    }
+    out.flush()?;
+
+    println!("Measured {} cardinalities of {} precisions", checkpoints.len(), PRECISIONS.len());
+
+    Ok(())
+}
+
+// feeds distinct random keys to a GHLL estimator in a single pass, returning its raw size
+// and its compressed size at every checkpoint
+fn compressed_sizes(precision: u8, seed: u64, checkpoints: &[usize]) -> Result<(usize, Vec<usize>), GumbelError> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut estimator = GumbelSketchBuilder::new()
+        .precision(precision)
+        .hasher(RandomState::with_seeds(rng.gen(), rng.gen(), rng.gen(), rng.gen()))
+        .seed(rng.gen())
+        .exact_threshold(0)
+        .build_ghll()?;
+
+    let mut added = 0;
+    let mut sizes = Vec::with_capacity(checkpoints.len());
+    for &checkpoint in checkpoints {
+        // random 64-bit keys are distinct with overwhelming probability
+        for _ in added..checkpoint {
+            estimator.add(&rng.gen::<u64>());
+        }
+        added = checkpoint;
+        sizes.push(estimator.to_compressed_bytes().len());
+    }
+
+    Ok((estimator.to_bytes().len(), sizes))
+}
@@ -0,0 +1,254 @@
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use rayon::prelude::*;
+use std::env;
+use std::error::Error;
+
+use comparison::baselines::{MAX_PRECISION, MIN_PRECISION};
+
+// the simulated cardinalities reach `MAX_MULTIPLE * 2^precision`, where no register is left empty
+// and the bias correction vanishes; they are spread evenly on a logarithmic scale
+const MAX_MULTIPLE: usize = 20;
+const CHECKPOINTS: usize = 300;
+
+// the number of simulated estimators is `max(MIN_TRIALS, TRIAL_REGISTERS / 2^precision)`
+const MIN_TRIALS: usize = 64;
+const TRIAL_REGISTERS: usize = 1 << 20;
+
+// the maximal number of steps of the fit
+const MAX_STEPS: usize = 100;
+
+// the coefficients in the order of the terms `zeros, zl, zl^2, ..., zl^7` with `zl = ln(zeros + 1)`
+type Coefficients = [f64; 8];
+
+fn main() -> Result<(), Box<dyn Error>> {
+    // a single precision can be fitted with `--precision P`
+    let args: Vec<_> = env::args().collect();
+    let precisions: Vec<u8> = match args.iter().position(|arg| arg == "--precision") {
+        Some(i) => vec![args.get(i + 1).ok_or("missing value of --precision")?.parse()?],
+        None => (MIN_PRECISION..=MAX_PRECISION).collect(),
+    };
+    if let Some(precision) = precisions.iter().find(|p| !(MIN_PRECISION..=MAX_PRECISION).contains(p)) {
+        return Err(format!("precision {} is out of the range {}..={}", precision, MIN_PRECISION, MAX_PRECISION).into());
+    }
+
+    println!("Fitting the LogLog-Beta bias correction...");
+
+    let fits: Vec<_> = precisions.par_iter().map(|&precision| fit(precision)).collect();
+
+    // the rows of `LogLogBeta::BETA`, rounded to ten significant digits
+    for (precision, (coefficients, _)) in precisions.iter().zip(&fits) {
+        let row: Vec<_> = coefficients.iter()
+            .map(|c| format!("{:?}", format!("{:.9e}", c).parse::<f64>().unwrap()))
+            .collect();
+        println!("        [{}], // precision {}", row.join(", "), precision);
+    }
+
+    println!("Mean squared bias over the cardinalities:");
+    for (precision, (_, objective)) in precisions.iter().zip(&fits) {
+        println!("- precision {}: {:.1e}", precision, objective);
+    }
+
+    Ok(())
+}
+
+// the registers of simulated estimators at the checkpoints
+struct Simulation {
+    m: f64,
+    alpha: f64,
+    cardinalities: Vec<f64>,
+    // the share of the logarithmic scale around each checkpoint
+    weights: Vec<f64>,
+    // the number of empty registers and the sum of `2^-register` of every estimator at every checkpoint
+    samples: Vec<Vec<(f64, f64)>>,
+}
+
+impl Simulation {
+    fn run(precision: u8) -> Self {
+        let m = 1_usize << precision;
+        let max = MAX_MULTIPLE * m;
+
+        let mut checkpoints: Vec<_> = (0..CHECKPOINTS)
+            .map(|i| f64::powf(max as f64, i as f64 / (CHECKPOINTS - 1) as f64).round() as usize)
+            .collect();
+        checkpoints.dedup();
+        let weights = (0..checkpoints.len()).map(|i| {
+            let lower = checkpoints[i.saturating_sub(1)] as f64;
+            let upper = checkpoints[usize::min(i + 1, checkpoints.len() - 1)] as f64;
+            f64::ln(upper / lower) / 2.0
+        }).collect();
+
+        // random hashes stand in for the hashed elements, as in `LogLogBeta::add`
+        let trials = usize::max(MIN_TRIALS, TRIAL_REGISTERS / m);
+        let mut samples = vec![Vec::with_capacity(trials); checkpoints.len()];
+        let mut rng = StdRng::seed_from_u64(precision as u64);
+        for _ in 0..trials {
+            let mut registers = vec![0_u8; m];
+            let (mut zeros, mut sum) = (m as f64, m as f64);
+            let mut next = 0;
+
+            for n in 1..=max {
+                let hash: u64 = rng.gen();
+                let index = (hash >> (64 - precision)) as usize;
+                let rank = u8::min((hash << precision).leading_zeros() as u8, 64 - precision) + 1;
+
+                let old = registers[index];
+                if rank > old {
+                    if old == 0 {
+                        zeros -= 1.0;
+                    }
+                    sum += f64::powi(2.0, -(rank as i32)) - f64::powi(2.0, -(old as i32));
+                    registers[index] = rank;
+                }
+
+                if n == checkpoints[next] {
+                    samples[next].push((zeros, sum));
+                    next += 1;
+                    if next == checkpoints.len() {
+                        break;
+                    }
+                }
+            }
+        }
+
+        let m = m as f64;
+        Self {
+            m,
+            alpha: 0.7213 / (1.0 + 1.079 / m),
+            cardinalities: checkpoints.into_iter().map(|n| n as f64).collect(),
+            weights,
+            samples,
+        }
+    }
+
+    // the terms of the bias correction, scaled to similar magnitudes to keep the fit well-conditioned
+    fn terms(&self, zeros: f64) -> Coefficients {
+        let zl = f64::ln(zeros + 1.0) / f64::ln(self.m + 1.0);
+        let mut terms = [zeros / self.m; 8];
+        let mut power = 1.0;
+        for term in &mut terms[1..] {
+            power *= zl;
+            *term = power;
+        }
+        terms
+    }
+
+    // the mean relative estimate at every checkpoint with its gradient in the scaled coefficients,
+    // or `None` if some estimate is not positive
+    fn bias(&self, scaled: &Coefficients) -> Option<Vec<(f64, Coefficients)>> {
+        self.samples.iter().zip(&self.cardinalities).map(|(samples, &n)| {
+            let mut mean = 0.0;
+            let mut gradient = [0.0; 8];
+            for &(zeros, sum) in samples {
+                let terms = self.terms(zeros);
+                let denominator = scaled.iter().zip(&terms).map(|(c, t)| c * t).sum::<f64>() + sum;
+                if denominator <= 0.0 {
+                    return None;
+                }
+
+                let estimate = self.alpha * self.m * (self.m - zeros) / (n * denominator);
+                mean += estimate;
+                for (g, t) in gradient.iter_mut().zip(&terms) {
+                    *g -= estimate / denominator * t;
+                }
+            }
+
+            let count = samples.len() as f64;
+            Some((mean / count - 1.0, gradient.map(|g| g / count)))
+        }).collect()
+    }
+
+    fn objective(&self, bias: &[(f64, Coefficients)]) -> f64 {
+        bias.iter().zip(&self.weights).map(|((b, _), w)| w * b * b).sum::<f64>() / self.weights.iter().sum::<f64>()
+    }
+}
+
+// fits the coefficients minimising the mean squared bias of the estimates over the logarithmic scale of
+// cardinalities by the Levenberg-Marquardt method; returns them with the reached mean squared bias
+fn fit(precision: u8) -> (Coefficients, f64) {
+    let simulation = Simulation::run(precision);
+
+    let mut scaled = [0.0; 8];
+    let mut bias = simulation.bias(&scaled).expect("the estimates without a correction are positive");
+    let mut objective = simulation.objective(&bias);
+    let mut damping = 1e-3;
+
+    for _ in 0..MAX_STEPS {
+        if damping >= 1e9 {
+            break;
+        }
+
+        // the normal equations of the linearised bias
+        let mut matrix = [[0.0; 8]; 8];
+        let mut vector = [0.0; 8];
+        for ((b, gradient), w) in bias.iter().zip(&simulation.weights) {
+            for i in 0..8 {
+                vector[i] -= w * gradient[i] * b;
+                for j in 0..8 {
+                    matrix[i][j] += w * gradient[i] * gradient[j];
+                }
+            }
+        }
+
+        // increase the damping until a step lowers the objective
+        loop {
+            let mut damped = matrix;
+            for (i, row) in damped.iter_mut().enumerate() {
+                row[i] += damping * matrix[i][i] + 1e-12;
+            }
+            let step = solve(damped, vector);
+            let candidate: Coefficients = std::array::from_fn(|i| scaled[i] + step[i]);
+
+            match simulation.bias(&candidate) {
+                Some(candidate_bias) if simulation.objective(&candidate_bias) < objective => {
+                    scaled = candidate;
+                    objective = simulation.objective(&candidate_bias);
+                    bias = candidate_bias;
+                    damping = f64::max(damping / 10.0, 1e-9);
+                    break;
+                }
+                _ => {
+                    damping *= 10.0;
+                    if damping >= 1e9 {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    // undo the scaling of the terms
+    let zl_scale = f64::ln(simulation.m + 1.0);
+    let mut coefficients = scaled;
+    coefficients[0] /= simulation.m;
+    for (i, c) in coefficients.iter_mut().enumerate().skip(1) {
+        *c /= zl_scale.powi(i as i32);
+    }
+
+    (coefficients, objective)
+}
+
+// solves the linear system by Gaussian elimination with partial pivoting
+fn solve(mut matrix: [[f64; 8]; 8], mut vector: [f64; 8]) -> [f64; 8] {
+    for i in 0..8 {
+        let pivot = (i..8).max_by(|&a, &b| matrix[a][i].abs().total_cmp(&matrix[b][i].abs())).unwrap();
+        matrix.swap(i, pivot);
+        vector.swap(i, pivot);
+
+        let (pivot_row, pivot_value) = (matrix[i], vector[i]);
+        for (row, value) in matrix.iter_mut().zip(&mut vector).skip(i + 1) {
+            let factor = row[i] / pivot_row[i];
+            for (entry, pivot_entry) in row.iter_mut().zip(&pivot_row).skip(i) {
+                *entry -= factor * pivot_entry;
+            }
+            *value -= factor * pivot_value;
+        }
+    }
+
+    let mut solution = [0.0; 8];
+    for i in (0..8).rev() {
+        let known = (i + 1..8).map(|j| matrix[i][j] * solution[j]).sum::<f64>();
+        solution[i] = (vector[i] - known) / matrix[i][i];
+    }
+    solution
+}
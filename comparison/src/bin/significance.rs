@@ -0,0 +1,41 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::error::Error;
+
+use comparison::analysis::read_results;
+use comparison::constants::{KEY, OUTPUT_LAYOUT};
+use comparison::significance::{test_all, write_csv};
+
+fn main() -> Result<(), Box<dyn Error>> {
+    // the number of bootstrap resamples can be set with `--resamples N`
+    let args: Vec<_> = env::args().collect();
+    let resamples = match args.iter().position(|arg| arg == "--resamples") {
+        Some(i) => args.get(i + 1).ok_or("missing value of --resamples")?.parse()?,
+        None => 1000,
+    };
+
+    println!("Testing the differences between algorithms...");
+
+    let estimates = read_results(OUTPUT_LAYOUT, KEY)?;
+    let tests = test_all(&estimates, resamples, 0.95, 0);
+
+    write_csv("../results/significance.csv", &tests)?;
+
+    // count the configurations won by each algorithm of every pair
+    let mut tally: BTreeMap<_, [usize; 3]> = BTreeMap::new();
+    for test in &tests {
+        let counts = tally.entry((test.first.as_str(), test.second.as_str())).or_default();
+        match test.winner.as_deref() {
+            Some(winner) if winner == test.first => counts[0] += 1,
+            Some(_) => counts[1] += 1,
+            None => counts[2] += 1,
+        }
+    }
+
+    println!("Configurations with a lower RSE at the 95% confidence level:");
+    for ((first, second), [first_wins, second_wins, ties]) in tally {
+        println!("- {} vs {}: {} - {}, {} ties", first, second, first_wins, second_wins, ties);
+    }
+
+    Ok(())
+}
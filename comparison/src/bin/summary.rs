@@ -0,0 +1,72 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::env;
+use std::error::Error;
+
+use comparison::analysis::{Summary, exact_cardinality, read_results, write_csv, write_json};
+use comparison::constants::{DATA_FORMAT, KEY, OUTPUT_LAYOUT};
+use gen_data::keys::{Email, KeyKind, Url, Uuid};
+
+fn main() -> Result<(), Box<dyn Error>> {
+    // with `--exact` the true cardinality is counted from the dataset
+    // instead of being taken from its specification
+    let exact = env::args().any(|arg| arg == "--exact");
+
+    println!("Summarising results...");
+
+    let estimates = read_results(OUTPUT_LAYOUT, KEY)?;
+
+    // the true cardinalities of the datasets, counted at most once per dataset
+    let mut truths = HashMap::new();
+
+    let mut summaries = Vec::new();
+    for (configuration, samples) in &estimates {
+        let (card, size) = (configuration.cardinality, configuration.size);
+
+        let truth = match truths.get(&(card, size)) {
+            Some(&truth) => truth,
+            None => {
+                let truth = if exact {
+                    match KEY {
+                        KeyKind::U64 => exact_cardinality::<u64>(card, size, DATA_FORMAT)?,
+                        KeyKind::Uuid => exact_cardinality::<Uuid>(card, size, DATA_FORMAT)?,
+                        KeyKind::Url => exact_cardinality::<Url>(card, size, DATA_FORMAT)?,
+                        KeyKind::Email => exact_cardinality::<Email>(card, size, DATA_FORMAT)?,
+                        KeyKind::Tuple => exact_cardinality::<(u32, u64)>(card, size, DATA_FORMAT)?,
+                    }
+                } else {
+                    card
+                };
+                if truth != card {
+                    eprintln!("dataset card={} size={} contains {} distinct elements", card, size, truth);
+                }
+                truths.insert((card, size), truth);
+
+                truth
+            }
+        };
+
+        summaries.push(Summary::new(configuration, truth, samples));
+    }
+
+    write_csv("../results/summary.csv", &summaries)?;
+    write_json("../results/summary.json", &summaries)?;
+
+    println!("Summarised {} configurations", summaries.len());
+
+    // compare the hash functions if the results were gathered with more than one
+    let mut by_hash: BTreeMap<_, Vec<&Summary>> = BTreeMap::new();
+    for summary in &summaries {
+        by_hash.entry((summary.algorithm.as_str(), summary.hash.as_str())).or_default().push(summary);
+    }
+    if by_hash.keys().map(|(_, hash)| hash).collect::<BTreeSet<_>>().len() > 1 {
+        println!("Mean relative RMSE and throughput per hash function:");
+        for ((algorithm, hash), summaries) in by_hash {
+            let n = summaries.len() as f64;
+            let rmse = summaries.iter().map(|s| s.relative_rmse).sum::<f64>() / n;
+            let throughput = summaries.iter().map(|s| s.throughput).sum::<f64>() / n;
+            println!("- {} with {}: {:.4}, {:.3e} elements/s", algorithm, hash, rmse, throughput);
+        }
+    }
+
+    Ok(())
+}
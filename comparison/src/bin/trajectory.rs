@@ -0,0 +1,75 @@
+use rand::{Rng, thread_rng};
+use rayon::ThreadPoolBuilder;
+use rayon::prelude::*;
+use std::env;
+use std::error::Error;
+use std::panic;
+use std::process;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use comparison::{Sizing, plan_budget_units, plan_units};
+use comparison::constants::{CARDINALITIES, DATA_SIZE_MULTIPLIES, GROUPS, HASHES, KEY, MEMORY_BUDGETS, PRECISIONS, SIZING, TRAJECTORY_CHECKPOINTS, WORKERS};
+use comparison::error::HarnessError;
+use comparison::trajectory::{Checkpoints, TrajectoryWriter, trajectory_key};
+
+fn main() -> Result<(), Box<dyn Error>> {
+    // the seed of the run can be set with `--seed N`; with the seed of a comparison run,
+    // the final estimates of the trajectories are the estimates of that run
+    let args: Vec<_> = env::args().collect();
+    let run_seed = match args.iter().position(|arg| arg == "--seed") {
+        Some(i) => args.get(i + 1).ok_or("missing value of --seed")?.parse()?,
+        None => thread_rng().gen(),
+    };
+
+    // the checkpoints can be set with `--checkpoints elements:N` or `--checkpoints distinct:N`
+    let checkpoints = match args.iter().position(|arg| arg == "--checkpoints") {
+        Some(i) => Checkpoints::from_arg(args.get(i + 1).ok_or("missing value of --checkpoints")?)?,
+        None => TRAJECTORY_CHECKPOINTS,
+    };
+    if checkpoints.interval() == 0 {
+        return Err("the interval of TRAJECTORY_CHECKPOINTS must be positive".into());
+    }
+
+    println!("Gathering trajectories with seed {}...", run_seed);
+
+    let mut units = match SIZING {
+        Sizing::Precision => plan_units(&GROUPS, &HASHES, &PRECISIONS, &CARDINALITIES, &DATA_SIZE_MULTIPLIES),
+        Sizing::Memory => plan_budget_units(&GROUPS, &HASHES, &MEMORY_BUDGETS, &CARDINALITIES, &DATA_SIZE_MULTIPLIES),
+    };
+    units.sort_by_key(|unit| std::cmp::Reverse(unit.size));
+
+    let out = TrajectoryWriter::create(KEY)?;
+    let completed = AtomicUsize::new(0);
+    let failed = Mutex::new(Vec::new());
+
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(WORKERS)
+        .thread_name(|i| format!("Worker {}", i))
+        .build()?;
+
+    pool.install(|| units.par_iter().with_max_len(1).for_each(|unit| {
+        let result = panic::catch_unwind(|| trajectory_key(KEY, unit, run_seed, checkpoints))
+            .unwrap_or(Err(HarnessError::Panicked))
+            .and_then(|records| Ok(out.write(&records)?));
+
+        match result {
+            Ok(()) => {
+                let completed = completed.fetch_add(1, Ordering::Relaxed) + 1;
+                println!("Completed {}/{} units", completed, units.len());
+            }
+            Err(e) => failed.lock().unwrap().push((*unit, e)),
+        }
+    }));
+
+    let failed = failed.into_inner().unwrap();
+    if !failed.is_empty() {
+        eprintln!("{} units failed:", failed.len());
+        for (unit, e) in failed {
+            eprintln!("- {} hash={} prec={} card={} size={}: {}", unit.group.name(), unit.hash.name(), unit.precision, unit.cardinality, unit.size, e);
+        }
+        process::exit(1);
+    }
+
+    Ok(())
+}
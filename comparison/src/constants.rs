@@ -1,5 +1,13 @@
 // this file can be edited to change the dataset sizes used for benchmarks and comparisons
 
+use gen_data::Format;
+use gen_data::keys::KeyKind;
+
+use crate::{Design, Group, Sizing};
+use crate::hashes::HashFunction;
+use crate::results::Layout;
+use crate::trajectory::Checkpoints;
+
 // create a const array from a start value and step
 const fn array_from_range<const K: usize>(begin: usize, step: usize) -> [usize; K] {
     // create the array
@@ -17,18 +25,65 @@ const fn array_from_range<const K: usize>(begin: usize, step: usize) -> [usize;
     result   
 }
 
+// the cardinalities and dataset size multiplies of the small datasets,
+// which are also used by the tests of the exact mode of GHLL+
+pub const SMALL_CARDINALITIES: [usize; 2000] = array_from_range(1, 1);
+pub const SMALL_DATA_SIZE_MULTIPLIES: [usize; 1] = [10];
+
 // cardinalities of the underlying multisets
 pub const CARDINALITIES: [usize; 80] = array_from_range(10_000, 10_000); // large datasets
-//pub const CARDINALITIES: [usize; 2000] = array_from_range(1, 1); // small datasets
+//pub const CARDINALITIES: [usize; 2000] = SMALL_CARDINALITIES; // small datasets
 
 // dataset size multiplies; the size of the dataset
 // is calculated as `cardinality * data_size_multiply`
 pub const DATA_SIZE_MULTIPLIES: [usize; 1] = [100]; // large datasets
-//pub const DATA_SIZE_MULTIPLIES: [usize; 1] = [10]; // small datasets
+//pub const DATA_SIZE_MULTIPLIES: [usize; 1] = SMALL_DATA_SIZE_MULTIPLIES; // small datasets
 
 // precisions to use for the HyperLogLog and Gumbel estimators;
 // the number of registers used is equal to `2^precision`
 pub const PRECISIONS: [u8; 4] = [4, 8, 12, 16];
 
+// the way of sizing the estimators; `Sizing::Precision` uses the `PRECISIONS` for all estimators
+// and `Sizing::Memory` picks for each estimator the largest precision fitting the `MEMORY_BUDGETS`
+pub const SIZING: Sizing = Sizing::Precision;
+
+// the budgets in bytes for the state of each estimator, used with `Sizing::Memory`
+pub const MEMORY_BUDGETS: [usize; 4] = [256, 1024, 4096, 16384];
+
+// the number of worker threads gathering the results; `0` uses all available cores
+pub const WORKERS: usize = 0;
+
+// the estimators to compare; each group computes the estimates of one or more algorithms
+pub const GROUPS: [Group; 14] = Group::ALL;
+
+// the hash functions used by the estimators; comparing several of them, e.g. `HashFunction::ALL`,
+// measures the sensitivity of the accuracy and the throughput of the estimators to the hash quality
+pub const HASHES: [HashFunction; 1] = [HashFunction::AHash];
+
+// the design of the experiments; with `Design::Paired` all groups share the hash function and the
+// initial Gumbel register values within an iteration, and with `Design::Independent` they do not
+pub const DESIGN: Design = Design::Paired;
+
 // the number of iterations per single dataset
 pub const ITERATIONS: usize = 100;
+
+// the format of the dataset files; `Format::Text` reads `data_{card}_{size}.txt` files
+// and `Format::Binary` memory-maps `data_{card}_{size}.bin` files written by `gen_data`
+pub const DATA_FORMAT: Format = Format::Text;
+
+// the kind of elements in the datasets; `KeyKind::U64` reads the `data_{card}_{size}` files
+// and any other kind reads the `data_{key}_{card}_{size}` files
+pub const KEY: KeyKind = KeyKind::U64;
+
+// the layout of the results; `Layout::Table` writes a single `results.csv` table with one row
+// per estimate and `Layout::Files` writes one file per configuration as in the original layout
+pub const OUTPUT_LAYOUT: Layout = Layout::Table;
+
+// the checkpoints of the trajectory program, at which the estimates are snapshotted within a single pass
+// over each dataset; `Checkpoints::Distinct(n)` snapshots whenever the number of distinct elements seen
+// reaches a multiple of `n` and `Checkpoints::Elements(n)` after every `n` elements
+pub const TRAJECTORY_CHECKPOINTS: Checkpoints = Checkpoints::Distinct(1_000);
+
+// the number of runs of the compression program, each feeding different random keys to the estimators
+// of every precision and measuring their compressed size at the `SMALL_CARDINALITIES` and `CARDINALITIES`
+pub const COMPRESSION_RUNS: usize = 20;
@@ -0,0 +1,185 @@
+use gen_data::{Format, dataset_name};
+use gen_data::binary::Header;
+use gen_data::keys::Key;
+use memmap2::Mmap;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader};
+use std::marker::PhantomData;
+
+/// A dataset of `K` elements opened for reading in either of the supported formats
+pub struct Dataset<K> {
+    source: Source,
+    size: usize,
+    _key: PhantomData<K>,
+}
+
+enum Source {
+    Text(BufReader<File>),
+    Binary(Mmap),
+}
+
+pub fn dataset_path<K: Key>(card: usize, size: usize, format: Format) -> String {
+    format!("../data/{}.{}", dataset_name(K::KIND, card, size), format.extension())
+}
+
+impl<K: Key> Dataset<K> {
+    pub fn open(card: usize, size: usize, format: Format) -> Result<Self, io::Error> {
+        Self::open_path(&dataset_path::<K>(card, size, format), card, size, format)
+    }
+
+    /// Opens the dataset at `inpath` like `open`, which reads it from the data directory
+    pub fn open_path(inpath: &str, card: usize, size: usize, format: Format) -> Result<Self, io::Error> {
+        let input = File::open(inpath).map_err(|err| {
+            io::Error::new(err.kind(), format!("failed to open file {}", inpath))
+        })?;
+
+        let source = match format {
+            Format::Text => Source::Text(BufReader::new(input)),
+            Format::Binary => {
+                // SAFETY: dataset files are written once by `gen_data` and are not
+                // modified while the benchmarks read them
+                let data = unsafe { Mmap::map(&input)? };
+
+                // make sure the file describes the requested dataset
+                let header = Header::read_from(&data)?;
+                if header.card != card as u64 || header.size != size as u64 || header.key != K::KIND {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+                        "file {} describes a dataset of {} keys with card={} size={}",
+                        inpath, header.key.name(), header.card, header.size
+                    )));
+                }
+
+                // keys of a fixed length determine the length of the file
+                if let Some(key_size) = K::BINARY_SIZE {
+                    if data.len() != Header::SIZE + key_size * size {
+                        return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+                            "file {} has {} bytes, but a dataset of {} {} keys takes {}",
+                            inpath, data.len(), size, K::KIND.name(), Header::SIZE + key_size * size
+                        )));
+                    }
+                }
+
+                Source::Binary(data)
+            }
+        };
+
+        Ok(Self {
+            source,
+            size,
+            _key: PhantomData,
+        })
+    }
+
+    /// Feeds every element of the dataset to `f`, in file order
+    pub fn for_each<F: FnMut(K)>(self, mut f: F) -> Result<(), io::Error> {
+        match self.source {
+            Source::Text(reader) => {
+                for line in reader.lines() {
+                    f(K::parse_text(&line?)?);
+                }
+            }
+            Source::Binary(data) => {
+                let mut elements = &data[Header::SIZE..];
+                let mut count = 0;
+                while !elements.is_empty() {
+                    let (value, len) = K::read_binary(elements)?;
+                    elements = &elements[len..];
+                    f(value);
+                    count += 1;
+                }
+
+                // the keys of varying length are only counted once read
+                if count != self.size {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+                        "the dataset holds {} keys instead of {}", count, self.size
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads the whole dataset into memory
+    pub fn load(self) -> Result<Vec<K>, io::Error> {
+        let mut data = Vec::new();
+        self.for_each(|value| data.push(value))?;
+
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gen_data::binary::{Distribution, MAGIC};
+    use gen_data::generate;
+    use gen_data::keys::{KeyKind, Url};
+    use std::collections::HashSet;
+    use std::fs;
+
+    const CARD: usize = 100;
+    const SIZE: usize = 1000;
+
+    // writes a dataset with `gen_data` to a temporary file, returning its path and bytes
+    fn write<K: Key>(name: &str, format: Format) -> (String, Vec<u8>) {
+        let mut bytes = Vec::new();
+        generate::<K, _>(&mut bytes, CARD, SIZE, 42, format).unwrap();
+        let path = std::env::temp_dir().join(format!("comparison_{}_{}.{}", name, std::process::id(), format.extension()));
+        fs::write(&path, &bytes).unwrap();
+
+        (path.to_str().unwrap().to_owned(), bytes)
+    }
+
+    fn load<K: Key>(path: &str, format: Format) -> Result<Vec<K>, io::Error> {
+        let data = Dataset::<K>::open_path(path, CARD, SIZE, format).and_then(Dataset::load);
+        fs::remove_file(path).unwrap();
+        data
+    }
+
+    #[test]
+    fn binary_datasets_round_trip() {
+        let (path, bytes) = write::<u64>("u64", Format::Binary);
+        let header = Header::read_from(&bytes).unwrap();
+        assert_eq!(header, Header { card: CARD as u64, size: SIZE as u64, seed: 42, distribution: Distribution::Uniform, key: KeyKind::U64 });
+
+        // the binary dataset holds the elements of the text one generated with the same seed
+        let binary = load::<u64>(&path, Format::Binary).unwrap();
+        let (path, _) = write::<u64>("u64", Format::Text);
+        assert_eq!(binary, load::<u64>(&path, Format::Text).unwrap());
+        assert_eq!(binary.iter().collect::<HashSet<_>>().len(), CARD);
+
+        // keys of varying length
+        let (path, bytes) = write::<Url>("url", Format::Binary);
+        assert_eq!(Header::read_from(&bytes).unwrap().key, KeyKind::Url);
+        let binary = load::<Url>(&path, Format::Binary).unwrap();
+        let (path, _) = write::<Url>("url", Format::Text);
+        assert_eq!(binary, load::<Url>(&path, Format::Text).unwrap());
+    }
+
+    #[test]
+    fn corrupt_binary_datasets_are_rejected() {
+        let (path, mut bytes) = write::<u64>("u64_truncated", Format::Binary);
+        bytes.truncate(bytes.len() - 4);
+        fs::write(&path, &bytes).unwrap();
+        assert_eq!(load::<u64>(&path, Format::Binary).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        let (path, mut bytes) = write::<u64>("u64_magic", Format::Binary);
+        bytes[0..8].copy_from_slice(b"NOTDATA\0");
+        assert_ne!(bytes[0..8], MAGIC);
+        fs::write(&path, &bytes).unwrap();
+        let error = load::<u64>(&path, Format::Binary).unwrap_err();
+        assert!(error.to_string().contains("not a binary dataset"), "{}", error);
+
+        // the length of varying keys is only checked while reading them
+        let (path, mut bytes) = write::<Url>("url_truncated", Format::Binary);
+        bytes.truncate(bytes.len() - 4);
+        fs::write(&path, &bytes).unwrap();
+        assert_eq!(load::<Url>(&path, Format::Binary).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+
+        // a file of other keys
+        let (path, _) = write::<Url>("url_as_u64", Format::Binary);
+        assert!(load::<u64>(&path, Format::Binary).is_err());
+    }
+}
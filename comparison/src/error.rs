@@ -0,0 +1,71 @@
+use gumbel_estimation::GumbelError;
+use hyperloglogplus::HyperLogLogError;
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use crate::baselines::BaselineError;
+
+/// An error raised while gathering the results of a unit
+#[derive(Debug)]
+pub enum HarnessError {
+    /// reading the dataset or writing the results failed
+    Io(io::Error),
+    /// a Gumbel estimator or UltraLogLog failed, e.g. for a precision out of range, or the
+    /// registers of a Gumbel estimator saturated, so that its estimates are only lower bounds
+    Gumbel(GumbelError),
+    /// a baseline estimator failed
+    Baseline(BaselineError),
+    /// an estimator of the `hyperloglogplus` crate failed
+    HyperLogLog(HyperLogLogError),
+    /// the unit panicked
+    Panicked,
+}
+
+impl fmt::Display for HarnessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HarnessError::Io(e) => write!(f, "{}", e),
+            HarnessError::Gumbel(e) => write!(f, "{}", e),
+            HarnessError::Baseline(e) => write!(f, "{}", e),
+            HarnessError::HyperLogLog(e) => write!(f, "{}", e),
+            HarnessError::Panicked => write!(f, "the unit panicked"),
+        }
+    }
+}
+
+impl Error for HarnessError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            HarnessError::Io(e) => Some(e),
+            HarnessError::Gumbel(e) => Some(e),
+            HarnessError::Baseline(e) => Some(e),
+            HarnessError::HyperLogLog(e) => Some(e),
+            HarnessError::Panicked => None,
+        }
+    }
+}
+
+impl From<io::Error> for HarnessError {
+    fn from(e: io::Error) -> Self {
+        HarnessError::Io(e)
+    }
+}
+
+impl From<GumbelError> for HarnessError {
+    fn from(e: GumbelError) -> Self {
+        HarnessError::Gumbel(e)
+    }
+}
+
+impl From<BaselineError> for HarnessError {
+    fn from(e: BaselineError) -> Self {
+        HarnessError::Baseline(e)
+    }
+}
+
+impl From<HyperLogLogError> for HarnessError {
+    fn from(e: HyperLogLogError) -> Self {
+        HarnessError::HyperLogLog(e)
+    }
+}
@@ -0,0 +1,220 @@
+use siphasher::sip::SipHasher13;
+use std::hash::{BuildHasher, Hasher};
+use wyhash::WyHash;
+use xxhash_rust::xxh3::xxh3_64_with_seed;
+
+/// A hash function used by the estimators
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HashFunction {
+    AHash,
+    SipHash,
+    Xxh3,
+    WyHash,
+    Murmur3,
+    /// a deliberately weak polynomial hash, `h = 31 * h + byte`, without any final mixing
+    Weak,
+}
+
+impl HashFunction {
+    pub const ALL: [HashFunction; 6] = [
+        HashFunction::AHash,
+        HashFunction::SipHash,
+        HashFunction::Xxh3,
+        HashFunction::WyHash,
+        HashFunction::Murmur3,
+        HashFunction::Weak,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HashFunction::AHash => "ahash",
+            HashFunction::SipHash => "siphash",
+            HashFunction::Xxh3 => "xxh3",
+            HashFunction::WyHash => "wyhash",
+            HashFunction::Murmur3 => "murmur3",
+            HashFunction::Weak => "weak",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|hash| hash.name() == name)
+    }
+}
+
+/// A family of hash functions indexed by a seed
+pub trait SeededBuilder: BuildHasher + Clone {
+    fn with_seed(seed: u64) -> Self;
+}
+
+impl SeededBuilder for ahash::RandomState {
+    fn with_seed(seed: u64) -> Self {
+        // `RandomState::with_seed` mixes in keys drawn once per process, so only
+        // explicit keys give the same hash function in every run
+        ahash::RandomState::with_seeds(seed, !seed, seed.rotate_left(32), !seed.rotate_left(32))
+    }
+}
+
+/// SipHash-1-3, the hash function of the standard library, with explicit keys
+#[derive(Debug, Clone, Copy)]
+pub struct SipBuilder {
+    keys: (u64, u64),
+}
+
+impl BuildHasher for SipBuilder {
+    type Hasher = SipHasher13;
+
+    fn build_hasher(&self) -> SipHasher13 {
+        SipHasher13::new_with_keys(self.keys.0, self.keys.1)
+    }
+}
+
+impl SeededBuilder for SipBuilder {
+    fn with_seed(seed: u64) -> Self {
+        Self { keys: (seed, !seed) }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct WyBuilder {
+    seed: u64,
+}
+
+impl BuildHasher for WyBuilder {
+    type Hasher = WyHash;
+
+    fn build_hasher(&self) -> WyHash {
+        WyHash::with_seed(self.seed)
+    }
+}
+
+impl SeededBuilder for WyBuilder {
+    fn with_seed(seed: u64) -> Self {
+        Self { seed }
+    }
+}
+
+/// XXH3 with 64-bit output
+#[derive(Debug, Clone, Copy)]
+pub struct Xxh3Builder {
+    seed: u64,
+}
+
+impl BuildHasher for Xxh3Builder {
+    type Hasher = OneShotHasher;
+
+    fn build_hasher(&self) -> OneShotHasher {
+        OneShotHasher::new(self.seed, xxh3_64_with_seed)
+    }
+}
+
+impl SeededBuilder for Xxh3Builder {
+    fn with_seed(seed: u64) -> Self {
+        Self { seed }
+    }
+}
+
+/// MurmurHash3 x64 with the lower half of its 128-bit output
+#[derive(Debug, Clone, Copy)]
+pub struct Murmur3Builder {
+    seed: u64,
+}
+
+impl BuildHasher for Murmur3Builder {
+    type Hasher = OneShotHasher;
+
+    fn build_hasher(&self) -> OneShotHasher {
+        OneShotHasher::new(self.seed, murmur3_64)
+    }
+}
+
+impl SeededBuilder for Murmur3Builder {
+    fn with_seed(seed: u64) -> Self {
+        Self { seed }
+    }
+}
+
+fn murmur3_64(bytes: &[u8], seed: u64) -> u64 {
+    // reading from a slice cannot fail
+    murmur3::murmur3_x64_128(&mut &bytes[..], seed as u32).unwrap() as u64
+}
+
+/// A hasher which collects the written bytes and hashes them at once with a one-shot function
+pub struct OneShotHasher {
+    seed: u64,
+    function: fn(&[u8], u64) -> u64,
+    // short inputs are kept on the stack, so that hashing them does not allocate
+    inline: [u8; Self::INLINE],
+    len: usize,
+    spilled: Vec<u8>,
+}
+
+impl OneShotHasher {
+    const INLINE: usize = 32;
+
+    fn new(seed: u64, function: fn(&[u8], u64) -> u64) -> Self {
+        Self {
+            seed,
+            function,
+            inline: [0; Self::INLINE],
+            len: 0,
+            spilled: Vec::new(),
+        }
+    }
+}
+
+impl Hasher for OneShotHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        if self.spilled.is_empty() && self.len + bytes.len() <= Self::INLINE {
+            self.inline[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+            self.len += bytes.len();
+        } else {
+            if self.spilled.is_empty() {
+                self.spilled.extend_from_slice(&self.inline[..self.len]);
+            }
+            self.spilled.extend_from_slice(bytes);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        if self.spilled.is_empty() {
+            (self.function)(&self.inline[..self.len], self.seed)
+        } else {
+            (self.function)(&self.spilled, self.seed)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct WeakBuilder {
+    seed: u64,
+}
+
+impl BuildHasher for WeakBuilder {
+    type Hasher = WeakHasher;
+
+    fn build_hasher(&self) -> WeakHasher {
+        WeakHasher { state: self.seed }
+    }
+}
+
+impl SeededBuilder for WeakBuilder {
+    fn with_seed(seed: u64) -> Self {
+        Self { seed }
+    }
+}
+
+pub struct WeakHasher {
+    state: u64,
+}
+
+impl Hasher for WeakHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.state = self.state.wrapping_mul(31).wrapping_add(byte as u64);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.state
+    }
+}
@@ -1,94 +1,600 @@
 use ahash::random_state::RandomState;
-use gumbel_estimation::{GHLL, GHLLPlus, GHLLReal};
-use hyperloglogplus::{HyperLogLog, HyperLogLogPF};
-use std::fs::File;
+use gen_data::keys::{Email, Key, KeyKind, Url, Uuid};
+use gumbel_estimation::{Fixed, GHLL, GHLLPlus, GHLLReal, RealRegister, Strategy, ULL};
+use hyperloglogplus::{HyperLogLog, HyperLogLogPF, HyperLogLogPlus};
+use itertools::iproduct;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use std::hash::BuildHasher;
 use std::io;
-use std::io::{BufRead, BufReader, Write};
+use std::time::{Duration, Instant};
 
+pub mod analysis;
+pub mod baselines;
 pub mod constants;
+pub mod dataset;
+pub mod error;
+pub mod hashes;
+pub mod manifest;
+pub mod progress;
+pub mod results;
+pub mod significance;
+pub mod trajectory;
 
-use constants::ITERATIONS;
-
-pub fn create_output(alg: &str, prec: u8, card: usize, size: usize) -> Result<File, io::Error> {
-    let outpath = format!("../results/{}_{}_{}_{}.txt", alg, prec, card, size);
-    let out = File::create(outpath)?;
-
-    Ok(out)
-}
-
-pub fn create_input(card: usize, size: usize) -> Result<BufReader<File>, io::Error> {
-    let inpath = format!("../data/data_{}_{}.txt", card, size);
-    let input = File::open(&inpath).map_err(|err| {
-        io::Error::new(err.kind(), format!("failed to open file {}", inpath))
-    })?;
-    let reader = BufReader::new(input);
-
-    Ok(reader)
-}
-
-pub fn gather(prec: u8, card: usize, size: usize) -> Result<(), io::Error> {
-    // prepare the input data
-    let reader = create_input(card, size)?;
-
-    // prepare the output
-    let mut hll_out = create_output("HLL", prec, card, size)?;
-    let mut ghll_geo_out = create_output("GHLLGeo", prec, card, size)?;
-    let mut ghll_har_out = create_output("GHLLHar", prec, card, size)?;
-    let mut ghllr_geo_out = create_output("GHLLRealGeo", prec, card, size)?;
-    let mut ghllr_har_out = create_output("GHLLRealHar", prec, card, size)?;
-    let mut ghllp_out = create_output("GHLLPlus", prec, card, size)?;
-    
-    // create `ITERATIONS` independent estimators with a common random state
-    let builders: Vec<_> = (0..ITERATIONS).map(|_| RandomState::new()).collect();
-    let mut hll_estimators: Vec<_> = (0..ITERATIONS).map(|i| HyperLogLogPF::<u64, _>::new(prec, builders[i].clone()).unwrap()).collect();
-    let mut ghll_estimators: Vec<_> = (0..ITERATIONS).map(|i| GHLL::<_>::with_precision(prec, builders[i].clone()).unwrap()).collect();
-    let mut ghllr_estimators: Vec<_> = (0..ITERATIONS).map(|i| GHLLReal::<_>::with_precision(prec, builders[i].clone()).unwrap()).collect();
-    let mut ghllp_estimators: Vec<_> = (0..ITERATIONS).map(|i| GHLLPlus::<_>::with_precision(prec, builders[i].clone()).unwrap()).collect();
-
-    // analyse the data
-    for line in reader.lines() {
-        // read the next value
-        let value = line.and_then(|l| l.trim().parse::<u64>()
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
-        )?;
-
-        // feed the value to each estimator
-        for estimator in &mut hll_estimators {
-            estimator.insert(&value);
-        };
-        for estimator in &mut ghll_estimators {
-            estimator.add(&value);
-        };
-        for estimator in &mut ghllr_estimators {
-            estimator.add(&value);
-        };
-        for estimator in &mut ghllp_estimators {
-            estimator.add(&value);
+use baselines::{Exact, KMV, LinearCounting, LogLogBeta};
+use constants::{DATA_FORMAT, DESIGN, ITERATIONS};
+use dataset::Dataset;
+use error::HarnessError;
+use hashes::{HashFunction, Murmur3Builder, SeededBuilder, SipBuilder, WeakBuilder, WyBuilder, Xxh3Builder};
+use results::Record;
+
+/// The names of the algorithms whose results are gathered
+pub const ALGORITHMS: [&str; 22] = [
+    "HLL", "GHLLGeo", "GHLLHar", "GHLLML", "GHLLBias", "GHLLRealGeo", "GHLLRealHar", "GHLLReal64Geo", "GHLLReal64Har",
+    "GHLLFixed8Geo", "GHLLFixed8Har", "GHLLFixed12Geo", "GHLLFixed12Har", "GHLLFixed16Geo", "GHLLFixed16Har", "GHLLPlus", "ULL",
+    "HLLPlus", "LogLogBeta", "LinearCounting", "KMV", "Exact",
+];
+
+/// A group of algorithms sharing a single estimator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Group {
+    HLL,
+    GHLL,
+    GHLLReal,
+    GHLLReal64,
+    GHLLFixed8,
+    GHLLFixed12,
+    GHLLFixed16,
+    GHLLPlus,
+    ULL,
+    HLLPlus,
+    LogLogBeta,
+    LinearCounting,
+    KMV,
+    Exact,
+}
+
+impl Group {
+    pub const ALL: [Group; 14] = [
+        Group::HLL, Group::GHLL, Group::GHLLReal, Group::GHLLReal64,
+        Group::GHLLFixed8, Group::GHLLFixed12, Group::GHLLFixed16, Group::GHLLPlus, Group::ULL,
+        Group::HLLPlus, Group::LogLogBeta, Group::LinearCounting, Group::KMV, Group::Exact,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Group::HLL => "HLL",
+            Group::GHLL => "GHLL",
+            Group::GHLLReal => "GHLLReal",
+            Group::GHLLReal64 => "GHLLReal64",
+            Group::GHLLFixed8 => "GHLLFixed8",
+            Group::GHLLFixed12 => "GHLLFixed12",
+            Group::GHLLFixed16 => "GHLLFixed16",
+            Group::GHLLPlus => "GHLLPlus",
+            Group::ULL => "ULL",
+            Group::HLLPlus => "HLLPlus",
+            Group::LogLogBeta => "LogLogBeta",
+            Group::LinearCounting => "LinearCounting",
+            Group::KMV => "KMV",
+            Group::Exact => "Exact",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|group| group.name() == name)
+    }
+
+    /// The group whose estimator computes the given algorithm
+    pub fn of_algorithm(algorithm: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|group| group.algorithms().contains(&algorithm))
+    }
+
+    /// The names of the algorithms computed from this group's estimator
+    pub fn algorithms(self) -> &'static [&'static str] {
+        match self {
+            Group::HLL => &["HLL"],
+            Group::GHLL => &["GHLLGeo", "GHLLHar", "GHLLML", "GHLLBias"],
+            Group::GHLLReal => &["GHLLRealGeo", "GHLLRealHar"],
+            Group::GHLLReal64 => &["GHLLReal64Geo", "GHLLReal64Har"],
+            Group::GHLLFixed8 => &["GHLLFixed8Geo", "GHLLFixed8Har"],
+            Group::GHLLFixed12 => &["GHLLFixed12Geo", "GHLLFixed12Har"],
+            Group::GHLLFixed16 => &["GHLLFixed16Geo", "GHLLFixed16Har"],
+            Group::GHLLPlus => &["GHLLPlus"],
+            Group::ULL => &["ULL"],
+            Group::HLLPlus => &["HLLPlus"],
+            Group::LogLogBeta => &["LogLogBeta"],
+            Group::LinearCounting => &["LinearCounting"],
+            Group::KMV => &["KMV"],
+            Group::Exact => &["Exact"],
+        }
+    }
+
+    /// The number of bytes taken by the state of this group's estimator with the given precision,
+    /// or `None` if the precision is not accepted or the memory depends on the data; the sizes are
+    /// computed from the layouts of the states without building the estimators
+    pub fn memory_bytes(self, precision: u8) -> Option<usize> {
+        let (min, max) = match self {
+            Group::HLL => (4, 16),
+            Group::HLLPlus => (4, 18),
+            Group::LogLogBeta | Group::LinearCounting | Group::KMV => (baselines::MIN_PRECISION, baselines::MAX_PRECISION),
+            Group::Exact => return None,
+            _ => (gumbel_estimation::MIN_PRECISION, gumbel_estimation::MAX_PRECISION),
         };
-    };
+        if !(min..=max).contains(&precision) {
+            return None;
+        }
+
+        let m = 1_usize << precision;
+        Some(match self {
+            Group::HLL | Group::HLLPlus => hll_memory_bytes(precision),
+            // 5-bit registers, six to a 32-bit word, and the bit vector of the free registers
+            Group::GHLL | Group::GHLLPlus => m.div_ceil(6) * std::mem::size_of::<u32>() + m.div_ceil(usize::BITS as usize) * std::mem::size_of::<usize>(),
+            Group::GHLLReal => m * std::mem::size_of::<f32>(),
+            Group::GHLLReal64 => m * std::mem::size_of::<f64>(),
+            // fixed-point registers packed without spare bits
+            Group::GHLLFixed8 => (m * 8).div_ceil(8),
+            Group::GHLLFixed12 => (m * 12).div_ceil(8),
+            Group::GHLLFixed16 => (m * 16).div_ceil(8),
+            Group::ULL | Group::LogLogBeta => m,
+            Group::LinearCounting => usize::max(m / 64, 1) * std::mem::size_of::<u64>(),
+            Group::KMV => m * std::mem::size_of::<u64>(),
+            Group::Exact => return None,
+        })
+    }
+
+    /// The largest precision of this group's estimator whose state fits in `budget` bytes
+    pub fn precision_for_budget(self, budget: usize) -> Option<u8> {
+        (baselines::MIN_PRECISION..=baselines::MAX_PRECISION).rev()
+            .find(|&precision| self.memory_bytes(precision).is_some_and(|memory| memory <= budget))
+    }
+}
+
+/// The way of sizing the compared estimators
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sizing {
+    /// all estimators use the same precisions
+    Precision,
+    /// each estimator uses the largest precision whose state fits in the given byte budgets
+    Memory,
+}
+
+/// The number of bytes taken by the registers of HyperLogLog (and the dense representation
+/// of HyperLogLog++), which packs five 6-bit registers into a 32-bit word
+fn hll_memory_bytes(precision: u8) -> usize {
+    (1_usize << precision).div_ceil(5) * std::mem::size_of::<u32>()
+}
+
+/// A unit of work: all iterations of a single estimator group on a single dataset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Unit {
+    pub group: Group,
+    pub hash: HashFunction,
+    pub precision: u8,
+    pub cardinality: usize,
+    pub size: usize,
+}
+
+/// Creates the units for all combinations of the given groups, hash functions, precisions and
+/// (cardinality, size multiply) pairs; datasets of size larger than a billion are ignored. The exact
+/// baseline does not depend on the precision, so it is only planned with the first precision.
+pub fn plan_units(groups: &[Group], hashes: &[HashFunction], precisions: &[u8], cardinalities: &[usize], multiplies: &[usize]) -> Vec<Unit> {
+    iproduct!(precisions, cardinalities, multiplies, hashes, groups)
+        .filter(|(_, card, mult, _, _)| *card * *mult <= 1_000_000_000)
+        .filter(|(precision, _, _, _, group)| **group != Group::Exact || Some(*precision) == precisions.first())
+        .map(|(&precision, &cardinality, &mult, &hash, &group)| Unit {
+            group,
+            hash,
+            precision,
+            cardinality,
+            size: cardinality * mult,
+        })
+        .collect()
+}
+
+/// Creates the units for all combinations of the given groups, hash functions, byte budgets and
+/// (cardinality, size multiply) pairs, choosing the precision of each group to fit the budget; groups which
+/// do not fit a budget are skipped for it, and budgets leading to the same precision are merged
+pub fn plan_budget_units(groups: &[Group], hashes: &[HashFunction], budgets: &[usize], cardinalities: &[usize], multiplies: &[usize]) -> Vec<Unit> {
+    let mut sizes = Vec::new();
+    for (&budget, &group) in iproduct!(budgets, groups) {
+        if let Some(precision) = group.precision_for_budget(budget) {
+            if !sizes.contains(&(group, precision)) {
+                sizes.push((group, precision));
+            }
+        }
+    }
+
+    iproduct!(cardinalities, multiplies, hashes, sizes)
+        .filter(|(card, mult, _, _)| *card * *mult <= 1_000_000_000)
+        .map(|(&cardinality, &mult, &hash, (group, precision))| Unit {
+            group,
+            hash,
+            precision,
+            cardinality,
+            size: cardinality * mult,
+        })
+        .collect()
+}
+
+/// The design of the experiments, i.e. which randomness is shared between the estimator groups
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Design {
+    /// in each iteration, every group uses the same hash function and the Gumbel estimators
+    /// start from the same initial register values, so that the estimates are correlated
+    Paired,
+    /// every group uses its own hash function and initial register values
+    Independent,
+}
+
+/// Derives the seed of an iteration from the seed of the whole run; all randomness of the
+/// iteration, i.e. the hash function and the initial values of the Gumbel registers, is derived
+/// from it. In the paired design, every group gets the same seed in the same iteration
+/// on the same dataset.
+pub fn iteration_seed(run_seed: u64, design: Design, unit: &Unit, iteration: usize) -> u64 {
+    let seed = [unit.precision as u64, unit.cardinality as u64, unit.size as u64, iteration as u64]
+        .into_iter()
+        .fold(run_seed, |seed, value| splitmix64(seed ^ splitmix64(value)));
+
+    match design {
+        Design::Paired => seed,
+        Design::Independent => splitmix64(seed ^ splitmix64(unit.group as u64)),
+    }
+}
+
+// the SplitMix64 finaliser; a cheap bijective mixing of 64-bit values
+pub(crate) fn splitmix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// A dataset read into memory once and shared by all units gathered on it
+pub enum LoadedData {
+    U64(Vec<u64>),
+    Uuid(Vec<Uuid>),
+    Url(Vec<Url>),
+    Email(Vec<Email>),
+    Tuple(Vec<(u32, u64)>),
+}
+
+impl LoadedData {
+    /// Reads the dataset of the given key kind, cardinality and size
+    pub fn load(key: KeyKind, card: usize, size: usize) -> Result<Self, io::Error> {
+        Ok(match key {
+            KeyKind::U64 => LoadedData::U64(Dataset::open(card, size, DATA_FORMAT)?.load()?),
+            KeyKind::Uuid => LoadedData::Uuid(Dataset::open(card, size, DATA_FORMAT)?.load()?),
+            KeyKind::Url => LoadedData::Url(Dataset::open(card, size, DATA_FORMAT)?.load()?),
+            KeyKind::Email => LoadedData::Email(Dataset::open(card, size, DATA_FORMAT)?.load()?),
+            KeyKind::Tuple => LoadedData::Tuple(Dataset::open(card, size, DATA_FORMAT)?.load()?),
+        })
+    }
+}
 
-    // acquire the cardinality estimate for each estimator and write the result
-    for estimator in &mut hll_estimators {
-        let estimate = estimator.count();
-        writeln!(hll_out, "{}", estimate)?;
+/// Splits the units into the groups of units sharing a dataset, ordered by the first unit of every dataset
+pub fn group_by_dataset(units: &[Unit]) -> Vec<Vec<Unit>> {
+    let mut datasets: Vec<Vec<Unit>> = Vec::new();
+    for unit in units {
+        match datasets.iter_mut().find(|dataset| (dataset[0].cardinality, dataset[0].size) == (unit.cardinality, unit.size)) {
+            Some(dataset) => dataset.push(*unit),
+            None => datasets.push(vec![*unit]),
+        }
     }
-    for estimator in &mut ghll_estimators {
-        let estimate = estimator.count_geo();
-        writeln!(ghll_geo_out, "{}", estimate)?;
-        let estimate = estimator.count_har();
-        writeln!(ghll_har_out, "{}", estimate)?;
+
+    datasets
+}
+
+/// Gathers the results of a unit on its dataset, read beforehand
+pub fn gather_key(data: &LoadedData, unit: &Unit, run_seed: u64) -> Result<Vec<Record>, HarnessError> {
+    match data {
+        LoadedData::U64(data) => gather_hash(data, unit, run_seed),
+        LoadedData::Uuid(data) => gather_hash(data, unit, run_seed),
+        LoadedData::Url(data) => gather_hash(data, unit, run_seed),
+        LoadedData::Email(data) => gather_hash(data, unit, run_seed),
+        LoadedData::Tuple(data) => gather_hash(data, unit, run_seed),
     }
-    for estimator in &mut ghllr_estimators {
-        let estimate = estimator.count_geo();
-        writeln!(ghllr_geo_out, "{}", estimate)?;
-        let estimate = estimator.count_har();
-        writeln!(ghllr_har_out, "{}", estimate)?;
+}
+
+/// Gathers the results of a unit with the hash function of the unit
+pub fn gather_hash<K: Key>(data: &[K], unit: &Unit, run_seed: u64) -> Result<Vec<Record>, HarnessError> {
+    match unit.hash {
+        HashFunction::AHash => gather::<K, RandomState>(data, unit, run_seed),
+        HashFunction::SipHash => gather::<K, SipBuilder>(data, unit, run_seed),
+        HashFunction::Xxh3 => gather::<K, Xxh3Builder>(data, unit, run_seed),
+        HashFunction::WyHash => gather::<K, WyBuilder>(data, unit, run_seed),
+        HashFunction::Murmur3 => gather::<K, Murmur3Builder>(data, unit, run_seed),
+        HashFunction::Weak => gather::<K, WeakBuilder>(data, unit, run_seed),
+    }
+}
+
+/// Gathers the results of a unit, timing every estimator on its own pass over the data; fails with
+/// `HarnessError::Gumbel` if the registers of `GHLL` or `GHLLPlus` saturate in any iteration. The
+/// saturation of UltraLogLog is not an error here, since its estimator treats the largest update value
+/// as observing any larger one
+pub fn gather<K: Key, S: SeededBuilder>(data: &[K], unit: &Unit, run_seed: u64) -> Result<Vec<Record>, HarnessError> {
+    let Unit { group, hash, precision: prec, cardinality: card, size } = *unit;
+
+    let mut records = Vec::with_capacity(group.algorithms().len() * ITERATIONS);
+
+    for iteration in 0..ITERATIONS {
+        // derive the hash function and the generator of the initial register values
+        // of this iteration from a recorded seed
+        let seed = iteration_seed(run_seed, DESIGN, unit, iteration);
+        let builder = S::with_seed(seed);
+        let mut rng = StdRng::seed_from_u64(splitmix64(seed));
+
+        let mut record = |algorithm, estimate, elapsed, memory| records.push(Record {
+            algorithm,
+            hash,
+            precision: prec,
+            cardinality: card,
+            size,
+            iteration,
+            seed,
+            estimate,
+            elapsed,
+            memory,
+        });
+
+        // the elapsed time covers the construction of the estimator,
+        // feeding it the data and acquiring the estimate
+        match group {
+            Group::HLL => {
+                let start = Instant::now();
+                let mut estimator = HyperLogLogPF::<K, _>::new(prec, builder)?;
+                for value in data {
+                    estimator.insert(value);
+                }
+                let estimate = estimator.count();
+                record("HLL", estimate, start.elapsed(), hll_memory_bytes(prec));
+            }
+            Group::GHLL => {
+                let start = Instant::now();
+                let mut estimator = GHLL::<_>::with_precision_and_rng(prec, builder, &mut rng)?;
+                for value in data {
+                    estimator.add(value);
+                }
+                let insertion = start.elapsed();
+                let start = Instant::now();
+                let estimate = estimator.count_geo();
+                record("GHLLGeo", estimate, insertion + start.elapsed(), estimator.memory_bytes());
+                let start = Instant::now();
+                let estimate = estimator.count_har();
+                record("GHLLHar", estimate, insertion + start.elapsed(), estimator.memory_bytes());
+                let start = Instant::now();
+                let estimate = estimator.estimate(Strategy::MaximumLikelihood);
+                record("GHLLML", estimate, insertion + start.elapsed(), estimator.memory_bytes());
+                let start = Instant::now();
+                let estimate = estimator.estimate(Strategy::BiasCorrected);
+                record("GHLLBias", estimate, insertion + start.elapsed(), estimator.memory_bytes());
+
+                // the estimates of saturated registers are only lower bounds of the cardinality
+                estimator.check_saturation()?;
+            }
+            Group::GHLLReal => gather_real::<_, _, f32>(data, prec, builder, &mut rng, group.algorithms(), &mut record)?,
+            Group::GHLLReal64 => gather_real::<_, _, f64>(data, prec, builder, &mut rng, group.algorithms(), &mut record)?,
+            Group::GHLLFixed8 => gather_real::<_, _, Fixed<8>>(data, prec, builder, &mut rng, group.algorithms(), &mut record)?,
+            Group::GHLLFixed12 => gather_real::<_, _, Fixed<12>>(data, prec, builder, &mut rng, group.algorithms(), &mut record)?,
+            Group::GHLLFixed16 => gather_real::<_, _, Fixed<16>>(data, prec, builder, &mut rng, group.algorithms(), &mut record)?,
+            Group::GHLLPlus => {
+                let start = Instant::now();
+                let mut estimator = GHLLPlus::<_>::with_precision_and_rng(prec, builder, &mut rng)?;
+                for value in data {
+                    estimator.add(value);
+                }
+                let estimate = estimator.count();
+                record("GHLLPlus", estimate, start.elapsed(), estimator.memory_bytes());
+                estimator.check_saturation()?;
+            }
+            Group::ULL => {
+                let start = Instant::now();
+                let mut estimator = ULL::<_>::with_precision(prec, builder)?;
+                for value in data {
+                    estimator.add(value);
+                }
+                let estimate = estimator.count();
+                record("ULL", estimate, start.elapsed(), estimator.memory_bytes());
+            }
+            Group::HLLPlus => {
+                let start = Instant::now();
+                let mut estimator = HyperLogLogPlus::<K, _>::new(prec, builder)?;
+                for value in data {
+                    estimator.insert(value);
+                }
+                let estimate = estimator.count();
+                record("HLLPlus", estimate, start.elapsed(), hll_memory_bytes(prec));
+            }
+            Group::LogLogBeta => {
+                let start = Instant::now();
+                let mut estimator = LogLogBeta::with_precision(prec, builder)?;
+                for value in data {
+                    estimator.add(value);
+                }
+                let estimate = estimator.count();
+                record("LogLogBeta", estimate, start.elapsed(), estimator.memory_bytes());
+            }
+            Group::LinearCounting => {
+                let start = Instant::now();
+                let mut estimator = LinearCounting::with_precision(prec, builder)?;
+                for value in data {
+                    estimator.add(value);
+                }
+                let estimate = estimator.count();
+                record("LinearCounting", estimate, start.elapsed(), estimator.memory_bytes());
+            }
+            Group::KMV => {
+                let start = Instant::now();
+                let mut estimator = KMV::with_precision(prec, builder)?;
+                for value in data {
+                    estimator.add(value);
+                }
+                let estimate = estimator.count();
+                record("KMV", estimate, start.elapsed(), estimator.memory_bytes());
+            }
+            Group::Exact => {
+                let start = Instant::now();
+                let mut estimator = Exact::new(builder);
+                for value in data {
+                    estimator.add(value);
+                }
+                let estimate = estimator.count();
+                record("Exact", estimate, start.elapsed(), estimator.memory_bytes());
+            }
+        }
     }
-    for estimator in &mut ghllp_estimators {
-        let estimate = estimator.count();
-        writeln!(ghllp_out, "{}", estimate)?;
+
+    Ok(records)
+}
+
+// feeds the data to a `GHLLReal` estimator with registers of type `R`, recording its geometric
+// and harmonic estimates as the given algorithms; the elapsed time is measured as in `gather`
+fn gather_real<K: Key, B: BuildHasher, R: RealRegister>(
+    data: &[K],
+    prec: u8,
+    builder: B,
+    rng: &mut StdRng,
+    algorithms: &'static [&'static str],
+    mut record: impl FnMut(&'static str, f64, Duration, usize),
+) -> Result<(), HarnessError> {
+    let start = Instant::now();
+    let mut estimator = GHLLReal::<_, R>::with_precision_and_rng(prec, builder, rng)?;
+    for value in data {
+        estimator.add(value);
     }
+    let insertion = start.elapsed();
+
+    let start = Instant::now();
+    let estimate = estimator.count_geo();
+    record(algorithms[0], estimate, insertion + start.elapsed(), estimator.memory_bytes());
+    let start = Instant::now();
+    let estimate = estimator.count_har();
+    record(algorithms[1], estimate, insertion + start.elapsed(), estimator.memory_bytes());
 
     Ok(())
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use baselines::BaselineError;
+    use gumbel_estimation::GumbelError;
+    use hyperloglogplus::HyperLogLogError;
+
+    // the memory of the estimator of the group built with the given precision
+    fn built_memory_bytes(group: Group, precision: u8) -> Option<usize> {
+        let builder = RandomState::with_seed(0);
+        let mut rng = StdRng::seed_from_u64(0);
+        match group {
+            Group::HLL => HyperLogLogPF::<u64, _>::new(precision, builder).ok().map(|_| hll_memory_bytes(precision)),
+            Group::HLLPlus => HyperLogLogPlus::<u64, _>::new(precision, builder).ok().map(|_| hll_memory_bytes(precision)),
+            Group::GHLL => GHLL::with_precision_and_rng(precision, builder, &mut rng).ok().map(|e| e.memory_bytes()),
+            Group::GHLLReal => GHLLReal::<_, f32>::with_precision_and_rng(precision, builder, &mut rng).ok().map(|e| e.memory_bytes()),
+            Group::GHLLReal64 => GHLLReal::<_, f64>::with_precision_and_rng(precision, builder, &mut rng).ok().map(|e| e.memory_bytes()),
+            Group::GHLLFixed8 => GHLLReal::<_, Fixed<8>>::with_precision_and_rng(precision, builder, &mut rng).ok().map(|e| e.memory_bytes()),
+            Group::GHLLFixed12 => GHLLReal::<_, Fixed<12>>::with_precision_and_rng(precision, builder, &mut rng).ok().map(|e| e.memory_bytes()),
+            Group::GHLLFixed16 => GHLLReal::<_, Fixed<16>>::with_precision_and_rng(precision, builder, &mut rng).ok().map(|e| e.memory_bytes()),
+            Group::GHLLPlus => GHLLPlus::with_precision_and_rng(precision, builder, &mut rng).ok().map(|e| e.memory_bytes()),
+            Group::ULL => ULL::with_precision(precision, builder).ok().map(|e| e.memory_bytes()),
+            Group::LogLogBeta => LogLogBeta::with_precision(precision, builder).ok().map(|e| e.memory_bytes()),
+            Group::LinearCounting => LinearCounting::with_precision(precision, builder).ok().map(|e| e.memory_bytes()),
+            Group::KMV => KMV::with_precision(precision, builder).ok().map(|e| e.memory_bytes()),
+            Group::Exact => None,
+        }
+    }
+
+    #[test]
+    fn memory_matches_the_built_estimators() {
+        for group in Group::ALL {
+            for precision in 0..=20 {
+                assert_eq!(group.memory_bytes(precision), built_memory_bytes(group, precision), "{} with precision {}", group.name(), precision);
+            }
+        }
+
+        // the storage of the registers
+        assert_eq!(Group::GHLL.memory_bytes(12), Some(683 * 4 + 64 * 8));
+        assert_eq!(Group::GHLLReal.memory_bytes(12), Some(4096 * 4));
+        assert_eq!(Group::GHLLReal64.memory_bytes(12), Some(4096 * 8));
+        assert_eq!(Group::GHLLFixed8.memory_bytes(12), Some(4096));
+        assert_eq!(Group::GHLLFixed12.memory_bytes(12), Some(6144));
+        assert_eq!(Group::GHLLFixed16.memory_bytes(12), Some(8192));
+        assert_eq!(Group::HLLPlus.memory_bytes(18), Some((1 << 18) / 5 * 4 + 4));
+    }
+
+    #[test]
+    fn memory_and_budget_precisions_are_monotone() {
+        for group in Group::ALL {
+            let memory: Vec<_> = (4..=18).filter_map(|precision| group.memory_bytes(precision)).collect();
+            assert!(memory.windows(2).all(|pair| pair[0] <= pair[1]), "{}", group.name());
+
+            let budgets = (0..24).map(|shift| 1_usize << shift);
+            let precisions: Vec<_> = budgets.clone().map(|budget| group.precision_for_budget(budget)).collect();
+            assert!(precisions.windows(2).all(|pair| pair[0] <= pair[1]), "{}", group.name());
+
+            // the chosen precision is the largest one fitting the budget
+            for (budget, precision) in budgets.zip(precisions) {
+                if let Some(precision) = precision {
+                    assert!(group.memory_bytes(precision).unwrap() <= budget);
+                    assert!(group.memory_bytes(precision + 1).is_none_or(|memory| memory > budget));
+                }
+            }
+        }
+
+        assert_eq!(Group::HLLPlus.precision_for_budget(usize::MAX), Some(18));
+        assert_eq!(Group::GHLLFixed8.precision_for_budget(usize::MAX), Some(16));
+        assert_eq!(Group::Exact.precision_for_budget(usize::MAX), None);
+    }
+
+    fn unit(group: Group) -> Unit {
+        Unit { group, hash: HashFunction::AHash, precision: 8, cardinality: 500, size: 2000 }
+    }
+
+    fn data() -> Vec<u64> {
+        (0..2000).map(|value| splitmix64(value % 500)).collect()
+    }
+
+    #[test]
+    fn iterations_are_reproducible_from_the_run_seed() {
+        let estimates = |run_seed| gather_hash(&data(), &unit(Group::GHLL), run_seed).unwrap()
+            .into_iter()
+            .map(|record| (record.algorithm, record.iteration, record.seed, record.estimate))
+            .collect::<Vec<_>>();
+
+        let first = estimates(42);
+        assert_eq!(first.len(), 4 * ITERATIONS);
+        assert_eq!(first, estimates(42));
+        assert_ne!(first, estimates(43));
+    }
+
+    #[test]
+    fn groups_share_the_seed_of_an_iteration() {
+        let seeds = |group| gather_hash(&data(), &unit(group), 42).unwrap()
+            .into_iter()
+            .map(|record| (record.iteration, record.seed))
+            .collect::<std::collections::BTreeSet<_>>();
+
+        // in the paired design of the harness, every group hashes with the same seed in an iteration
+        assert_eq!(DESIGN, Design::Paired);
+        let ghll = seeds(Group::GHLL);
+        assert_eq!(ghll.len(), ITERATIONS);
+        for group in [Group::HLL, Group::GHLLReal, Group::GHLLFixed8, Group::ULL, Group::KMV] {
+            assert_eq!(seeds(group), ghll, "{}", group.name());
+        }
+
+        // the seeds differ between iterations, and between groups in the independent design
+        let (ghll, hll) = (unit(Group::GHLL), unit(Group::HLL));
+        assert_ne!(iteration_seed(42, Design::Paired, &ghll, 0), iteration_seed(42, Design::Paired, &ghll, 1));
+        assert_eq!(iteration_seed(42, Design::Paired, &ghll, 0), iteration_seed(42, Design::Paired, &hll, 0));
+        assert_ne!(iteration_seed(42, Design::Independent, &ghll, 0), iteration_seed(42, Design::Independent, &hll, 0));
+    }
+
+    #[test]
+    fn errors_of_the_estimators_keep_their_types() {
+        let gather = |group, precision| gather_hash(&data(), &Unit { precision, ..unit(group) }, 42).unwrap_err();
+
+        assert!(matches!(gather(Group::GHLL, 20), HarnessError::Gumbel(GumbelError::InvalidPrecision { precision: 20, .. })));
+        assert!(matches!(gather(Group::GHLLFixed12, 3), HarnessError::Gumbel(GumbelError::InvalidPrecision { precision: 3, .. })));
+        assert!(matches!(gather(Group::LogLogBeta, 20), HarnessError::Baseline(BaselineError::InvalidPrecision)));
+        assert!(matches!(gather(Group::HLL, 17), HarnessError::HyperLogLog(HyperLogLogError::InvalidPrecision)));
+        assert_eq!(gather(Group::GHLL, 20).to_string(), "precision 20 is out of the range 4..=16");
+    }
+}
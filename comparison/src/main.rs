@@ -1,64 +1,95 @@
-use itertools::iproduct;
-use std::io::{stdout, Write};
-use std::thread;
-use std::sync::{Arc, Mutex};
+use rand::{Rng, thread_rng};
+use rayon::ThreadPoolBuilder;
+use rayon::prelude::*;
+use std::io;
+use std::panic;
+use std::process;
+use std::sync::Mutex;
+use std::time::Instant;
 
-use comparison::gather;
-use comparison::constants::{CARDINALITIES, DATA_SIZE_MULTIPLIES, PRECISIONS};
+use comparison::{LoadedData, Sizing, gather_key, group_by_dataset, plan_budget_units, plan_units};
+use comparison::constants::{CARDINALITIES, DATA_SIZE_MULTIPLIES, GROUPS, HASHES, KEY, MEMORY_BUDGETS, OUTPUT_LAYOUT, PRECISIONS, SIZING, WORKERS};
+use comparison::error::HarnessError;
+use comparison::manifest::{Manifest, manifest_path};
+use comparison::progress::{Progress, log_path};
+use comparison::results::ResultsWriter;
 
 fn main() {
     println!("Gathering results...");
 
-    // take dataset specifications based on all combinations of
-    // (cardinality, data_size) using the constants from constants.rs;
-    // datasets of size larger than a billion are ignored
-    let data_sizes: Vec<_> = iproduct!(CARDINALITIES, DATA_SIZE_MULTIPLIES).filter(|(card, mult)| card * mult <= 1_000_000_000).collect();
-    let no_datasets = data_sizes.len();
+    // open the manifest of the run; if a previous run was interrupted, its seed is reused
+    // and its completed units are skipped
+    let manifest = Manifest::open(&manifest_path(KEY), thread_rng().gen())
+        .unwrap_or_else(|e| panic!("Failed opening the manifest: {}\n", e));
+    let run_seed = manifest.run_seed();
 
-    // prepare the handles
-    let mut handles = Vec::new();
+    // split the experiments into units of (precision, dataset, hash function, estimator group)
+    // using the constants from constants.rs; the largest datasets go first,
+    // so that the run does not end with a single worker on a long unit
+    let mut units = match SIZING {
+        Sizing::Precision => plan_units(&GROUPS, &HASHES, &PRECISIONS, &CARDINALITIES, &DATA_SIZE_MULTIPLIES),
+        Sizing::Memory => plan_budget_units(&GROUPS, &HASHES, &MEMORY_BUDGETS, &CARDINALITIES, &DATA_SIZE_MULTIPLIES),
+    };
+    manifest.retain_remaining(&mut units);
+    units.sort_by_key(|unit| std::cmp::Reverse(unit.size));
 
-    // create the counter for experiments done or in progress
-    let in_progress_all = Arc::new(Mutex::new(0));
+    // prepare the output shared by all workers
+    let out = if manifest.is_resumed() {
+        println!("Resuming the run with seed {}; {} units remaining", run_seed, units.len());
+        ResultsWriter::resume(OUTPUT_LAYOUT, KEY, &manifest)
+    } else {
+        ResultsWriter::create(OUTPUT_LAYOUT, KEY)
+    }.unwrap_or_else(|e| panic!("Failed creating the output: {}\n", e));
 
-    // calculate the number of threads
-    let no_threads = PRECISIONS.len();
+    // start tracking the progress and prepare the list of failed units
+    let progress = Progress::new(&units, &log_path(KEY))
+        .unwrap_or_else(|e| panic!("Failed creating the progress log: {}\n", e));
+    let failed = Mutex::new(Vec::new());
 
-    // gather the results; split the gatherer into threads based on precision
-    for prec in PRECISIONS {
-        // total number of experiments
-        let total_experiments = no_datasets * no_threads;
+    // create the worker pool; zero workers use all available cores
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(WORKERS)
+        .thread_name(|i| format!("Worker {}", i))
+        .build()
+        .unwrap_or_else(|e| panic!("Failed creating the worker pool: {}\n", e));
 
-        // clone the data iterators
-        let data_sizes = data_sizes.clone();
+    // gather the results dataset by dataset, so that every dataset is read once and only one
+    // is held in memory; idle workers steal the remaining units of the dataset from busy ones
+    for units in group_by_dataset(&units) {
+        let data = LoadedData::load(KEY, units[0].cardinality, units[0].size);
 
-        // get a shared reference to the counter of completed experiments
-        let in_progress = Arc::clone(&in_progress_all);
+        pool.install(|| units.par_iter().with_max_len(1).for_each(|unit| {
+            let started = Instant::now();
 
-        // create the thread
-        let handle = thread::Builder::new()
-            .name(format!("Thread prec={}", prec))
-            .spawn(move || {
-            for (card, mult) in data_sizes {
-                // update the datasets-in-progress counter
-                {
-                    let mut count = in_progress.lock().unwrap();
-                    *count += 1;
-                    print!("\rin progress: {}/{}; ", count, total_experiments);
-                    stdout().flush().unwrap();
-                }
+            // a failing unit, including a panicking one, is reported at the end
+            // instead of stopping the whole run
+            let result = match &data {
+                Ok(data) => panic::catch_unwind(|| gather_key(data, unit, run_seed))
+                    .unwrap_or(Err(HarnessError::Panicked)),
+                Err(e) => Err(HarnessError::Io(io::Error::new(e.kind(), e.to_string()))),
+            }.and_then(|records| {
+                out.write(&records)?;
+                manifest.complete(unit)?;
+                Ok(records)
+            });
 
-                // gather results
-                gather(prec, card, card * mult).unwrap_or_else(|e| panic!("Failed gathering data: {}\n", e));
+            progress.finish(unit, started, result.as_deref())
+                .unwrap_or_else(|e| panic!("Failed writing the progress log: {}\n", e));
+            if let Err(e) = result {
+                failed.lock().unwrap().push((*unit, e));
             }
-        }).unwrap();
-
-        handles.push(handle);
+        }));
     }
 
-    for handle in handles {
-        let _ = handle.join();
-    }
+    progress.print_summary();
 
-    println!();
+    // report the failed units; rerunning the program retries only these
+    let failed = failed.into_inner().unwrap();
+    if !failed.is_empty() {
+        eprintln!("{} units failed:", failed.len());
+        for (unit, e) in failed {
+            eprintln!("- {} hash={} prec={} card={} size={}: {}", unit.group.name(), unit.hash.name(), unit.precision, unit.cardinality, unit.size, e);
+        }
+        process::exit(1);
+    }
 }
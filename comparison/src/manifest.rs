@@ -0,0 +1,159 @@
+use gen_data::keys::KeyKind;
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{BufWriter, Write};
+use std::sync::Mutex;
+
+use crate::{Group, Unit};
+use crate::hashes::HashFunction;
+
+/// The path of the run manifest for the given key kind
+pub fn manifest_path(key: KeyKind) -> String {
+    match key {
+        KeyKind::U64 => "../results/manifest.csv".to_owned(),
+        key => format!("../results/manifest_{}.csv", key.name()),
+    }
+}
+
+/// The record of the units completed by a run, which allows resuming it after a failure
+///
+/// Each line holds a completed unit along with the seed of the run, from which the seeds
+/// of all its iterations are derived. A resumed run reuses that seed, so that the estimators
+/// of every group keep sharing hash functions within an iteration.
+pub struct Manifest {
+    run_seed: u64,
+    completed: HashSet<(Group, HashFunction, u8, usize, usize)>,
+    out: Mutex<BufWriter<File>>,
+}
+
+impl Manifest {
+    const CSV_HEADER: &'static str = "group,hash,precision,cardinality,size,seed";
+
+    /// Opens the manifest at `path`, or creates a new one with the given seed if there is none
+    pub fn open(path: &str, run_seed: u64) -> Result<Self, io::Error> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(io::Error::new(e.kind(), format!("failed to read file {}", path))),
+        };
+
+        let mut seed = None;
+        let mut completed = HashSet::new();
+        for line in content.lines().skip(1) {
+            let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("invalid row {} in file {}", line, path));
+            let fields: Vec<_> = line.split(',').collect();
+            if fields.len() != 6 {
+                return Err(invalid());
+            }
+
+            let group = Group::from_name(fields[0]).ok_or_else(invalid)?;
+            let hash = HashFunction::from_name(fields[1]).ok_or_else(invalid)?;
+            let precision = fields[2].parse().map_err(|_| invalid())?;
+            let cardinality = fields[3].parse().map_err(|_| invalid())?;
+            let size = fields[4].parse().map_err(|_| invalid())?;
+            let line_seed = fields[5].parse().map_err(|_| invalid())?;
+
+            if *seed.get_or_insert(line_seed) != line_seed {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("file {} mixes units of different runs", path)));
+            }
+            completed.insert((group, hash, precision, cardinality, size));
+        }
+
+        let mut out = BufWriter::new(OpenOptions::new().create(true).append(true).open(path)?);
+        if content.is_empty() {
+            writeln!(out, "{}", Self::CSV_HEADER)?;
+            out.flush()?;
+        }
+
+        Ok(Self {
+            run_seed: seed.unwrap_or(run_seed),
+            completed,
+            out: Mutex::new(out),
+        })
+    }
+
+    /// The seed of the run recorded in the manifest
+    pub fn run_seed(&self) -> u64 {
+        self.run_seed
+    }
+
+    /// Whether the manifest resumes a previous run
+    pub fn is_resumed(&self) -> bool {
+        !self.completed.is_empty()
+    }
+
+    pub fn is_completed(&self, unit: &Unit) -> bool {
+        self.completed.contains(&(unit.group, unit.hash, unit.precision, unit.cardinality, unit.size))
+    }
+
+    /// Keeps only the units that are not recorded as completed, i.e. those a resumed run has to gather
+    pub fn retain_remaining(&self, units: &mut Vec<Unit>) {
+        units.retain(|unit| !self.is_completed(unit));
+    }
+
+    /// Records a unit as completed; this should happen only after its results were written
+    pub fn complete(&self, unit: &Unit) -> Result<(), io::Error> {
+        let mut out = self.out.lock().unwrap();
+        writeln!(out, "{},{},{},{},{},{}", unit.group.name(), unit.hash.name(), unit.precision, unit.cardinality, unit.size, self.run_seed)?;
+        out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plan_units;
+
+    fn path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("comparison_manifest_{}_{}.csv", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path.to_str().unwrap().to_owned()
+    }
+
+    #[test]
+    fn resumed_run_gathers_only_the_unfinished_units() {
+        let path = path("resume");
+        let units = plan_units(&[Group::GHLL, Group::HLL], &[HashFunction::AHash, HashFunction::Xxh3], &[8, 10], &[100, 1000], &[10]);
+
+        // a run stopping after every third unit
+        let manifest = Manifest::open(&path, 5).unwrap();
+        assert!(!manifest.is_resumed());
+        for unit in units.iter().step_by(3) {
+            manifest.complete(unit).unwrap();
+        }
+        drop(manifest);
+
+        // the resumed run keeps the seed of the first one and skips the completed units
+        let manifest = Manifest::open(&path, 9).unwrap();
+        assert!(manifest.is_resumed());
+        assert_eq!(manifest.run_seed(), 5);
+        let mut remaining = units.clone();
+        manifest.retain_remaining(&mut remaining);
+        let unfinished: Vec<_> = units.iter().enumerate().filter(|(i, _)| i % 3 != 0).map(|(_, unit)| *unit).collect();
+        assert_eq!(remaining, unfinished);
+
+        // completing the rest leaves nothing to gather
+        for unit in &remaining {
+            manifest.complete(unit).unwrap();
+        }
+        drop(manifest);
+        let manifest = Manifest::open(&path, 9).unwrap();
+        let mut remaining = units.clone();
+        manifest.retain_remaining(&mut remaining);
+        assert!(remaining.is_empty());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn manifests_of_mixed_runs_are_rejected() {
+        let path = path("mixed");
+        let content = format!("{}\nGHLL,ahash,8,100,1000,5\nGHLL,xxh3,8,100,1000,7\n", Manifest::CSV_HEADER);
+        std::fs::write(&path, content).unwrap();
+
+        let error = Manifest::open(&path, 5).err().unwrap();
+        assert!(error.to_string().contains("mixes units of different runs"), "{}", error);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,163 @@
+use gen_data::keys::KeyKind;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{BufWriter, Write, stdout};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::Unit;
+use crate::constants::ITERATIONS;
+use crate::error::HarnessError;
+use crate::results::Record;
+
+/// The path of the progress log for the given key kind
+pub fn log_path(key: KeyKind) -> String {
+    match key {
+        KeyKind::U64 => "../results/progress.jsonl".to_owned(),
+        key => format!("../results/progress_{}.jsonl", key.name()),
+    }
+}
+
+/// A single line of the progress log, describing one `gather` call
+#[derive(Debug, Serialize)]
+struct LogEntry<'a> {
+    group: &'static str,
+    hash: &'static str,
+    precision: u8,
+    cardinality: usize,
+    size: usize,
+    /// the number of elements fed to the estimators, over all iterations
+    elements: u64,
+    /// the seconds since the start of the run at which the unit started
+    started: f64,
+    /// the wall time of the unit in seconds
+    elapsed: f64,
+    error: Option<&'a str>,
+}
+
+struct State {
+    completed: usize,
+    failed: usize,
+    elements: u64,
+    remaining_elements: u64,
+    /// the time spent by each algorithm, summed over all iterations and workers
+    algorithm_time: BTreeMap<&'static str, Duration>,
+}
+
+/// Tracks the progress of a run, reporting it on the terminal and in a JSON lines log
+pub struct Progress {
+    start: Instant,
+    no_units: usize,
+    state: Mutex<State>,
+    log: Mutex<BufWriter<File>>,
+}
+
+// the number of elements a unit feeds to its estimators
+fn unit_elements(unit: &Unit) -> u64 {
+    (unit.size * ITERATIONS) as u64
+}
+
+impl Progress {
+    /// Starts tracking the given units, appending to the progress log at `path`
+    pub fn new(units: &[Unit], path: &str) -> Result<Self, io::Error> {
+        let log = OpenOptions::new().create(true).append(true).open(path)
+            .map_err(|err| io::Error::new(err.kind(), format!("failed to open file {}", path)))?;
+
+        Ok(Self {
+            start: Instant::now(),
+            no_units: units.len(),
+            state: Mutex::new(State {
+                completed: 0,
+                failed: 0,
+                elements: 0,
+                remaining_elements: units.iter().map(unit_elements).sum(),
+                algorithm_time: BTreeMap::new(),
+            }),
+            log: Mutex::new(BufWriter::new(log)),
+        })
+    }
+
+    /// Records a finished unit which started at `started`, with its records if it succeeded
+    pub fn finish(&self, unit: &Unit, started: Instant, result: Result<&[Record], &HarnessError>) -> Result<(), io::Error> {
+        let elapsed = started.elapsed();
+        let elements = unit_elements(unit);
+
+        let error = result.as_ref().err().map(|e| e.to_string());
+        let entry = LogEntry {
+            group: unit.group.name(),
+            hash: unit.hash.name(),
+            precision: unit.precision,
+            cardinality: unit.cardinality,
+            size: unit.size,
+            elements,
+            started: started.duration_since(self.start).as_secs_f64(),
+            elapsed: elapsed.as_secs_f64(),
+            error: error.as_deref(),
+        };
+        {
+            let mut log = self.log.lock().unwrap();
+            serde_json::to_writer(&mut *log, &entry)?;
+            writeln!(log)?;
+            log.flush()?;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        state.remaining_elements -= elements;
+        match result {
+            Ok(records) => {
+                state.completed += 1;
+                state.elements += elements;
+                for record in records {
+                    *state.algorithm_time.entry(record.algorithm).or_default() += record.elapsed;
+                }
+            }
+            Err(_) => state.failed += 1,
+        }
+
+        self.report(&state);
+
+        Ok(())
+    }
+
+    // print the state of the run on a single line
+    fn report(&self, state: &State) {
+        let elapsed = self.start.elapsed();
+        let throughput = state.elements as f64 / elapsed.as_secs_f64();
+        let eta = if state.elements > 0 {
+            format_duration(Duration::from_secs_f64(state.remaining_elements as f64 / throughput))
+        } else {
+            "?".to_owned()
+        };
+
+        print!("\rcompleted: {}/{}, failed: {}; {:.3e} elements/s; elapsed {}, ETA {}   ",
+            state.completed, self.no_units, state.failed, throughput, format_duration(elapsed), eta);
+        stdout().flush().unwrap();
+    }
+
+    /// Prints the time spent by each algorithm over the whole run
+    pub fn print_summary(&self) {
+        let state = self.state.lock().unwrap();
+
+        println!();
+        println!("Finished in {}", format_duration(self.start.elapsed()));
+        for (algorithm, time) in &state.algorithm_time {
+            println!("- {}: {}", algorithm, format_duration(*time));
+        }
+    }
+}
+
+// format a duration as e.g. `1h02m03s`
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (hours, minutes, seconds) = (secs / 3600, secs / 60 % 60, secs % 60);
+
+    if hours > 0 {
+        format!("{}h{:02}m{:02}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m{:02}s", minutes, seconds)
+    } else {
+        format!("{}.{:01}s", seconds, duration.subsec_millis() / 100)
+    }
+}
@@ -0,0 +1,245 @@
+use gen_data::keys::KeyKind;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::{Group, Unit};
+use crate::hashes::HashFunction;
+use crate::manifest::Manifest;
+
+/// The layout of the gathered results
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// a single long-format CSV table with one row per estimate
+    Table,
+    /// one text file per (algorithm, precision, cardinality, size) with one estimate per line
+    Files,
+}
+
+/// A single estimate produced by an algorithm in one iteration
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub algorithm: &'static str,
+    pub hash: HashFunction,
+    pub precision: u8,
+    pub cardinality: usize,
+    pub size: usize,
+    pub iteration: usize,
+    pub seed: u64,
+    pub estimate: f64,
+    pub elapsed: Duration,
+    /// the number of bytes taken by the state of the estimator
+    pub memory: usize,
+}
+
+impl Record {
+    pub const CSV_HEADER: &'static str = "algorithm,hash,precision,cardinality,size,iteration,seed,estimate,elapsed,memory";
+
+    fn write_csv<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "{},{},{},{},{},{},{},{},{},{}",
+            self.algorithm, self.hash.name(), self.precision, self.cardinality, self.size,
+            self.iteration, self.seed, self.estimate, self.elapsed.as_secs_f64(), self.memory,
+        )
+    }
+}
+
+/// The path of the results table for the given key kind
+pub fn table_path(key: KeyKind) -> String {
+    match key {
+        KeyKind::U64 => "../results/results.csv".to_owned(),
+        key => format!("../results/results_{}.csv", key.name()),
+    }
+}
+
+/// The path of a results file in the `Layout::Files` layout
+pub fn output_path(alg: &str, key: KeyKind, hash: HashFunction, prec: u8, card: usize, size: usize) -> String {
+    // results for `u64` keys hashed with ahash keep the original naming
+    let mut name = alg.to_owned();
+    if key != KeyKind::U64 {
+        name = format!("{}_{}", name, key.name());
+    }
+    if hash != HashFunction::AHash {
+        name = format!("{}_{}", name, hash.name());
+    }
+
+    format!("../results/{}_{}_{}_{}.txt", name, prec, card, size)
+}
+
+pub fn create_output(alg: &str, key: KeyKind, hash: HashFunction, prec: u8, card: usize, size: usize) -> Result<File, io::Error> {
+    let out = File::create(output_path(alg, key, hash, prec, card, size))?;
+
+    Ok(out)
+}
+
+/// A sink for gathered records shared between the gathering threads
+pub enum ResultsWriter {
+    Table(Mutex<BufWriter<File>>),
+    Files(KeyKind),
+}
+
+impl ResultsWriter {
+    pub fn create(layout: Layout, key: KeyKind) -> Result<Self, io::Error> {
+        match layout {
+            Layout::Table => Self::create_table(&table_path(key)),
+            Layout::Files => Ok(ResultsWriter::Files(key)),
+        }
+    }
+
+    /// Opens the output of a run resumed from `manifest`, dropping the rows of the units that
+    /// were not recorded as completed, e.g. because the run stopped while writing them
+    pub fn resume(layout: Layout, key: KeyKind, manifest: &Manifest) -> Result<Self, io::Error> {
+        match layout {
+            Layout::Table => Self::resume_table(&table_path(key), manifest),
+            // every unit overwrites its own files, so there is nothing to drop
+            Layout::Files => Ok(ResultsWriter::Files(key)),
+        }
+    }
+
+    fn create_table(path: &str) -> Result<Self, io::Error> {
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(out, "{}", Record::CSV_HEADER)?;
+        out.flush()?;
+
+        Ok(ResultsWriter::Table(Mutex::new(out)))
+    }
+
+    fn resume_table(path: &str, manifest: &Manifest) -> Result<Self, io::Error> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Self::create_table(path),
+            Err(e) => return Err(io::Error::new(e.kind(), format!("failed to read file {}", path))),
+        };
+
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(out, "{}", Record::CSV_HEADER)?;
+        for line in content.lines().skip(1) {
+            let fields: Vec<_> = line.splitn(6, ',').collect();
+            let unit = match fields[..] {
+                [algorithm, hash, precision, cardinality, size, _] => Group::of_algorithm(algorithm)
+                    .zip(HashFunction::from_name(hash))
+                    .zip(precision.parse().ok())
+                    .zip(cardinality.parse().ok().zip(size.parse().ok()))
+                    .map(|(((group, hash), precision), (cardinality, size))| Unit { group, hash, precision, cardinality, size }),
+                _ => None,
+            };
+
+            if unit.is_some_and(|unit| manifest.is_completed(&unit)) {
+                writeln!(out, "{}", line)?;
+            }
+        }
+        out.flush()?;
+
+        Ok(ResultsWriter::Table(Mutex::new(out)))
+    }
+
+    /// Writes the records of a single dataset configuration
+    pub fn write(&self, records: &[Record]) -> Result<(), io::Error> {
+        match self {
+            ResultsWriter::Table(out) => {
+                // write the whole configuration at once, so that the rows of
+                // different threads are not interleaved
+                let mut out = out.lock().unwrap();
+                for record in records {
+                    record.write_csv(&mut *out)?;
+                }
+                out.flush()
+            }
+            ResultsWriter::Files(key) => {
+                let Some(first) = records.first() else {
+                    return Ok(());
+                };
+
+                // the records are grouped by algorithm in one file each,
+                // keeping the order of the iterations
+                let mut algorithms: Vec<_> = records.iter().map(|r| r.algorithm).collect();
+                algorithms.sort_unstable();
+                algorithms.dedup();
+                for alg in algorithms {
+                    let mut out = BufWriter::new(create_output(alg, *key, first.hash, first.precision, first.cardinality, first.size)?);
+                    for record in records.iter().filter(|r| r.algorithm == alg) {
+                        writeln!(out, "{}", record.estimate)?;
+                    }
+                    out.flush()?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::read_table;
+    use crate::plan_units;
+
+    fn path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("comparison_results_{}_{}.csv", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path.to_str().unwrap().to_owned()
+    }
+
+    // the records of three iterations of a unit
+    fn records(unit: &Unit) -> Vec<Record> {
+        unit.group.algorithms().iter()
+            .flat_map(|&algorithm| (0..3).map(move |iteration| Record {
+                algorithm,
+                hash: unit.hash,
+                precision: unit.precision,
+                cardinality: unit.cardinality,
+                size: unit.size,
+                iteration,
+                seed: iteration as u64,
+                estimate: unit.cardinality as f64 + iteration as f64,
+                elapsed: Duration::from_millis(1),
+                memory: 64,
+            }))
+            .collect()
+    }
+
+    #[test]
+    fn resumed_table_drops_the_unfinished_units() {
+        let (table, manifest_path) = (path("table"), path("manifest"));
+        let units = plan_units(&[Group::GHLL, Group::HLL], &[HashFunction::AHash], &[8], &[100, 1000], &[10]);
+
+        // a run completing all units but the last, which stops while writing its rows
+        let manifest = Manifest::open(&manifest_path, 0).unwrap();
+        let out = ResultsWriter::create_table(&table).unwrap();
+        for unit in &units {
+            out.write(&records(unit)).unwrap();
+            if unit != units.last().unwrap() {
+                manifest.complete(unit).unwrap();
+            }
+        }
+        drop((out, manifest));
+        let content = fs::read_to_string(&table).unwrap();
+        fs::write(&table, &content[..content.len() - 10]).unwrap();
+
+        // the resumed run drops the rows of the last unit, including the truncated one, and writes them again
+        let manifest = Manifest::open(&manifest_path, 0).unwrap();
+        let out = ResultsWriter::resume_table(&table, &manifest).unwrap();
+        let content = fs::read_to_string(&table).unwrap();
+        let last = units.last().unwrap();
+        let written: usize = units.iter().filter(|&unit| unit != last).map(|unit| records(unit).len()).sum();
+        assert_eq!(content.lines().count(), 1 + written);
+        assert!(content.ends_with('\n'));
+        out.write(&records(last)).unwrap();
+        drop(out);
+
+        // every configuration holds each iteration once
+        let estimates = read_table(&table).unwrap();
+        let configurations: usize = units.iter().map(|unit| unit.group.algorithms().len()).sum();
+        assert_eq!(estimates.len(), configurations);
+        for (configuration, samples) in estimates {
+            assert_eq!(samples.iterations, vec![0, 1, 2], "{:?}", configuration);
+            assert_eq!(samples.estimates[2], configuration.cardinality as f64 + 2.0);
+        }
+
+        fs::remove_file(&table).unwrap();
+        fs::remove_file(&manifest_path).unwrap();
+    }
+}
@@ -0,0 +1,369 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::io::Write;
+
+use crate::analysis::{Configuration, Estimates, Samples, quantile};
+
+/// The outcome of testing two algorithms against each other on a single
+/// (hash, precision, cardinality, size) configuration
+///
+/// The estimates of both algorithms are paired by iteration, since the estimators of
+/// an iteration share the hash function. The relative standard errors are compared with
+/// a paired bootstrap confidence interval of their difference, and the variances with
+/// the Pitman-Morgan test for paired samples and the Brown-Forsythe variant of Levene's test.
+#[derive(Debug, Clone, Serialize)]
+pub struct PairedTest {
+    pub first: String,
+    pub second: String,
+    pub hash: String,
+    pub precision: u8,
+    pub cardinality: usize,
+    pub size: usize,
+    pub iterations: usize,
+    pub rse_first: f64,
+    pub rse_second: f64,
+    /// `rse_first - rse_second`
+    pub rse_difference: f64,
+    pub ci_lower: f64,
+    pub ci_upper: f64,
+    pub pitman_morgan_p: f64,
+    pub levene_p: f64,
+    /// the algorithm with the lower RSE if the confidence interval excludes zero
+    pub winner: Option<String>,
+}
+
+impl PairedTest {
+    const CSV_HEADER: &'static str = "first,second,hash,precision,cardinality,size,iterations,rse_first,rse_second,rse_difference,ci_lower,ci_upper,pitman_morgan_p,levene_p,winner";
+
+    #[allow(clippy::too_many_arguments)]
+    fn new<R: Rng>(
+        first: &str,
+        second: &str,
+        hash: &str,
+        precision: u8,
+        card: usize,
+        size: usize,
+        a: &Samples,
+        b: &Samples,
+        resamples: usize,
+        level: f64,
+        rng: &mut R,
+    ) -> Option<Self> {
+        // only the iterations gathered for both algorithms can be paired
+        let b_by_iteration: BTreeMap<_, _> = b.iterations.iter().zip(&b.estimates).collect();
+        let (a, b): (Vec<f64>, Vec<f64>) = a.iterations.iter().zip(&a.estimates)
+            .filter_map(|(iteration, &x)| b_by_iteration.get(iteration).map(|&&y| (x, y)))
+            .unzip();
+        let n = a.len();
+        // the Pitman-Morgan test needs at least one degree of freedom
+        if n < 3 {
+            return None;
+        }
+        let (a, b) = (a.as_slice(), b.as_slice());
+        let truth = card as f64;
+
+        let rse_first = variance(a).sqrt() / truth;
+        let rse_second = variance(b).sqrt() / truth;
+        let rse_difference = rse_first - rse_second;
+
+        // resample the pairs of estimates with replacement
+        let mut differences: Vec<_> = (0..resamples).map(|_| {
+            let indices: Vec<_> = (0..n).map(|_| rng.gen_range(0..n)).collect();
+            let a: Vec<_> = indices.iter().map(|&i| a[i]).collect();
+            let b: Vec<_> = indices.iter().map(|&i| b[i]).collect();
+            (variance(&a).sqrt() - variance(&b).sqrt()) / truth
+        }).collect();
+        differences.sort_by(f64::total_cmp);
+        let ci_lower = quantile(&differences, (1.0 - level) / 2.0);
+        let ci_upper = quantile(&differences, (1.0 + level) / 2.0);
+
+        let winner = if ci_lower > 0.0 {
+            Some(second.to_owned())
+        } else if ci_upper < 0.0 {
+            Some(first.to_owned())
+        } else {
+            None
+        };
+
+        Some(Self {
+            first: first.to_owned(),
+            second: second.to_owned(),
+            hash: hash.to_owned(),
+            precision,
+            cardinality: card,
+            size,
+            iterations: n,
+            rse_first,
+            rse_second,
+            rse_difference,
+            ci_lower,
+            ci_upper,
+            pitman_morgan_p: pitman_morgan(a, b),
+            levene_p: levene(a, b),
+            winner,
+        })
+    }
+
+    fn write_csv<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            self.first, self.second, self.hash, self.precision, self.cardinality, self.size, self.iterations,
+            self.rse_first, self.rse_second, self.rse_difference, self.ci_lower, self.ci_upper,
+            self.pitman_morgan_p, self.levene_p, self.winner.as_deref().unwrap_or("tie"),
+        )
+    }
+}
+
+/// Tests every pair of algorithms gathered with the same hash function on the same configuration,
+/// skipping the pairs with fewer than three iterations gathered for both algorithms
+///
+/// # Arguments
+///
+/// - `resamples` - the number of bootstrap resamples per pair
+/// - `level` - the confidence level of the bootstrap intervals, e.g. `0.95`
+/// - `seed` - the seed of the resampling, so that the report can be reproduced
+pub fn test_all(estimates: &Estimates, resamples: usize, level: f64, seed: u64) -> Vec<PairedTest> {
+    // group the algorithms by configuration
+    let mut configurations: BTreeMap<_, Vec<_>> = BTreeMap::new();
+    for (configuration, samples) in estimates {
+        let Configuration { hash, precision, cardinality, size, .. } = configuration;
+        configurations.entry((hash.as_str(), *precision, *cardinality, *size))
+            .or_default()
+            .push((configuration.algorithm.as_str(), samples));
+    }
+
+    let mut pairs = Vec::new();
+    for ((hash, precision, card, size), algorithms) in configurations {
+        for (i, first) in algorithms.iter().enumerate() {
+            for second in &algorithms[i + 1..] {
+                pairs.push((hash, precision, card, size, *first, *second));
+            }
+        }
+    }
+
+    pairs.into_par_iter()
+        .enumerate()
+        .filter_map(|(index, (hash, precision, card, size, (first, a), (second, b)))| {
+            let mut rng = StdRng::seed_from_u64(seed ^ index as u64);
+            PairedTest::new(first, second, hash, precision, card, size, a, b, resamples, level, &mut rng)
+        })
+        .collect()
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+// the unbiased sample variance, undefined for fewer than two values
+fn variance(values: &[f64]) -> f64 {
+    let mean = mean(values);
+    values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (values.len() as f64 - 1.0)
+}
+
+/// The p-value of the Pitman-Morgan test of equal variances of paired samples; the variances
+/// are equal exactly when the sums and the differences of the pairs are uncorrelated
+pub fn pitman_morgan(a: &[f64], b: &[f64]) -> f64 {
+    let n = a.len() as f64;
+    let sums: Vec<_> = a.iter().zip(b).map(|(x, y)| x + y).collect();
+    let differences: Vec<_> = a.iter().zip(b).map(|(x, y)| x - y).collect();
+
+    let (mean_sum, mean_difference) = (mean(&sums), mean(&differences));
+    let covariance = sums.iter().zip(&differences)
+        .map(|(s, d)| (s - mean_sum) * (d - mean_difference))
+        .sum::<f64>() / (n - 1.0);
+    // rounding may push the correlation of a constant sample slightly past -1 or 1
+    let r = (covariance / (variance(&sums) * variance(&differences)).sqrt()).clamp(-1.0, 1.0);
+
+    // identical samples have no differences to correlate
+    if !r.is_finite() {
+        return 1.0;
+    }
+
+    // the correlation follows Student's t distribution with `n - 2` degrees of freedom
+    let df = n - 2.0;
+    let t = r * (df / (1.0 - r * r)).sqrt();
+    regularized_beta(df / (df + t * t), df / 2.0, 0.5)
+}
+
+/// The p-value of the Brown-Forsythe variant of Levene's test of equal variances, i.e. the
+/// one-way ANOVA of the absolute deviations from the group medians
+pub fn levene(a: &[f64], b: &[f64]) -> f64 {
+    let deviations = |values: &[f64]| {
+        let mut sorted = values.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let median = quantile(&sorted, 0.5);
+        values.iter().map(|v| (v - median).abs()).collect::<Vec<_>>()
+    };
+    let (za, zb) = (deviations(a), deviations(b));
+    let (na, nb) = (za.len() as f64, zb.len() as f64);
+
+    let (mean_a, mean_b) = (mean(&za), mean(&zb));
+    let mean_all = (mean_a * na + mean_b * nb) / (na + nb);
+
+    let between = na * (mean_a - mean_all).powi(2) + nb * (mean_b - mean_all).powi(2);
+    let within = za.iter().map(|z| (z - mean_a).powi(2)).sum::<f64>()
+        + zb.iter().map(|z| (z - mean_b).powi(2)).sum::<f64>();
+
+    if within == 0.0 {
+        return if between == 0.0 { 1.0 } else { 0.0 };
+    }
+
+    // the statistic follows the F distribution with 1 and `na + nb - 2` degrees of freedom
+    let df = na + nb - 2.0;
+    let f = between / (within / df);
+    regularized_beta(df / (df + f), df / 2.0, 0.5)
+}
+
+// the regularized incomplete beta function `I_x(a, b)`, evaluated with
+// the continued fraction from Numerical Recipes
+fn regularized_beta(x: f64, a: f64, b: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+
+    let front = f64::exp(ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln());
+
+    // the continued fraction converges quickly for `x < (a + 1) / (a + b + 2)`
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_fraction(x, a, b) / a
+    } else {
+        1.0 - front * beta_fraction(1.0 - x, b, a) / b
+    }
+}
+
+// the continued fraction of the incomplete beta function, by the modified Lentz's method
+fn beta_fraction(x: f64, a: f64, b: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+
+    let mut c = 1.0;
+    let mut d = 1.0 - (a + b) * x / (a + 1.0);
+    d = 1.0 / if d.abs() < TINY { TINY } else { d };
+    let mut result = d;
+
+    for m in 1..=300 {
+        let m = m as f64;
+        for numerator in [
+            m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m)),
+            -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0)),
+        ] {
+            d = 1.0 + numerator * d;
+            d = 1.0 / if d.abs() < TINY { TINY } else { d };
+            c = 1.0 + numerator / c;
+            c = if c.abs() < TINY { TINY } else { c };
+            result *= d * c;
+        }
+
+        if (d * c - 1.0).abs() < 1e-15 {
+            break;
+        }
+    }
+
+    result
+}
+
+// the logarithm of the gamma function by the Lanczos approximation (g = 7, n = 9)
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    // the reflection formula for small arguments
+    if x < 0.5 {
+        return f64::ln(std::f64::consts::PI / f64::sin(std::f64::consts::PI * x)) - ln_gamma(1.0 - x);
+    }
+
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let series = COEFFICIENTS[1..].iter()
+        .enumerate()
+        .fold(COEFFICIENTS[0], |sum, (i, c)| sum + c / (x + i as f64 + 1.0));
+
+    0.5 * f64::ln(2.0 * std::f64::consts::PI) + (x + 0.5) * t.ln() - t + series.ln()
+}
+
+pub fn write_csv(path: &str, tests: &[PairedTest]) -> Result<(), io::Error> {
+    let mut out = io::BufWriter::new(fs::File::create(path)?);
+    writeln!(out, "{}", PairedTest::CSV_HEADER)?;
+    for test in tests {
+        test.write_csv(&mut out)?;
+    }
+
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the reference values are computed with the regularized incomplete beta function of mpmath
+    fn assert_close(value: f64, expected: f64) {
+        assert!((value - expected).abs() <= 1e-9 * expected, "{} instead of {}", value, expected);
+    }
+
+    fn samples(iterations: &[usize], estimates: &[f64]) -> Samples {
+        Samples { iterations: iterations.to_vec(), estimates: estimates.to_vec(), elapsed: Vec::new() }
+    }
+
+    const A: [f64; 8] = [10.1, 9.8, 10.4, 10.0, 9.7, 10.3, 10.2, 9.9];
+    const B: [f64; 8] = [10.3, 9.5, 10.9, 10.1, 9.2, 10.6, 10.4, 9.6];
+
+    #[test]
+    fn regularized_beta_matches_reference_values() {
+        assert_close(regularized_beta(0.5, 2.0, 3.0), 0.6875);
+        assert_close(regularized_beta(0.2, 1.0, 3.0), 0.488);
+        assert_close(regularized_beta(0.9, 5.0, 0.5), 0.316_642_915_020_012_26);
+        assert_close(regularized_beta(0.3, 15.0, 0.5), 2.445_601_036_995_876_3e-9);
+        assert_eq!(regularized_beta(0.0, 2.0, 3.0), 0.0);
+        assert_eq!(regularized_beta(1.0, 2.0, 3.0), 1.0);
+    }
+
+    #[test]
+    fn pitman_morgan_matches_reference_values() {
+        // computed from the ratio of the variances and the correlation of the samples
+        assert_close(pitman_morgan(&A, &B), 3.297_892_574_947_295e-6);
+        assert_close(pitman_morgan(&B, &A), 3.297_892_574_947_295e-6);
+        assert_eq!(pitman_morgan(&A, &A), 1.0);
+        // the sums and the differences of a constant and a varying sample are perfectly correlated
+        assert_eq!(pitman_morgan(&[10.0; 8], &B), 0.0);
+    }
+
+    #[test]
+    fn levene_matches_reference_values() {
+        assert_close(levene(&A, &B), 0.042_985_484_019_065_16);
+        // with two degrees of freedom the p-value is `1 - sqrt(F / (2 + F))`
+        assert_close(levene(&[1.0, 2.0, 6.0], &[5.0]), 0.559_774_546_837_188_1);
+        assert_eq!(levene(&A, &A), 1.0);
+    }
+
+    #[test]
+    fn pairs_the_estimates_by_iteration() {
+        let mut rng = StdRng::seed_from_u64(0);
+        // the second algorithm is missing the second iteration
+        let a = samples(&[0, 1, 2, 3, 4], &[10.0, 1000.0, 12.0, 11.0, 9.0]);
+        let b = samples(&[0, 2, 3, 4], &[10.0, 12.0, 11.0, 9.0]);
+
+        let test = PairedTest::new("a", "b", "hash", 4, 10, 100, &a, &b, 100, 0.95, &mut rng).unwrap();
+        assert_eq!(test.iterations, 4);
+        assert_eq!(test.rse_first, test.rse_second);
+        assert_eq!(test.pitman_morgan_p, 1.0);
+        assert_eq!(test.winner, None);
+
+        let c = samples(&[0, 5], &[10.0, 11.0]);
+        assert!(PairedTest::new("a", "c", "hash", 4, 10, 100, &a, &c, 100, 0.95, &mut rng).is_none());
+    }
+}
@@ -0,0 +1,309 @@
+use ahash::random_state::RandomState;
+use gen_data::keys::{Email, Key, KeyKind, Url, Uuid};
+use gumbel_estimation::{Fixed, GHLL, GHLLPlus, GHLLReal, RealRegister, Strategy, ULL};
+use hyperloglogplus::{HyperLogLog, HyperLogLogPF, HyperLogLogPlus};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use std::collections::HashSet;
+use std::fs::File;
+use std::hash::BuildHasher;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::sync::Mutex;
+
+use crate::{Group, Unit, iteration_seed, splitmix64};
+use crate::baselines::{Exact, KMV, LinearCounting, LogLogBeta};
+use crate::constants::{DATA_FORMAT, DESIGN, ITERATIONS};
+use crate::dataset::Dataset;
+use crate::error::HarnessError;
+use crate::hashes::{HashFunction, Murmur3Builder, SeededBuilder, SipBuilder, WeakBuilder, WyBuilder, Xxh3Builder};
+
+/// The points of the stream at which the estimates are snapshotted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Checkpoints {
+    /// after every `n` elements fed to the estimators
+    Elements(usize),
+    /// whenever the number of distinct elements fed to the estimators reaches a multiple of `n`
+    Distinct(usize),
+}
+
+/// A point of the stream at which the estimates are snapshotted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// the number of elements fed to the estimators
+    pub position: usize,
+    /// the number of distinct elements among them
+    pub distinct: usize,
+}
+
+impl Checkpoints {
+    /// Parses the checkpoints from `elements:N` or `distinct:N`, rejecting `N == 0`
+    pub fn from_arg(arg: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid checkpoints {}; expected elements:N or distinct:N with N > 0", arg);
+        let (kind, n) = arg.split_once(':').ok_or_else(invalid)?;
+        let n = n.parse().ok().filter(|&n| n > 0).ok_or_else(invalid)?;
+
+        match kind {
+            "elements" => Ok(Checkpoints::Elements(n)),
+            "distinct" => Ok(Checkpoints::Distinct(n)),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// The number of elements, or distinct elements, between consecutive checkpoints
+    pub fn interval(self) -> usize {
+        match self {
+            Checkpoints::Elements(n) | Checkpoints::Distinct(n) => n,
+        }
+    }
+
+    /// Locates the checkpoints in the data; the end of the data is always a checkpoint
+    ///
+    /// # Panics
+    ///
+    /// Panics if the interval is zero
+    pub fn locate<K: Key>(self, data: &[K]) -> Vec<Checkpoint> {
+        let mut seen = HashSet::new();
+        let mut checkpoints = Vec::new();
+
+        for (i, value) in data.iter().enumerate() {
+            let new = seen.insert(value);
+            let position = i + 1;
+            let reached = match self {
+                Checkpoints::Elements(n) => position % n == 0,
+                Checkpoints::Distinct(n) => new && seen.len() % n == 0,
+            };
+
+            if reached || position == data.len() {
+                checkpoints.push(Checkpoint { position, distinct: seen.len() });
+            }
+        }
+
+        checkpoints
+    }
+}
+
+/// A single estimate produced by an algorithm at a checkpoint of one iteration
+#[derive(Debug, Clone)]
+pub struct TrajectoryRecord {
+    pub algorithm: &'static str,
+    pub hash: HashFunction,
+    pub precision: u8,
+    pub cardinality: usize,
+    pub size: usize,
+    pub iteration: usize,
+    pub seed: u64,
+    /// the number of elements fed to the estimator
+    pub checkpoint: usize,
+    /// the number of distinct elements among them
+    pub true_distinct: usize,
+    pub estimate: f64,
+}
+
+impl TrajectoryRecord {
+    pub const CSV_HEADER: &'static str = "algorithm,hash,precision,cardinality,size,iteration,seed,checkpoint,true_distinct,estimate";
+
+    fn write_csv<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "{},{},{},{},{},{},{},{},{},{}",
+            self.algorithm, self.hash.name(), self.precision, self.cardinality, self.size,
+            self.iteration, self.seed, self.checkpoint, self.true_distinct, self.estimate,
+        )
+    }
+}
+
+/// The path of the trajectory table for the given key kind
+pub fn trajectory_path(key: KeyKind) -> String {
+    match key {
+        KeyKind::U64 => "../results/trajectory.csv".to_owned(),
+        key => format!("../results/trajectory_{}.csv", key.name()),
+    }
+}
+
+/// A sink for trajectory records shared between the gathering threads
+pub struct TrajectoryWriter {
+    out: Mutex<BufWriter<File>>,
+}
+
+impl TrajectoryWriter {
+    pub fn create(key: KeyKind) -> Result<Self, io::Error> {
+        let mut out = BufWriter::new(File::create(trajectory_path(key))?);
+        writeln!(out, "{}", TrajectoryRecord::CSV_HEADER)?;
+        out.flush()?;
+
+        Ok(Self { out: Mutex::new(out) })
+    }
+
+    /// Writes the records of a single unit
+    pub fn write(&self, records: &[TrajectoryRecord]) -> Result<(), io::Error> {
+        // write the whole unit at once, so that the rows of different threads are not interleaved
+        let mut out = self.out.lock().unwrap();
+        for record in records {
+            record.write_csv(&mut *out)?;
+        }
+        out.flush()
+    }
+}
+
+/// Gathers the trajectories of a unit for a dataset of the given key kind
+pub fn trajectory_key(key: KeyKind, unit: &Unit, run_seed: u64, checkpoints: Checkpoints) -> Result<Vec<TrajectoryRecord>, HarnessError> {
+    match key {
+        KeyKind::U64 => trajectory_hash::<u64>(unit, run_seed, checkpoints),
+        KeyKind::Uuid => trajectory_hash::<Uuid>(unit, run_seed, checkpoints),
+        KeyKind::Url => trajectory_hash::<Url>(unit, run_seed, checkpoints),
+        KeyKind::Email => trajectory_hash::<Email>(unit, run_seed, checkpoints),
+        KeyKind::Tuple => trajectory_hash::<(u32, u64)>(unit, run_seed, checkpoints),
+    }
+}
+
+/// Gathers the trajectories of a unit with the hash function of the unit
+pub fn trajectory_hash<K: Key>(unit: &Unit, run_seed: u64, checkpoints: Checkpoints) -> Result<Vec<TrajectoryRecord>, HarnessError> {
+    match unit.hash {
+        HashFunction::AHash => trajectory::<K, RandomState>(unit, run_seed, checkpoints),
+        HashFunction::SipHash => trajectory::<K, SipBuilder>(unit, run_seed, checkpoints),
+        HashFunction::Xxh3 => trajectory::<K, Xxh3Builder>(unit, run_seed, checkpoints),
+        HashFunction::WyHash => trajectory::<K, WyBuilder>(unit, run_seed, checkpoints),
+        HashFunction::Murmur3 => trajectory::<K, Murmur3Builder>(unit, run_seed, checkpoints),
+        HashFunction::Weak => trajectory::<K, WeakBuilder>(unit, run_seed, checkpoints),
+    }
+}
+
+/// Feeds the data to the estimators of a unit in a single pass per iteration, snapshotting
+/// the estimates at the checkpoints; the seeds of the iterations are the same as in `gather`
+pub fn trajectory<K: Key, S: SeededBuilder>(unit: &Unit, run_seed: u64, checkpoints: Checkpoints) -> Result<Vec<TrajectoryRecord>, HarnessError> {
+    let Unit { group, hash, precision: prec, cardinality: card, size } = *unit;
+
+    let data = Dataset::<K>::open(card, size, DATA_FORMAT)?.load()?;
+    let checkpoints = checkpoints.locate(&data);
+
+    let mut records = Vec::with_capacity(group.algorithms().len() * checkpoints.len() * ITERATIONS);
+
+    for iteration in 0..ITERATIONS {
+        let seed = iteration_seed(run_seed, DESIGN, unit, iteration);
+        let builder = S::with_seed(seed);
+        let mut rng = StdRng::seed_from_u64(splitmix64(seed));
+
+        let mut record = |algorithm, checkpoint: &Checkpoint, estimate| records.push(TrajectoryRecord {
+            algorithm,
+            hash,
+            precision: prec,
+            cardinality: card,
+            size,
+            iteration,
+            seed,
+            checkpoint: checkpoint.position,
+            true_distinct: checkpoint.distinct,
+            estimate,
+        });
+
+        match group {
+            Group::HLL => trace(&data, &checkpoints, HyperLogLogPF::<K, _>::new(prec, builder)?,
+                |e, value| e.insert(value), &[("HLL", |e| e.count())], &mut record),
+            Group::GHLL => trace(&data, &checkpoints, GHLL::with_precision_and_rng(prec, builder, &mut rng)?,
+                |e, value| e.add(value), &[("GHLLGeo", |e| e.count_geo()), ("GHLLHar", |e| e.count_har()),
+                    ("GHLLML", |e| e.estimate(Strategy::MaximumLikelihood)), ("GHLLBias", |e| e.estimate(Strategy::BiasCorrected))], &mut record),
+            Group::GHLLReal => trace_real(&data, &checkpoints, GHLLReal::<_, f32>::with_precision_and_rng(prec, builder, &mut rng)?,
+                group.algorithms(), &mut record),
+            Group::GHLLReal64 => trace_real(&data, &checkpoints, GHLLReal::<_, f64>::with_precision_and_rng(prec, builder, &mut rng)?,
+                group.algorithms(), &mut record),
+            Group::GHLLFixed8 => trace_real(&data, &checkpoints, GHLLReal::<_, Fixed<8>>::with_precision_and_rng(prec, builder, &mut rng)?,
+                group.algorithms(), &mut record),
+            Group::GHLLFixed12 => trace_real(&data, &checkpoints, GHLLReal::<_, Fixed<12>>::with_precision_and_rng(prec, builder, &mut rng)?,
+                group.algorithms(), &mut record),
+            Group::GHLLFixed16 => trace_real(&data, &checkpoints, GHLLReal::<_, Fixed<16>>::with_precision_and_rng(prec, builder, &mut rng)?,
+                group.algorithms(), &mut record),
+            Group::GHLLPlus => trace(&data, &checkpoints, GHLLPlus::with_precision_and_rng(prec, builder, &mut rng)?,
+                |e, value| e.add(value), &[("GHLLPlus", |e| e.count())], &mut record),
+            Group::ULL => trace(&data, &checkpoints, ULL::with_precision(prec, builder)?,
+                |e, value| e.add(value), &[("ULL", |e| e.count())], &mut record),
+            Group::HLLPlus => trace(&data, &checkpoints, HyperLogLogPlus::<K, _>::new(prec, builder)?,
+                |e, value| e.insert(value), &[("HLLPlus", |e| e.count())], &mut record),
+            Group::LogLogBeta => trace(&data, &checkpoints, LogLogBeta::with_precision(prec, builder)?,
+                |e, value| e.add(value), &[("LogLogBeta", |e| e.count())], &mut record),
+            Group::LinearCounting => trace(&data, &checkpoints, LinearCounting::with_precision(prec, builder)?,
+                |e, value| e.add(value), &[("LinearCounting", |e| e.count())], &mut record),
+            Group::KMV => trace(&data, &checkpoints, KMV::with_precision(prec, builder)?,
+                |e, value| e.add(value), &[("KMV", |e| e.count())], &mut record),
+            Group::Exact => trace(&data, &checkpoints, Exact::new(builder),
+                |e, value| e.add(value), &[("Exact", |e| e.count())], &mut record),
+        }
+    }
+
+    Ok(records)
+}
+
+// traces a `GHLLReal` estimator, recording its geometric and harmonic estimates as the given algorithms
+fn trace_real<K: Key, B: BuildHasher, R: RealRegister>(
+    data: &[K],
+    checkpoints: &[Checkpoint],
+    estimator: GHLLReal<B, R>,
+    algorithms: &'static [&'static str],
+    record: impl FnMut(&'static str, &Checkpoint, f64),
+) {
+    let estimates: [Estimate<GHLLReal<B, R>>; 2] = [(algorithms[0], |e| e.count_geo()), (algorithms[1], |e| e.count_har())];
+    trace(data, checkpoints, estimator, |e, value| e.add(value), &estimates, record);
+}
+
+// a named algorithm computing an estimate from an estimator
+type Estimate<E> = (&'static str, fn(&mut E) -> f64);
+
+// feeds the data to the estimator, recording each of its estimates at every checkpoint
+fn trace<K, E>(
+    data: &[K],
+    checkpoints: &[Checkpoint],
+    mut estimator: E,
+    add: impl Fn(&mut E, &K),
+    estimates: &[Estimate<E>],
+    mut record: impl FnMut(&'static str, &Checkpoint, f64),
+) {
+    let mut fed = 0;
+    for checkpoint in checkpoints {
+        for value in &data[fed..checkpoint.position] {
+            add(&mut estimator, value);
+        }
+        fed = checkpoint.position;
+
+        for (algorithm, estimate) in estimates {
+            record(algorithm, checkpoint, estimate(&mut estimator));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locate(checkpoints: Checkpoints, data: &[u64]) -> Vec<(usize, usize)> {
+        checkpoints.locate(data).into_iter().map(|checkpoint| (checkpoint.position, checkpoint.distinct)).collect()
+    }
+
+    #[test]
+    fn element_checkpoints_include_the_end_of_data() {
+        let data = [1, 2, 2, 3, 1, 4, 5];
+        assert_eq!(locate(Checkpoints::Elements(3), &data), [(3, 2), (6, 4), (7, 5)]);
+        assert_eq!(locate(Checkpoints::Elements(7), &data), [(7, 5)]);
+        assert_eq!(locate(Checkpoints::Elements(1), &data).len(), data.len());
+        assert_eq!(locate(Checkpoints::Elements(100), &data), [(7, 5)]);
+        assert!(locate(Checkpoints::Elements(3), &[]).is_empty());
+    }
+
+    #[test]
+    fn distinct_checkpoints_skip_repeated_values() {
+        // the repeated values keep the distinct count at a multiple without adding checkpoints
+        let data = [1, 1, 2, 2, 3, 4, 4, 5];
+        assert_eq!(locate(Checkpoints::Distinct(2), &data), [(3, 2), (6, 4), (8, 5)]);
+        assert_eq!(locate(Checkpoints::Distinct(1), &data), [(1, 1), (3, 2), (5, 3), (6, 4), (8, 5)]);
+
+        // the end of data is a checkpoint even if its value repeats
+        assert_eq!(locate(Checkpoints::Distinct(2), &[1, 2, 2]), [(2, 2), (3, 2)]);
+        assert_eq!(locate(Checkpoints::Distinct(5), &[1, 2, 2]), [(3, 2)]);
+    }
+
+    #[test]
+    fn checkpoints_are_parsed_with_a_positive_interval() {
+        assert_eq!(Checkpoints::from_arg("elements:10"), Ok(Checkpoints::Elements(10)));
+        assert_eq!(Checkpoints::from_arg("distinct:1000"), Ok(Checkpoints::Distinct(1000)));
+        for arg in ["elements:0", "distinct:0", "distinct:-1", "distinct", "unique:10", ""] {
+            assert!(Checkpoints::from_arg(arg).is_err(), "{}", arg);
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use std::collections::HashSet;
+
+use comparison::constants::{PRECISIONS, SMALL_CARDINALITIES, SMALL_DATA_SIZE_MULTIPLIES};
+use comparison::hashes::SeededBuilder;
+use gumbel_estimation::GHLLPlus;
+use rand::distributions::Uniform;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+// the number of seeds each precision is run with
+const ITERATIONS: u64 = 10;
+
+type Builder = ahash::RandomState;
+
+// GHLL+ with and without the exact mode, sharing the hash function and initial register values
+fn estimators(precision: u8, seed: u64) -> (GHLLPlus<Builder>, GHLLPlus<Builder>) {
+    let plain = GHLLPlus::with_precision_and_rng(precision, <Builder as SeededBuilder>::with_seed(seed), &mut StdRng::seed_from_u64(seed)).unwrap();
+
+    // count exactly as long as the hashes take at most the memory of the registers
+    let mut exact = plain.clone();
+    exact.set_exact_threshold(plain.memory_bytes() / std::mem::size_of::<u64>());
+
+    (exact, plain)
+}
+
+fn add_all(estimators: &mut [&mut GHLLPlus<Builder>], keys: impl IntoIterator<Item = u64>) {
+    for key in keys {
+        for estimator in estimators.iter_mut() {
+            estimator.add(&key);
+        }
+    }
+}
+
+// the estimates of both estimators on the small datasets of `cardinality` distinct random keys
+// and `cardinality * multiply` elements; the datasets are fed as a single stream, which holds
+// the elements of each dataset up to the order of its duplicates, which does not change the estimators
+fn small_estimates(precision: u8, seed: u64, multiply: usize) -> Vec<(usize, f64, f64)> {
+    let (mut exact, mut plain) = estimators(precision, seed);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut keys = Vec::new();
+    let mut universe = HashSet::new();
+
+    SMALL_CARDINALITIES.iter()
+        .map(|&cardinality| {
+            while keys.len() < cardinality {
+                let key: u64 = rng.gen();
+                if universe.insert(key) {
+                    add_all(&mut [&mut exact, &mut plain], [key]);
+                    keys.push(key);
+                }
+            }
+
+            let unif_index = Uniform::new(0, keys.len());
+            for _ in 0..multiply - 1 {
+                add_all(&mut [&mut exact, &mut plain], [keys[rng.sample(unif_index)]]);
+            }
+
+            (cardinality, exact.count(), plain.count())
+        })
+        .collect()
+}
+
+#[test]
+fn exact_up_to_the_threshold() {
+    for (precision, multiply, seed) in PRECISIONS.into_iter()
+        .flat_map(|precision| SMALL_DATA_SIZE_MULTIPLIES.map(|multiply| (precision, multiply)))
+        .flat_map(|(precision, multiply)| (0..ITERATIONS).map(move |seed| (precision, multiply, seed)))
+    {
+        let threshold = estimators(precision, seed).0.exact_threshold();
+        assert!(threshold > 0);
+
+        let (mut exact_error, mut plain_error) = (0.0, 0.0);
+        for (cardinality, exact, plain) in small_estimates(precision, seed, multiply) {
+            if cardinality <= threshold {
+                assert_eq!(exact, cardinality as f64, "precision {}, cardinality {}", precision, cardinality);
+                exact_error += f64::abs(exact / cardinality as f64 - 1.0);
+                plain_error += f64::abs(plain / cardinality as f64 - 1.0);
+            } else {
+                // the registers take over, which received every element all along
+                assert_eq!(exact, plain, "precision {}, cardinality {}", precision, cardinality);
+            }
+        }
+
+        // below the threshold the sketch alone is not exact, unless it has a single register per element
+        if threshold > 2 {
+            assert!(plain_error > exact_error, "precision {}", precision);
+        }
+    }
+}
+
+#[test]
+fn hands_over_one_element_past_the_threshold() {
+    for precision in PRECISIONS {
+        let (mut exact, mut plain) = estimators(precision, 0);
+        let threshold = exact.exact_threshold() as u64;
+
+        // one element below and at the threshold the count is exact, even with duplicates
+        add_all(&mut [&mut exact, &mut plain], 0..threshold - 1);
+        assert!(exact.is_exact());
+        assert_eq!(exact.count(), (threshold - 1) as f64);
+
+        add_all(&mut [&mut exact, &mut plain], (0..threshold).rev());
+        assert!(exact.is_exact());
+        assert_eq!(exact.count(), threshold as f64);
+
+        // one element past it, the sketch equals one which never counted exactly,
+        // since the registers received every element all along
+        add_all(&mut [&mut exact, &mut plain], [threshold]);
+        assert!(!exact.is_exact());
+        assert_eq!(exact.count(), plain.count());
+        assert_eq!(exact.to_bytes(), plain.to_bytes());
+
+        // the exact mode is not resumed once the registers took over
+        add_all(&mut [&mut exact, &mut plain], 0..threshold);
+        assert!(!exact.is_exact());
+        assert_eq!(exact.to_bytes(), plain.to_bytes());
+    }
+}
+
+#[test]
+fn merges_exact_with_exact_and_sketch() {
+    let (mut first, mut first_plain) = estimators(12, 0);
+    let (mut second, mut second_plain) = estimators(12, 0);
+    let threshold = first.exact_threshold() as u64;
+    add_all(&mut [&mut first, &mut first_plain], 0..200);
+    add_all(&mut [&mut second, &mut second_plain], 100..300);
+
+    // the union of two exact sketches within the threshold stays exact
+    let mut union = first.clone();
+    union.merge(&second).unwrap();
+    assert!(union.is_exact());
+    assert_eq!(union.count(), 300.0);
+
+    // the union exceeding the threshold hands over to the merged registers
+    let (mut large, mut large_plain) = estimators(12, 0);
+    add_all(&mut [&mut large, &mut large_plain], 150..threshold + 50);
+    assert!(large.is_exact());
+    let mut union = first.clone();
+    union.merge(&large).unwrap();
+    let mut union_plain = first_plain.clone();
+    union_plain.merge(&large_plain).unwrap();
+    assert!(!union.is_exact());
+    assert_eq!(union.to_bytes(), union_plain.to_bytes());
+
+    // a sketch which is no longer exact makes the union inexact, in either order
+    add_all(&mut [&mut second, &mut second_plain], 1_000..1_000 + threshold);
+    assert!(!second.is_exact());
+
+    let mut union = first.clone();
+    union.merge(&second).unwrap();
+    assert!(!union.is_exact());
+    let mut union_plain = first_plain.clone();
+    union_plain.merge(&second_plain).unwrap();
+    assert_eq!(union.to_bytes(), union_plain.to_bytes());
+
+    let mut union = second.clone();
+    union.merge(&first).unwrap();
+    assert!(!union.is_exact());
+    assert_eq!(union.to_bytes(), union_plain.to_bytes());
+}
+
+#[test]
+fn serializes_the_exact_mode() {
+    let (mut exact, mut plain) = estimators(12, 0);
+    let threshold = exact.exact_threshold() as u64;
+    add_all(&mut [&mut exact, &mut plain], 0..300);
+
+    for bytes in [exact.to_bytes(), exact.to_compressed_bytes()] {
+        let mut restored = GHLLPlus::from_bytes(&bytes, <Builder as SeededBuilder>::with_seed(0)).unwrap();
+        assert!(restored.is_exact());
+        assert_eq!(restored.exact_threshold(), exact.exact_threshold());
+        assert_eq!(restored.count(), 300.0);
+        assert_eq!(restored.to_bytes(), exact.to_bytes());
+
+        // the restored sketch keeps the hashes, so that it counts on exactly and hands over in time
+        let mut original = exact.clone();
+        add_all(&mut [&mut restored, &mut original], 200..threshold);
+        assert!(restored.is_exact());
+        assert_eq!(restored.count(), threshold as f64);
+
+        add_all(&mut [&mut restored, &mut original], [threshold]);
+        assert!(!restored.is_exact());
+        assert_eq!(restored.to_bytes(), original.to_bytes());
+    }
+
+    // a sketch past the threshold is serialized without the hashes
+    add_all(&mut [&mut exact, &mut plain], 0..=threshold);
+    assert_eq!(exact.to_bytes(), plain.to_bytes());
+}
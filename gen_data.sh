@@ -2,6 +2,16 @@
 
 cardinalities=()
 
+# dataset format: `txt` for one decimal element per line, `bin` for the binary format;
+# remember to set `DATA_FORMAT` in comparison/src/constants.rs accordingly
+
+format=txt
+
+# element kind: u64, uuid, url, email or tuple;
+# remember to set `KEY` in comparison/src/constants.rs accordingly
+
+key=u64
+
 # large datasets
 
 size_multiplies=(100)
@@ -21,8 +31,12 @@ done
 for card in "${cardinalities[@]}"; do
 	for mult in "${size_multiplies[@]}"; do
 		size=$((card * mult))
-		out="data/data_${card}_${size}.txt"
+		if [ "${key}" = "u64" ]; then
+			out="data/data_${card}_${size}.${format}"
+		else
+			out="data/data_${key}_${card}_${size}.${format}"
+		fi
 		echo "generating card=${card} size=${size} to ${out}"
-		cargo run --release -q --manifest-path="gen_data/Cargo.toml" "${out}" ${card} ${size}
+		cargo run --release -q --manifest-path="gen_data/Cargo.toml" "${out}" ${card} ${size} --key ${key}
 	done
 done
@@ -0,0 +1,142 @@
+use std::io;
+use std::io::Write;
+
+use crate::keys::KeyKind;
+
+/// The magic bytes opening every binary dataset file
+pub const MAGIC: [u8; 8] = *b"CEBDATA\0";
+
+/// The current version of the binary dataset format
+pub const VERSION: u32 = 2;
+
+/// The distribution used to draw the duplicate elements of a dataset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Distribution {
+    Uniform,
+}
+
+impl Distribution {
+    fn to_tag(self) -> u32 {
+        match self {
+            Distribution::Uniform => 0,
+        }
+    }
+
+    fn from_tag(tag: u32) -> Option<Self> {
+        match tag {
+            0 => Some(Distribution::Uniform),
+            _ => None,
+        }
+    }
+}
+
+/// The header of a binary dataset file
+///
+/// The header is followed by `size` elements encoded with `Key::write_binary`, which for `u64`
+/// keys are plain little-endian integers. All header fields are stored in little-endian order as
+/// well, and the header is padded to a multiple of 8 bytes so that the elements stay aligned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header {
+    pub card: u64,
+    pub size: u64,
+    pub seed: u64,
+    pub distribution: Distribution,
+    pub key: KeyKind,
+}
+
+impl Header {
+    /// The size of the encoded header in bytes
+    pub const SIZE: usize = 48;
+
+    pub fn write_to<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        out.write_all(&MAGIC)?;
+        out.write_all(&VERSION.to_le_bytes())?;
+        out.write_all(&self.distribution.to_tag().to_le_bytes())?;
+        out.write_all(&self.key.to_tag().to_le_bytes())?;
+        out.write_all(&[0; 4])?;
+        out.write_all(&self.card.to_le_bytes())?;
+        out.write_all(&self.size.to_le_bytes())?;
+        out.write_all(&self.seed.to_le_bytes())?;
+
+        Ok(())
+    }
+
+    pub fn read_from(bytes: &[u8]) -> io::Result<Self> {
+        if bytes.len() < Self::SIZE {
+            return Err(invalid_data("file too short to contain a dataset header"));
+        }
+        if bytes[0..8] != MAGIC {
+            return Err(invalid_data("not a binary dataset file"));
+        }
+
+        let version = read_u32(bytes, 8);
+        if version != VERSION {
+            return Err(invalid_data(format!("unsupported dataset format version {}", version)));
+        }
+
+        let tag = read_u32(bytes, 12);
+        let distribution = Distribution::from_tag(tag)
+            .ok_or_else(|| invalid_data(format!("unknown distribution tag {}", tag)))?;
+
+        let tag = read_u32(bytes, 16);
+        let key = KeyKind::from_tag(tag)
+            .ok_or_else(|| invalid_data(format!("unknown key tag {}", tag)))?;
+
+        Ok(Self {
+            card: read_u64(bytes, 24),
+            size: read_u64(bytes, 32),
+            seed: read_u64(bytes, 40),
+            distribution,
+            key,
+        })
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+fn invalid_data<E>(error: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header() -> Header {
+        Header { card: 1000, size: 100_000, seed: 42, distribution: Distribution::Uniform, key: KeyKind::Email }
+    }
+
+    #[test]
+    fn header_round_trips() {
+        let mut bytes = Vec::new();
+        header().write_to(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), Header::SIZE);
+        assert_eq!(Header::read_from(&bytes).unwrap(), header());
+    }
+
+    #[test]
+    fn invalid_headers_are_rejected() {
+        let mut bytes = Vec::new();
+        header().write_to(&mut bytes).unwrap();
+
+        let corrupt = |offset: usize, value: u8| {
+            let mut bytes = bytes.clone();
+            bytes[offset] = value;
+            Header::read_from(&bytes).unwrap_err().to_string()
+        };
+        assert!(corrupt(0, b'X').contains("not a binary dataset"));
+        assert!(corrupt(8, 1).contains("unsupported dataset format version 1"));
+        assert!(corrupt(12, 7).contains("unknown distribution tag 7"));
+        assert!(corrupt(16, 9).contains("unknown key tag 9"));
+        assert!(Header::read_from(&bytes[..Header::SIZE - 1]).unwrap_err().to_string().contains("too short"));
+    }
+}
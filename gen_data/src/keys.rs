@@ -0,0 +1,379 @@
+use rand::Rng;
+use rand::distributions::{Alphanumeric, Uniform};
+use std::fmt::Write as _;
+use std::hash::Hash;
+use std::io;
+use std::io::Write;
+
+/// The kinds of keys a dataset can consist of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyKind {
+    U64,
+    Uuid,
+    Url,
+    Email,
+    Tuple,
+}
+
+impl KeyKind {
+    pub const ALL: [KeyKind; 5] = [KeyKind::U64, KeyKind::Uuid, KeyKind::Url, KeyKind::Email, KeyKind::Tuple];
+
+    /// The name of the key kind, as used on the command line and in file names
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyKind::U64 => "u64",
+            KeyKind::Uuid => "uuid",
+            KeyKind::Url => "url",
+            KeyKind::Email => "email",
+            KeyKind::Tuple => "tuple",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+
+    pub(crate) fn to_tag(self) -> u32 {
+        self as u32
+    }
+
+    pub(crate) fn from_tag(tag: u32) -> Option<Self> {
+        Self::ALL.get(tag as usize).copied()
+    }
+}
+
+/// An element type that datasets can be generated for and read back from
+pub trait Key: Hash + Eq + Clone + Sized {
+    const KIND: KeyKind;
+
+    /// The number of bytes of every key in the binary format, or `None` if it varies between keys
+    const BINARY_SIZE: Option<usize>;
+
+    /// Draws a random key
+    fn random<R: Rng>(rng: &mut R) -> Self;
+
+    /// Writes the key as a single line of text, without the line break
+    fn write_text<W: Write>(&self, out: &mut W) -> io::Result<()>;
+
+    fn parse_text(line: &str) -> io::Result<Self>;
+
+    fn write_binary<W: Write>(&self, out: &mut W) -> io::Result<()>;
+
+    /// Reads a key from the start of `bytes`, returning it along with the number of bytes consumed
+    fn read_binary(bytes: &[u8]) -> io::Result<(Self, usize)>;
+}
+
+/// A random version 4 UUID in its hyphenated textual form
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Uuid(pub String);
+
+/// A random URL with a varying number of path segments and an optional query
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Url(pub String);
+
+/// A random email address on either a popular or a random domain
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Email(pub String);
+
+impl Key for u64 {
+    const KIND: KeyKind = KeyKind::U64;
+    const BINARY_SIZE: Option<usize> = Some(8);
+
+    fn random<R: Rng>(rng: &mut R) -> Self {
+        rng.sample(Uniform::new(0, u64::MAX))
+    }
+
+    fn write_text<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        write!(out, "{}", self)
+    }
+
+    fn parse_text(line: &str) -> io::Result<Self> {
+        line.trim().parse::<u64>()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    fn write_binary<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        out.write_all(&self.to_le_bytes())
+    }
+
+    #[inline]
+    fn read_binary(bytes: &[u8]) -> io::Result<(Self, usize)> {
+        let bytes = bytes.get(..8).ok_or_else(truncated)?;
+        Ok((u64::from_le_bytes(bytes.try_into().unwrap()), 8))
+    }
+}
+
+/// A pair of a 32-bit and a 64-bit identifier, e.g. a tenant and a user
+impl Key for (u32, u64) {
+    const KIND: KeyKind = KeyKind::Tuple;
+    const BINARY_SIZE: Option<usize> = Some(12);
+
+    fn random<R: Rng>(rng: &mut R) -> Self {
+        (rng.gen_range(0..1024), rng.gen())
+    }
+
+    fn write_text<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        write!(out, "{},{}", self.0, self.1)
+    }
+
+    fn parse_text(line: &str) -> io::Result<Self> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("invalid tuple key {}", line));
+        let (first, second) = line.trim().split_once(',').ok_or_else(invalid)?;
+
+        Ok((
+            first.parse().map_err(|_| invalid())?,
+            second.parse().map_err(|_| invalid())?,
+        ))
+    }
+
+    fn write_binary<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        out.write_all(&self.0.to_le_bytes())?;
+        out.write_all(&self.1.to_le_bytes())
+    }
+
+    fn read_binary(bytes: &[u8]) -> io::Result<(Self, usize)> {
+        let bytes = bytes.get(..12).ok_or_else(truncated)?;
+        let first = u32::from_le_bytes(bytes[0..4].try_into().unwrap());
+        let second = u64::from_le_bytes(bytes[4..12].try_into().unwrap());
+
+        Ok(((first, second), 12))
+    }
+}
+
+// implement `Key` for a string newtype; text keys are stored verbatim
+// and binary keys are prefixed with their little-endian `u32` length
+macro_rules! impl_string_key {
+    ($ty:ident, $kind:expr, $gen:expr) => {
+        impl Key for $ty {
+            const KIND: KeyKind = $kind;
+            const BINARY_SIZE: Option<usize> = None;
+
+            fn random<R: Rng>(rng: &mut R) -> Self {
+                $ty($gen(rng))
+            }
+
+            fn write_text<W: Write>(&self, out: &mut W) -> io::Result<()> {
+                out.write_all(self.0.as_bytes())
+            }
+
+            fn parse_text(line: &str) -> io::Result<Self> {
+                Ok($ty(line.trim().to_owned()))
+            }
+
+            fn write_binary<W: Write>(&self, out: &mut W) -> io::Result<()> {
+                out.write_all(&(self.0.len() as u32).to_le_bytes())?;
+                out.write_all(self.0.as_bytes())
+            }
+
+            fn read_binary(bytes: &[u8]) -> io::Result<(Self, usize)> {
+                let (value, len) = read_string(bytes)?;
+                Ok(($ty(value), len))
+            }
+        }
+    };
+}
+
+impl_string_key!(Uuid, KeyKind::Uuid, random_uuid);
+impl_string_key!(Url, KeyKind::Url, random_url);
+impl_string_key!(Email, KeyKind::Email, random_email);
+
+// popular top-level domains, roughly ordered by frequency
+const TLDS: [&str; 8] = ["com", "com", "com", "org", "net", "io", "de", "pl"];
+
+// popular email providers; the remaining addresses use random company domains
+const EMAIL_PROVIDERS: [&str; 6] = ["gmail.com", "yahoo.com", "outlook.com", "hotmail.com", "icloud.com", "proton.me"];
+
+// create a random lowercase alphanumeric word with a length in the given range
+fn random_word<R: Rng>(rng: &mut R, min_len: usize, max_len: usize) -> String {
+    let len = rng.gen_range(min_len..=max_len);
+    rng.sample_iter(Alphanumeric)
+        .take(len)
+        .map(|c| (c as char).to_ascii_lowercase())
+        .collect()
+}
+
+// create a version 4 UUID, e.g. `1b4e28ba-2fa1-4d2e-883f-0016d3cca427`
+fn random_uuid<R: Rng>(rng: &mut R) -> String {
+    let mut bytes: [u8; 16] = rng.gen();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let mut uuid = String::with_capacity(36);
+    for (i, byte) in bytes.iter().enumerate() {
+        if matches!(i, 4 | 6 | 8 | 10) {
+            uuid.push('-');
+        }
+        write!(uuid, "{:02x}", byte).unwrap();
+    }
+
+    uuid
+}
+
+// create a URL with 0 to 5 path segments and a query in 30% of the cases,
+// which gives lengths between about 15 and 120 characters
+fn random_url<R: Rng>(rng: &mut R) -> String {
+    let mut url = String::from("https://");
+    if rng.gen_bool(0.5) {
+        url.push_str("www.");
+    }
+    url.push_str(&random_word(rng, 3, 12));
+    url.push('.');
+    url.push_str(TLDS[rng.gen_range(0..TLDS.len())]);
+
+    // the number of segments is roughly geometric
+    let mut segments = 0;
+    while segments < 5 && rng.gen_bool(0.6) {
+        url.push('/');
+        url.push_str(&random_word(rng, 2, 12));
+        segments += 1;
+    }
+
+    if rng.gen_bool(0.3) {
+        write!(url, "?{}={}", random_word(rng, 1, 6), random_word(rng, 1, 16)).unwrap();
+    }
+
+    url
+}
+
+// create an email address, e.g. `john.smith84@gmail.com`
+fn random_email<R: Rng>(rng: &mut R) -> String {
+    let mut email = random_word(rng, 2, 10);
+    if rng.gen_bool(0.5) {
+        email.push('.');
+        email.push_str(&random_word(rng, 2, 12));
+    }
+    if rng.gen_bool(0.4) {
+        write!(email, "{}", rng.gen_range(0..100)).unwrap();
+    }
+
+    email.push('@');
+    if rng.gen_bool(0.7) {
+        email.push_str(EMAIL_PROVIDERS[rng.gen_range(0..EMAIL_PROVIDERS.len())]);
+    } else {
+        email.push_str(&random_word(rng, 3, 15));
+        email.push('.');
+        email.push_str(TLDS[rng.gen_range(0..TLDS.len())]);
+    }
+
+    email
+}
+
+fn read_string(bytes: &[u8]) -> io::Result<(String, usize)> {
+    let len_bytes = bytes.get(..4).ok_or_else(truncated)?;
+    let len = u32::from_le_bytes(len_bytes.try_into().unwrap()) as usize;
+    let value = bytes.get(4..4 + len).ok_or_else(truncated)?;
+    let value = String::from_utf8(value.to_vec())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    Ok((value, 4 + len))
+}
+
+fn truncated() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "truncated binary key")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Format, generate};
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use std::collections::HashSet;
+    use std::fmt::Debug;
+    use std::io::BufRead;
+
+    const CARD: usize = 500;
+    const SIZE: usize = 2000;
+
+    // reads back the keys of a generated file in either format
+    fn read<K: Key>(bytes: &[u8], format: Format) -> Vec<K> {
+        match format {
+            Format::Text => bytes.lines().map(|line| K::parse_text(&line.unwrap()).unwrap()).collect(),
+            Format::Binary => {
+                let mut elements = &bytes[crate::binary::Header::SIZE..];
+                let mut keys = Vec::new();
+                while !elements.is_empty() {
+                    let (key, len) = K::read_binary(elements).unwrap();
+                    if let Some(size) = K::BINARY_SIZE {
+                        assert_eq!(len, size);
+                    }
+                    elements = &elements[len..];
+                    keys.push(key);
+                }
+                keys
+            }
+        }
+    }
+
+    // generates files of the keys in both formats and checks they hold the same `CARD` distinct keys,
+    // then writes and reads back single keys; returns the kind of the keys
+    fn round_trip<K: Key + Debug>() -> KeyKind {
+        let files = [Format::Text, Format::Binary].map(|format| {
+            let mut bytes = Vec::new();
+            generate::<K, _>(&mut bytes, CARD, SIZE, 7, format).unwrap();
+            read::<K>(&bytes, format)
+        });
+        assert_eq!(files[0], files[1], "{}", K::KIND.name());
+        assert_eq!(files[0].len(), SIZE);
+        assert_eq!(files[0].iter().collect::<HashSet<_>>().len(), CARD);
+
+        let mut rng = StdRng::seed_from_u64(0);
+        for key in (0..1000).map(|_| K::random(&mut rng)) {
+            let mut text = Vec::new();
+            key.write_text(&mut text).unwrap();
+            assert_eq!(K::parse_text(std::str::from_utf8(&text).unwrap()).unwrap(), key);
+
+            let mut binary = Vec::new();
+            key.write_binary(&mut binary).unwrap();
+            assert_eq!(K::read_binary(&binary).unwrap(), (key.clone(), binary.len()));
+            assert!(K::read_binary(&binary[..binary.len() - 1]).is_err());
+        }
+
+        K::KIND
+    }
+
+    // the lengths of many random keys
+    fn lengths<K: Key>(len: impl Fn(&K) -> usize) -> (usize, usize) {
+        let mut rng = StdRng::seed_from_u64(1);
+        (0..10_000).map(|_| len(&K::random(&mut rng)))
+            .fold((usize::MAX, 0), |(min, max), len| (min.min(len), max.max(len)))
+    }
+
+    #[test]
+    fn keys_of_every_kind_round_trip() {
+        let kinds = [
+            round_trip::<u64>(),
+            round_trip::<(u32, u64)>(),
+            round_trip::<Uuid>(),
+            round_trip::<Url>(),
+            round_trip::<Email>(),
+        ];
+        for kind in KeyKind::ALL {
+            assert!(kinds.contains(&kind));
+            assert_eq!(KeyKind::from_name(kind.name()), Some(kind));
+            assert_eq!(KeyKind::from_tag(kind.to_tag()), Some(kind));
+        }
+    }
+
+    #[test]
+    fn string_keys_have_the_expected_lengths() {
+        assert_eq!(lengths::<Uuid>(|key| key.0.len()), (36, 36));
+
+        // 8 + 3 + 1 + 2 characters of the shortest host up to 5 segments of 13 and a query of 24
+        let (min, max) = lengths::<Url>(|key| key.0.len());
+        assert!((14..=20).contains(&min) && (80..=117).contains(&max), "{} {}", min, max);
+
+        // 2 + 1 + 6 characters of the shortest address up to a local part of 25 and a domain of 19
+        let (min, max) = lengths::<Email>(|key| key.0.len());
+        assert!((9..=14).contains(&min) && (35..=45).contains(&max), "{} {}", min, max);
+
+        let mut rng = StdRng::seed_from_u64(2);
+        for _ in 0..1000 {
+            let Uuid(uuid) = Uuid::random(&mut rng);
+            assert_eq!(&uuid[14..15], "4");
+            assert!(uuid.split('-').map(str::len).eq([8, 4, 4, 4, 12]));
+            assert!(Url::random(&mut rng).0.starts_with("https://"));
+            assert_eq!(Email::random(&mut rng).0.matches('@').count(), 1);
+        }
+    }
+}
@@ -1,31 +1,95 @@
-use rand::{Rng, thread_rng};
+use rand::{Rng, SeedableRng};
 use rand::distributions::Uniform;
+use rand::rngs::StdRng;
 use std::collections::HashSet;
-use std::fs::File;
 use std::io;
 use std::io::Write;
 
-pub fn generate(out: &mut File, card: usize, size: usize) -> io::Result<()> {
-    let mut rng = thread_rng();
-    let unif_elem = Uniform::new(0, u64::MAX);
+pub mod binary;
+pub mod keys;
+
+use binary::{Distribution, Header};
+use keys::{Key, KeyKind};
+
+/// The on-disk format of a dataset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// one decimal element per line
+    Text,
+    /// a `binary::Header` followed by binary-encoded elements
+    Binary,
+}
+
+impl Format {
+    /// The file extension used by datasets of this format
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Text => "txt",
+            Format::Binary => "bin",
+        }
+    }
+
+    /// Deduces the format from a file path, defaulting to the text format
+    pub fn from_path(path: &str) -> Self {
+        if path.ends_with(".bin") {
+            Format::Binary
+        } else {
+            Format::Text
+        }
+    }
+}
+
+/// The name of a dataset file without its extension; `u64` datasets keep
+/// the `data_{card}_{size}` naming, other keys add their name after `data_`
+pub fn dataset_name(key: KeyKind, card: usize, size: usize) -> String {
+    match key {
+        KeyKind::U64 => format!("data_{}_{}", card, size),
+        key => format!("data_{}_{}_{}", key.name(), card, size),
+    }
+}
+
+pub fn generate<K: Key, W: Write>(out: &mut W, card: usize, size: usize, seed: u64, format: Format) -> io::Result<()> {
+    let mut rng = StdRng::seed_from_u64(seed);
     let unif_index = Uniform::new(0, card);
 
+    // keep the universe in generation order, so that a seed always yields the same file
     let mut universe = HashSet::new();
+    let mut universe_vec = Vec::with_capacity(card);
     while universe.len() < card {
-        let elem = rng.sample(unif_elem);
-        universe.insert(elem);
+        let elem = K::random(&mut rng);
+        if universe.insert(elem.clone()) {
+            universe_vec.push(elem);
+        }
     }
 
-    universe.iter().try_for_each(|elem| {
-        writeln!(out, "{}", elem)
+    if format == Format::Binary {
+        let header = Header {
+            card: card as u64,
+            size: size as u64,
+            seed,
+            distribution: Distribution::Uniform,
+            key: K::KIND,
+        };
+        header.write_to(out)?;
+    }
+
+    let write_elem = |out: &mut W, elem: &K| match format {
+        Format::Text => {
+            elem.write_text(out)?;
+            writeln!(out)
+        }
+        Format::Binary => elem.write_binary(out),
+    };
+
+    universe_vec.iter().try_for_each(|elem| {
+        write_elem(out, elem)
     })?;
 
-    let universe_vec: Vec<u64> = universe.into_iter().collect();
     let no_duplicates = size - card;
     (0..no_duplicates).try_for_each(|_| {
         let index = rng.sample(unif_index);
-        let elem = universe_vec[index];
-        writeln!(out, "{}", elem)
+        let elem = &universe_vec[index];
+        write_elem(out, elem)
     })?;
 
     Ok(())
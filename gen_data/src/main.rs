@@ -1,40 +1,80 @@
+use rand::{Rng, thread_rng};
 use std::env;
 use std::error::Error;
 use std::fs::File;
+use std::io::{BufWriter, Write};
 
-use gen_data::generate;
+use gen_data::{Format, generate};
+use gen_data::keys::{Email, KeyKind, Url, Uuid};
 
 fn print_help() {
-    println!("Usage: cargo run <output_file> <card> <size>\n");
+    println!("Usage: cargo run <output_file> <card> <size> [--seed <seed>] [--key <key>]\n");
     println!("Arguments:");
-    println!("- output_file - the file data will be saved to");
+    println!("- output_file - the file data will be saved to; files ending with `.bin` are written in the binary format");
     println!("- card - the cardinality of the underlying dataset");
     println!("- size - the total size of the dataset");
+    println!("- seed - the seed of the generator; chosen at random if not provided");
+    println!("- key - the kind of elements to generate: u64 (default), uuid, url, email or tuple");
     println!("Example:");
     println!("cargo run data_1000_100000.txt 1000 100000");
+    println!("cargo run data_url_1000_100000.bin 1000 100000 --seed 42 --key url");
 }
 
-fn parse_args() -> Result<(File, usize, usize), Box<dyn Error>> {
+struct Args {
+    out: File,
+    format: Format,
+    card: usize,
+    size: usize,
+    seed: u64,
+    key: KeyKind,
+}
+
+fn parse_args() -> Result<Args, Box<dyn Error>> {
     let args: Vec<String> = env::args().collect::<Vec<_>>();
-    if args.len() != 4 {
+    if args.len() < 4 || !args[4..].chunks_exact(2).remainder().is_empty() {
         print_help();
         return Err("Incorrect number of arguments provided".into());
     }
 
     let out = File::create(&args[1])?;
+    let format = Format::from_path(&args[1]);
     let card = args[2].parse::<usize>()?;
     let size = args[3].parse::<usize>()?;
 
+    let mut seed = None;
+    let mut key = KeyKind::U64;
+    for option in args[4..].chunks_exact(2) {
+        match option[0].as_str() {
+            "--seed" => seed = Some(option[1].parse::<u64>()?),
+            "--key" => key = KeyKind::from_name(&option[1])
+                .ok_or_else(|| format!("unknown key kind {}", option[1]))?,
+            other => {
+                print_help();
+                return Err(format!("unknown option {}", other).into());
+            }
+        }
+    }
+    let seed = seed.unwrap_or_else(|| thread_rng().gen());
+
     if card > size {
         return Err("dataset size has to be at least the size of its cardinality".into());
     }
 
-    Ok((out, card, size))
+    Ok(Args { out, format, card, size, seed, key })
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let (mut out, card, size) = parse_args()?;
-    generate(&mut out, card, size)?;
+    let args = parse_args()?;
+    let mut out = BufWriter::new(args.out);
+    let (card, size, seed, format) = (args.card, args.size, args.seed, args.format);
+    match args.key {
+        KeyKind::U64 => generate::<u64, _>(&mut out, card, size, seed, format)?,
+        KeyKind::Uuid => generate::<Uuid, _>(&mut out, card, size, seed, format)?,
+        KeyKind::Url => generate::<Url, _>(&mut out, card, size, seed, format)?,
+        KeyKind::Email => generate::<Email, _>(&mut out, card, size, seed, format)?,
+        KeyKind::Tuple => generate::<(u32, u64), _>(&mut out, card, size, seed, format)?,
+    }
+    out.flush()?;
 
     Ok(())
 }
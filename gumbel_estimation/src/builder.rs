@@ -0,0 +1,370 @@
+use ahash::RandomState;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use core::hash::BuildHasher;
+
+use crate::common::*;
+use crate::sketch::Strategy;
+use crate::ghll_real::sealed::Register;
+use crate::{GHLL, GHLLPlus, GHLLReal, RealRegister};
+
+/// A builder of the Gumbel estimators, which validates the whole configuration up front
+///
+/// Options left unset take the defaults of the built estimator, e.g. the geometric strategy
+/// of `GHLL` and the occupied registers strategy with the low-range correction of `GHLLPlus`;
+/// the exact mode is disabled unless a threshold is set.
+/// Options an estimator does not support are rejected by its `build_*` method with
+/// `GumbelError::UnsupportedConfiguration`.
+#[derive(Debug, Clone)]
+pub struct GumbelSketchBuilder<B = RandomState> {
+    precision: u8,
+    register_width: Option<u8>,
+    hasher: B,
+    seed: Option<u64>,
+    strategy: Option<Strategy>,
+    low_range_correction: Option<bool>,
+    exact_threshold: Option<usize>,
+    fixed_range: Option<(f64, f64)>,
+    sparse: bool,
+}
+
+impl GumbelSketchBuilder<RandomState> {
+    /// The precision used unless set otherwise
+    pub const DEFAULT_PRECISION: u8 = 12;
+
+    /// The range of the fixed-point registers of `GHLLReal` used unless set otherwise,
+    /// which holds the registers up to about `e^24` elements per register
+    pub const DEFAULT_FIXED_RANGE: (f64, f64) = (-4.0, 28.0);
+
+    /// Creates a builder with the default precision and a randomly seeded `ahash` hasher
+    pub fn new() -> Self {
+        Self {
+            precision: Self::DEFAULT_PRECISION,
+            register_width: None,
+            hasher: RandomState::new(),
+            seed: None,
+            strategy: None,
+            low_range_correction: None,
+            exact_threshold: None,
+            fixed_range: None,
+            sparse: false,
+        }
+    }
+}
+
+impl Default for GumbelSketchBuilder<RandomState> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B: BuildHasher> GumbelSketchBuilder<B> {
+    /// Sets the precision, i.e. the binary logarithm of the number of registers;
+    /// the accepted values lie in the range {4, 5, ..., 16}
+    pub fn precision(mut self, precision: u8) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    /// Sets the width of the registers in bits; `GHLL` and `GHLLPlus` store 5-bit registers
+    /// and `GHLLReal` the 32-bit or 64-bit floats or the 8-bit, 12-bit or 16-bit fixed-point
+    /// values of its register type
+    pub fn register_width(mut self, width: u8) -> Self {
+        self.register_width = Some(width);
+        self
+    }
+
+    /// Sets the hash builder used for hashing the added values
+    pub fn hasher<C: BuildHasher>(self, hasher: C) -> GumbelSketchBuilder<C> {
+        GumbelSketchBuilder {
+            precision: self.precision,
+            register_width: self.register_width,
+            hasher,
+            seed: self.seed,
+            strategy: self.strategy,
+            low_range_correction: self.low_range_correction,
+            exact_threshold: self.exact_threshold,
+            fixed_range: self.fixed_range,
+            sparse: self.sparse,
+        }
+    }
+
+    /// Sets the seed of the generator of the initial register values; estimators built with
+    /// the same seed and hasher can be merged. Without a seed, the values are drawn from the
+    /// entropy of the system, which requires the `std` feature.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Sets the strategy used by the `count` method of the estimator
+    pub fn strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = Some(strategy);
+        self
+    }
+
+    /// Enables or disables linear counting while many registers are still free
+    pub fn low_range_correction(mut self, enabled: bool) -> Self {
+        self.low_range_correction = Some(enabled);
+        self
+    }
+
+    /// Sets the number of distinct elements counted exactly before the registers take over,
+    /// or disables the exact mode if 0; e.g. the hashes of 405 elements take the memory
+    /// of the registers at precision 12
+    pub fn exact_threshold(mut self, threshold: usize) -> Self {
+        self.exact_threshold = Some(threshold);
+        self
+    }
+
+    /// Sets the range `(min, max)` the fixed-point registers of `GHLLReal` quantise their values to
+    pub fn fixed_range(mut self, min: f64, max: f64) -> Self {
+        self.fixed_range = Some((min, max));
+        self
+    }
+
+    /// Enables or disables the sparse representation of the registers at low cardinalities;
+    /// no estimator implements it yet, so enabling it makes every `build_*` method fail
+    /// with `GumbelError::UnsupportedConfiguration`
+    pub fn sparse(mut self, enabled: bool) -> Self {
+        self.sparse = enabled;
+        self
+    }
+
+    /// Builds a `GHLL` estimator, which supports all strategies
+    /// with or without the low-range correction and the exact mode
+    pub fn build_ghll(self) -> Result<GHLL<B>, GumbelError> {
+        self.validate(Capabilities {
+            estimator: "GHLL",
+            register_width: 5,
+            strategies: &Strategy::ALL,
+            low_range_correction: true,
+            exact_mode: true,
+            fixed_range: false,
+        })?;
+
+        let mut rng = self.rng();
+        let mut estimator = GHLL::with_precision_and_rng(self.precision, self.hasher, &mut rng)?;
+        if let Some(strategy) = self.strategy {
+            estimator.set_strategy(strategy);
+        }
+        if let Some(enabled) = self.low_range_correction {
+            estimator.set_low_range_correction(enabled);
+        }
+        if let Some(threshold) = self.exact_threshold {
+            estimator.set_exact_threshold(threshold);
+        }
+
+        Ok(estimator)
+    }
+
+    /// Builds a `GHLLReal` estimator with floating-point or fixed-point registers, which supports
+    /// all strategies but the occupied registers one, since it does not track the free registers,
+    /// and the range of the fixed-point registers
+    pub fn build_ghll_real<R: RealRegister>(self) -> Result<GHLLReal<B, R>, GumbelError> {
+        self.validate(Capabilities {
+            estimator: "GHLLReal",
+            register_width: R::WIDTH,
+            strategies: &[Strategy::Geometric, Strategy::Harmonic, Strategy::MaximumLikelihood, Strategy::BiasCorrected],
+            low_range_correction: false,
+            exact_mode: false,
+            fixed_range: <R as Register>::FIXED,
+        })?;
+
+        let strategy = self.strategy.unwrap_or(Strategy::Geometric);
+        let range = self.fixed_range.unwrap_or(GumbelSketchBuilder::DEFAULT_FIXED_RANGE);
+        let mut rng = self.rng();
+        let mut estimator = GHLLReal::with_range_and_rng(self.precision, self.hasher, range, &mut rng)?;
+        estimator.strategy = strategy;
+
+        Ok(estimator)
+    }
+
+    /// Builds a `GHLLPlus` estimator, which supports all strategies
+    /// with or without the low-range correction and the exact mode
+    pub fn build_ghll_plus(self) -> Result<GHLLPlus<B>, GumbelError> {
+        self.validate(Capabilities {
+            estimator: "GHLLPlus",
+            register_width: 5,
+            strategies: &Strategy::ALL,
+            low_range_correction: true,
+            exact_mode: true,
+            fixed_range: false,
+        })?;
+
+        let mut rng = self.rng();
+        let mut estimator = GHLLPlus::with_precision_and_rng(self.precision, self.hasher, &mut rng)?;
+        if let Some(strategy) = self.strategy {
+            estimator.set_strategy(strategy);
+        }
+        if let Some(enabled) = self.low_range_correction {
+            estimator.set_low_range_correction(enabled);
+        }
+        if let Some(threshold) = self.exact_threshold {
+            estimator.set_exact_threshold(threshold);
+        }
+
+        Ok(estimator)
+    }
+
+    // the generator of the initial register values
+    fn rng(&self) -> StdRng {
+        match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            #[cfg(feature = "std")]
+            None => StdRng::from_entropy(),
+            #[cfg(not(feature = "std"))]
+            None => unreachable!("the configuration without a seed is rejected by `validate`"),
+        }
+    }
+
+    // checks the configuration against the options supported by an estimator
+    fn validate(&self, capabilities: Capabilities) -> Result<(), GumbelError> {
+        let Capabilities { estimator, register_width, strategies, low_range_correction, exact_mode, fixed_range } = capabilities;
+
+        check_precision(self.precision)?;
+
+        if let Some(width) = self.register_width {
+            if width != register_width {
+                return Err(GumbelError::InvalidRegisterWidth { width });
+            }
+        }
+
+        if let Some(strategy) = self.strategy {
+            if !strategies.contains(&strategy) {
+                return Err(GumbelError::UnsupportedConfiguration { estimator, option: strategy.description() });
+            }
+        }
+
+        if self.low_range_correction == Some(true) && !low_range_correction {
+            return Err(GumbelError::UnsupportedConfiguration { estimator, option: "low-range correction" });
+        }
+
+        if self.exact_threshold.is_some_and(|threshold| threshold > 0) && !exact_mode {
+            return Err(GumbelError::UnsupportedConfiguration { estimator, option: "exact mode" });
+        }
+
+        if let Some(range) = self.fixed_range {
+            if !fixed_range {
+                return Err(GumbelError::UnsupportedConfiguration { estimator, option: "a fixed-point range" });
+            }
+            check_range(range)?;
+        }
+
+        // without the entropy of the system, the initial register values need a seed
+        #[cfg(not(feature = "std"))]
+        if self.seed.is_none() {
+            return Err(GumbelError::UnsupportedConfiguration { estimator, option: "an unseeded initialisation without the std feature" });
+        }
+
+        // none of the estimators has a sparse representation yet
+        if self.sparse {
+            return Err(GumbelError::UnsupportedConfiguration { estimator, option: "sparse mode" });
+        }
+
+        Ok(())
+    }
+}
+
+// the options supported by an estimator
+struct Capabilities {
+    estimator: &'static str,
+    register_width: u8,
+    strategies: &'static [Strategy],
+    low_range_correction: bool,
+    exact_mode: bool,
+    fixed_range: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ahash::RandomState;
+    use crate::Fixed;
+
+    fn builder() -> GumbelSketchBuilder<RandomState> {
+        GumbelSketchBuilder::new()
+            .precision(8)
+            .hasher(RandomState::with_seeds(1, 2, 3, 4))
+            .seed(0)
+    }
+
+    fn unsupported(estimator: &'static str, option: &'static str) -> Option<GumbelError> {
+        Some(GumbelError::UnsupportedConfiguration { estimator, option })
+    }
+
+    #[test]
+    fn precision_is_checked() {
+        assert_eq!(
+            builder().precision(3).build_ghll().err(),
+            Some(GumbelError::InvalidPrecision { precision: 3, min: 4, max: 16 })
+        );
+        assert!(builder().precision(16).build_ghll().is_ok());
+    }
+
+    #[test]
+    fn register_width_must_match_the_estimator() {
+        assert_eq!(builder().register_width(6).build_ghll().err(), Some(GumbelError::InvalidRegisterWidth { width: 6 }));
+        assert_eq!(builder().register_width(6).build_ghll_plus().err(), Some(GumbelError::InvalidRegisterWidth { width: 6 }));
+        assert_eq!(builder().register_width(64).build_ghll_real::<f32>().err(), Some(GumbelError::InvalidRegisterWidth { width: 64 }));
+        assert_eq!(builder().register_width(16).build_ghll_real::<Fixed<12>>().err(), Some(GumbelError::InvalidRegisterWidth { width: 16 }));
+
+        assert!(builder().register_width(5).build_ghll().is_ok());
+        assert!(builder().register_width(64).build_ghll_real::<f64>().is_ok());
+        assert!(builder().register_width(12).build_ghll_real::<Fixed<12>>().is_ok());
+    }
+
+    #[test]
+    fn unsupported_options_are_rejected() {
+        assert_eq!(builder().exact_threshold(10).build_ghll_real::<f32>().err(), unsupported("GHLLReal", "exact mode"));
+        assert!(builder().exact_threshold(0).build_ghll_real::<f32>().is_ok());
+        assert!(builder().exact_threshold(10).build_ghll_plus().is_ok());
+
+        assert_eq!(builder().low_range_correction(true).build_ghll_real::<f64>().err(), unsupported("GHLLReal", "low-range correction"));
+        assert!(builder().low_range_correction(false).build_ghll_real::<f64>().is_ok());
+
+        assert_eq!(
+            builder().strategy(Strategy::Occupied).build_ghll_real::<f64>().err(),
+            unsupported("GHLLReal", "the occupied registers strategy")
+        );
+        for strategy in Strategy::ALL {
+            assert!(builder().strategy(strategy).build_ghll().is_ok());
+        }
+    }
+
+    #[test]
+    fn sparse_mode_is_rejected() {
+        assert_eq!(builder().sparse(true).build_ghll().err(), unsupported("GHLL", "sparse mode"));
+        assert_eq!(builder().sparse(true).build_ghll_real::<f32>().err(), unsupported("GHLLReal", "sparse mode"));
+        assert_eq!(builder().sparse(true).build_ghll_plus().err(), unsupported("GHLLPlus", "sparse mode"));
+        assert!(builder().sparse(false).build_ghll_plus().is_ok());
+    }
+
+    #[test]
+    fn fixed_range_is_checked() {
+        assert_eq!(builder().fixed_range(-4.0, 28.0).build_ghll().err(), unsupported("GHLL", "a fixed-point range"));
+        assert_eq!(builder().fixed_range(-4.0, 28.0).build_ghll_real::<f32>().err(), unsupported("GHLLReal", "a fixed-point range"));
+
+        for (min, max) in [(1.0, 1.0), (2.0, 1.0), (f64::NAN, 1.0), (0.0, f64::INFINITY)] {
+            assert_eq!(builder().fixed_range(min, max).build_ghll_real::<Fixed<8>>().err(), Some(GumbelError::InvalidRange));
+        }
+        assert!(builder().fixed_range(-4.0, 28.0).build_ghll_real::<Fixed<8>>().is_ok());
+        assert!(builder().fixed_range(-2.0, 20.0).build_ghll_real::<Fixed<16>>().is_ok());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn unseeded_build_uses_the_entropy_of_the_system() {
+        let unseeded = GumbelSketchBuilder::new().hasher(RandomState::with_seeds(1, 2, 3, 4));
+        assert!(unseeded.build_ghll_real::<f64>().is_ok());
+    }
+
+    #[test]
+    #[cfg(not(feature = "std"))]
+    fn unseeded_build_is_rejected_without_std() {
+        let unseeded = GumbelSketchBuilder::new().hasher(RandomState::with_seeds(1, 2, 3, 4));
+        assert_eq!(unseeded.clone().build_ghll().err(), unsupported("GHLL", "an unseeded initialisation without the std feature"));
+        assert_eq!(unseeded.build_ghll_real::<f64>().err(), unsupported("GHLLReal", "an unseeded initialisation without the std feature"));
+        assert!(builder().build_ghll().is_ok());
+    }
+}
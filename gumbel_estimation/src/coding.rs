@@ -0,0 +1,349 @@
+use alloc::{vec, vec::Vec};
+
+use crate::common::GumbelError;
+
+// The entropy coding of the compressed serialization: canonical Huffman codes for the register
+// values, which concentrate around `ln(n/m)`, and Golomb-Rice codes for the gaps between the rarer
+// bits of the bitmap of the free registers
+
+/// The longest Huffman code, so that the code lengths fit in 4 bits
+pub const MAX_CODE_LENGTH: u8 = 15;
+
+/// Writes bits into bytes, starting from the lowest bit of every byte
+pub struct BitWriter {
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+impl BitWriter {
+    pub fn new() -> Self {
+        Self { bytes: Vec::new(), len: 0 }
+    }
+
+    pub fn bit(&mut self, bit: bool) {
+        // start a new byte once the last one is full
+        if self.len == self.bytes.len() * 8 {
+            self.bytes.push(0);
+        }
+        if bit {
+            *self.bytes.last_mut().unwrap() |= 1 << (self.len % 8);
+        }
+        self.len += 1;
+    }
+
+    /// Writes the lowest `count` bits of the value, starting from the highest of them
+    pub fn bits(&mut self, value: u32, count: u8) {
+        for i in (0..count).rev() {
+            self.bit(value >> i & 1 == 1);
+        }
+    }
+
+    /// The written bytes, with the unused bits of the last byte unset
+    pub fn finish(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+/// Reads the bits written by `BitWriter`, failing with `GumbelError::CorruptData`
+/// when the data ends early
+pub struct BitReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    pub fn bit(&mut self) -> Result<bool, GumbelError> {
+        let byte = self.bytes.get(self.position / 8)
+            .ok_or(GumbelError::CorruptData { reason: "the data is truncated" })?;
+        let bit = byte >> (self.position % 8) & 1 == 1;
+        self.position += 1;
+
+        Ok(bit)
+    }
+
+    pub fn bits(&mut self, count: u8) -> Result<u32, GumbelError> {
+        (0..count).try_fold(0, |value, _| Ok(value << 1 | self.bit()? as u32))
+    }
+
+    /// The number of bytes read, including the last partially read byte
+    pub fn bytes_read(&self) -> usize {
+        self.position.div_ceil(8)
+    }
+}
+
+/// The lengths of the codes of a Huffman code of the symbols with the given frequencies,
+/// limited to `MAX_CODE_LENGTH`; the symbols of zero frequency get no code, i.e. the length 0
+pub fn code_lengths(frequencies: &[u32]) -> Vec<u8> {
+    let mut frequencies = frequencies.to_vec();
+    loop {
+        let lengths = huffman_lengths(&frequencies);
+        if lengths.iter().all(|&length| length <= MAX_CODE_LENGTH) {
+            return lengths;
+        }
+
+        // flatten the distribution until the code fits, keeping every symbol in use
+        for frequency in frequencies.iter_mut().filter(|frequency| **frequency > 0) {
+            *frequency = frequency.div_ceil(2);
+        }
+    }
+}
+
+// the lengths of the codes of an unlimited Huffman code, built by merging the two lightest trees,
+// which lengthens the codes of all their symbols; there are at most 32 symbols
+fn huffman_lengths(frequencies: &[u32]) -> Vec<u8> {
+    let mut lengths = vec![0; frequencies.len()];
+    let mut trees: Vec<(u64, Vec<usize>)> = frequencies.iter()
+        .enumerate()
+        .filter(|&(_, &frequency)| frequency > 0)
+        .map(|(symbol, &frequency)| (frequency as u64, vec![symbol]))
+        .collect();
+
+    // a single symbol still needs a code of a single bit
+    if let [(_, symbols)] = trees.as_slice() {
+        lengths[symbols[0]] = 1;
+    }
+
+    while trees.len() > 1 {
+        trees.sort_by_key(|&(weight, _)| core::cmp::Reverse(weight));
+        let (lightest, mut symbols) = trees.pop().unwrap();
+        let (weight, others) = trees.last_mut().unwrap();
+
+        for &symbol in symbols.iter().chain(others.iter()) {
+            lengths[symbol] += 1;
+        }
+        *weight += lightest;
+        others.append(&mut symbols);
+    }
+
+    lengths
+}
+
+/// The canonical Huffman codes of the given code lengths, assigned in the order of the lengths
+/// and then of the symbols
+pub fn canonical_codes(lengths: &[u8]) -> Vec<u32> {
+    let mut counts = [0; MAX_CODE_LENGTH as usize + 1];
+    for &length in lengths.iter().filter(|&&length| length > 0) {
+        counts[length as usize] += 1;
+    }
+
+    // the first code of every length
+    let mut next = [0; MAX_CODE_LENGTH as usize + 1];
+    let mut code = 0;
+    for length in 1..counts.len() {
+        code = (code + counts[length - 1]) << 1;
+        next[length] = code;
+    }
+
+    lengths.iter()
+        .map(|&length| {
+            let code = next[length as usize];
+            next[length as usize] += 1;
+            code
+        })
+        .collect()
+}
+
+/// Decodes the canonical Huffman codes of the given code lengths
+pub struct HuffmanDecoder {
+    counts: [u32; MAX_CODE_LENGTH as usize + 1],
+    symbols: Vec<usize>,
+}
+
+impl HuffmanDecoder {
+    /// Creates the decoder, or fails if the lengths do not form a prefix code
+    pub fn new(lengths: &[u8]) -> Result<Self, GumbelError> {
+        let mut counts = [0; MAX_CODE_LENGTH as usize + 1];
+        for &length in lengths.iter().filter(|&&length| length > 0) {
+            counts[length as usize] += 1;
+        }
+
+        // the codes must not use more than all the codes of the longest length
+        let used: u32 = (1..counts.len())
+            .map(|length| counts[length] << (MAX_CODE_LENGTH as usize - length))
+            .sum();
+        if used > 1 << MAX_CODE_LENGTH {
+            return Err(GumbelError::CorruptData { reason: "the code lengths do not form a prefix code" });
+        }
+
+        // the symbols in the order of their codes
+        let mut symbols: Vec<_> = (0..lengths.len()).filter(|&symbol| lengths[symbol] > 0).collect();
+        symbols.sort_by_key(|&symbol| lengths[symbol]);
+
+        Ok(Self { counts, symbols })
+    }
+
+    pub fn decode(&self, reader: &mut BitReader) -> Result<usize, GumbelError> {
+        // the code read so far, the first code of its length and the index of its first symbol
+        let (mut code, mut first, mut index) = (0, 0, 0);
+        for &count in &self.counts[1..] {
+            code |= reader.bit()? as u32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        Err(GumbelError::CorruptData { reason: "invalid code" })
+    }
+}
+
+/// Writes the value with the Golomb-Rice code of the parameter `k`, i.e. the quotient by `2^k`
+/// in unary, as ones ended by a zero, followed by the remainder in `k` bits
+pub fn write_rice(writer: &mut BitWriter, value: u32, k: u8) {
+    for _ in 0..value >> k {
+        writer.bit(true);
+    }
+    writer.bit(false);
+    writer.bits(value, k);
+}
+
+/// Reads a value written by `write_rice`, failing if it exceeds `max`
+pub fn read_rice(reader: &mut BitReader, k: u8, max: u32) -> Result<u32, GumbelError> {
+    let mut quotient = 0;
+    while reader.bit()? {
+        quotient += 1;
+        if quotient > max >> k {
+            return Err(GumbelError::CorruptData { reason: "a coded value is out of range" });
+        }
+    }
+
+    let value = quotient << k | reader.bits(k)?;
+    if value > max {
+        return Err(GumbelError::CorruptData { reason: "a coded value is out of range" });
+    }
+
+    Ok(value)
+}
+
+/// The Golomb-Rice parameter giving the shortest code of the values
+pub fn rice_parameter(values: &[u32]) -> u8 {
+    (0..16)
+        .min_by_key(|&k| values.iter().map(|&value| (value >> k) as usize + 1 + k as usize).sum::<usize>())
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // writes every symbol with the canonical code of the lengths, and decodes them back
+    fn huffman_round_trip(frequencies: &[u32]) -> Vec<u8> {
+        let lengths = code_lengths(frequencies);
+        let codes = canonical_codes(&lengths);
+        let symbols: Vec<usize> = (0..frequencies.len()).filter(|&symbol| frequencies[symbol] > 0).collect();
+
+        let mut writer = BitWriter::new();
+        for &symbol in &symbols {
+            writer.bits(codes[symbol], lengths[symbol]);
+        }
+        let bytes = writer.finish();
+
+        let decoder = HuffmanDecoder::new(&lengths).unwrap();
+        let mut reader = BitReader::new(&bytes);
+        for &symbol in &symbols {
+            assert_eq!(decoder.decode(&mut reader).unwrap(), symbol);
+        }
+        assert_eq!(reader.bytes_read(), bytes.len());
+
+        lengths
+    }
+
+    #[test]
+    fn bits_round_trip() {
+        let mut writer = BitWriter::new();
+        writer.bit(true);
+        writer.bits(0b10110, 5);
+        writer.bits(0x7fff, 15);
+        writer.bits(0, 0);
+        let bytes = writer.finish();
+        assert_eq!(bytes.len(), 3);
+
+        let mut reader = BitReader::new(&bytes);
+        assert!(reader.bit().unwrap());
+        assert_eq!(reader.bits(5).unwrap(), 0b10110);
+        assert_eq!(reader.bits(15).unwrap(), 0x7fff);
+        assert_eq!(reader.bytes_read(), 3);
+
+        // the unused bits of the last byte are unset and reading past them fails
+        assert_eq!(reader.bits(3).unwrap(), 0);
+        assert!(matches!(reader.bit(), Err(GumbelError::CorruptData { .. })));
+    }
+
+    #[test]
+    fn huffman_codes_round_trip() {
+        assert_eq!(huffman_round_trip(&[5]), [1]);
+        assert_eq!(huffman_round_trip(&[0, 3, 0]), [0, 1, 0]);
+        assert_eq!(huffman_round_trip(&[1, 1]), [1, 1]);
+        assert_eq!(huffman_round_trip(&[4, 2, 1, 1]), [1, 2, 3, 3]);
+        huffman_round_trip(&[1; 32]);
+    }
+
+    #[test]
+    fn huffman_codes_are_limited() {
+        // the Fibonacci frequencies lead to the longest codes, here 31 bits without the limit
+        let mut frequencies = vec![1_u32, 1];
+        while frequencies.len() < 32 {
+            frequencies.push(frequencies[frequencies.len() - 1] + frequencies[frequencies.len() - 2]);
+        }
+        assert_eq!(huffman_lengths(&frequencies).into_iter().max(), Some(31));
+
+        let lengths = huffman_round_trip(&frequencies);
+        assert!(lengths.iter().all(|&length| (1..=MAX_CODE_LENGTH).contains(&length)));
+    }
+
+    #[test]
+    fn huffman_decoder_rejects_invalid_codes() {
+        // three codes of a single bit do not form a prefix code
+        assert!(matches!(HuffmanDecoder::new(&[1, 1, 1]), Err(GumbelError::CorruptData { .. })));
+
+        // an incomplete code leaves some bit strings undecodable
+        let decoder = HuffmanDecoder::new(&[1, 0]).unwrap();
+        assert_eq!(decoder.decode(&mut BitReader::new(&[0])).unwrap(), 0);
+        assert!(matches!(decoder.decode(&mut BitReader::new(&[0xff, 0xff])), Err(GumbelError::CorruptData { .. })));
+
+        // a truncated code
+        let decoder = HuffmanDecoder::new(&[1, 2, 2]).unwrap();
+        assert!(matches!(decoder.decode(&mut BitReader::new(&[])), Err(GumbelError::CorruptData { .. })));
+    }
+
+    #[test]
+    fn rice_codes_round_trip() {
+        let values = [0, 1, 2, 7, 100, 65535];
+        for k in 0..16 {
+            let mut writer = BitWriter::new();
+            for &value in &values {
+                write_rice(&mut writer, value, k);
+            }
+            let bytes = writer.finish();
+
+            let mut reader = BitReader::new(&bytes);
+            for &value in &values {
+                assert_eq!(read_rice(&mut reader, k, 65535).unwrap(), value);
+            }
+        }
+    }
+
+    #[test]
+    fn rice_codes_are_bounded() {
+        let mut writer = BitWriter::new();
+        write_rice(&mut writer, 100, 2);
+        let bytes = writer.finish();
+
+        assert!(matches!(read_rice(&mut BitReader::new(&bytes), 2, 99), Err(GumbelError::CorruptData { .. })));
+        assert!(matches!(read_rice(&mut BitReader::new(&bytes), 2, 20), Err(GumbelError::CorruptData { .. })));
+        assert!(matches!(read_rice(&mut BitReader::new(&bytes[..2]), 2, 100), Err(GumbelError::CorruptData { .. })));
+    }
+
+    #[test]
+    fn rice_parameter_fits_the_values() {
+        assert_eq!(rice_parameter(&[0, 0, 1, 0]), 0);
+        assert_eq!(rice_parameter(&[1000, 1100, 900]), 9);
+    }
+}
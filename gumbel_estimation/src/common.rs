@@ -1,4 +1,4 @@
-use std::hash::{Hash, BuildHasher};
+use core::hash::{Hash, BuildHasher};
 
 /// Negative value of the gamma constant
 pub const NEG_GAMMA: f64 = -0.577_215_664_901_532_9_f64;
@@ -9,9 +9,44 @@ pub const MIN_PRECISION: u8 = 4;
 /// The maximmal accepted precision
 pub const MAX_PRECISION: u8 = 16;
 
-#[derive(Debug)]
-pub enum GumbelError {
-    InvalidPrecision,
+pub use crate::error::GumbelError;
+
+/// `ln(Γ(1 - x))` for small `x` by its Taylor series `γx + Σ ζ(k) x^k / k`
+pub fn ln_gamma_one_minus(x: f64) -> f64 {
+    // the values of the Riemann zeta function at {2, 3, ..., 10}
+    const ZETA: [f64; 9] = [
+        1.644_934_066_848_226_4,
+        1.202_056_903_159_594_3,
+        1.082_323_233_711_138_2,
+        1.036_927_755_143_37,
+        1.017_343_061_984_449,
+        1.008_349_277_381_922_8,
+        1.004_077_356_197_944_3,
+        1.002_008_392_826_082_2,
+        1.000_994_575_127_818_1,
+    ];
+
+    ZETA.iter()
+        .enumerate()
+        .fold(-NEG_GAMMA * x, |sum, (i, zeta)| sum + zeta * libm::pow(x, i as f64 + 2.0) / (i as f64 + 2.0))
+}
+
+/// Checks if the precision lies in the accepted range
+pub fn check_precision(precision: u8) -> Result<(), GumbelError> {
+    if (MIN_PRECISION..=MAX_PRECISION).contains(&precision) {
+        Ok(())
+    } else {
+        Err(GumbelError::InvalidPrecision { precision, min: MIN_PRECISION, max: MAX_PRECISION })
+    }
+}
+
+/// Checks if the range `(min, max)` of the fixed-point registers is finite and non-empty
+pub fn check_range((min, max): (f64, f64)) -> Result<(), GumbelError> {
+    if min.is_finite() && max.is_finite() && min < max {
+        Ok(())
+    } else {
+        Err(GumbelError::InvalidRange)
+    }
 }
 
 #[inline(always)]
@@ -21,8 +56,13 @@ where
     B: BuildHasher,
 {
     // obtain the value's hash
-    let mut hash = builder.hash_one(value) as u32;
-    
+    let hash = builder.hash_one(value) as u32;
+
+    split_hash(hash, precision)
+}
+
+#[inline(always)]
+fn split_hash(mut hash: u32, precision: u8) -> (usize, u32) {
     // choose a register based on the first `precision` bits
     let index: usize = (hash >> (32 - precision)) as usize;
 
@@ -31,3 +71,23 @@ where
 
     (index, hash)
 }
+
+/// Like `hash_value`, but keeps all `64 - precision` bits of the hash beyond the index, at the top
+/// of the remainder; its upper half is the remainder of `hash_value`, followed by the upper half
+/// of the hash, so that at least 52 bits are random up to the precision of 12
+#[inline(always)]
+pub fn hash_value_wide<H, B>(value: &H, builder: &B, precision: u8) -> (usize, u64)
+where
+    H: Hash + ?Sized,
+    B: BuildHasher,
+{
+    // obtain the value's hash
+    let hash = builder.hash_one(value);
+
+    // choose the register of `hash_value`
+    let (index, remainder) = split_hash(hash as u32, precision);
+
+    // append the upper half of the hash to the remainder
+    (index, ((remainder as u64) << 32) | ((hash >> 32) << precision))
+}
+
@@ -0,0 +1,58 @@
+use core::error::Error;
+use core::fmt;
+
+/// An error raised by the estimators
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GumbelError {
+    /// the precision lies outside of the range accepted by the estimator
+    InvalidPrecision { precision: u8, min: u8, max: u8 },
+    /// the width of the registers in bits is not supported by the estimator
+    InvalidRegisterWidth { width: u8 },
+    /// the range of the fixed-point registers is not finite or its minimum is not below its maximum
+    InvalidRange,
+    /// the estimator does not support the given option, e.g. `"sparse mode"`
+    UnsupportedConfiguration { estimator: &'static str, option: &'static str },
+    /// the estimators cannot be merged, since they differ in the given property,
+    /// e.g. `"precision"`
+    IncompatibleMerge { property: &'static str },
+    /// the serialized estimator is malformed, e.g. truncated
+    CorruptData { reason: &'static str },
+    /// the serialized estimator was written in a format version this library cannot read
+    UnsupportedVersion { version: u8 },
+    /// the given number of registers reached the largest value they can hold, so that
+    /// the estimate is only a lower bound of the cardinality
+    SaturatedRegisters { count: usize },
+}
+
+impl fmt::Display for GumbelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GumbelError::InvalidPrecision { precision, min, max } => {
+                write!(f, "precision {} is out of the range {}..={}", precision, min, max)
+            }
+            GumbelError::InvalidRegisterWidth { width } => {
+                write!(f, "registers of {} bits are not supported", width)
+            }
+            GumbelError::InvalidRange => {
+                write!(f, "the range of the fixed-point registers must be finite and non-empty")
+            }
+            GumbelError::UnsupportedConfiguration { estimator, option } => {
+                write!(f, "{} does not support {}", estimator, option)
+            }
+            GumbelError::IncompatibleMerge { property } => {
+                write!(f, "cannot merge estimators of different {}", property)
+            }
+            GumbelError::CorruptData { reason } => {
+                write!(f, "corrupt estimator data: {}", reason)
+            }
+            GumbelError::UnsupportedVersion { version } => {
+                write!(f, "unsupported estimator format version {}", version)
+            }
+            GumbelError::SaturatedRegisters { count } => {
+                write!(f, "{} registers are saturated", count)
+            }
+        }
+    }
+}
+
+impl Error for GumbelError {}
@@ -3,6 +3,9 @@ const MAX_REGISTER_VALUE: f32 = 15.0;
 
 pub const BIAS: i32 = 16;
 
+// the largest value of a rounded register, which it keeps for any larger gumbel value
+pub const MAX_ROUNDED: u32 = (MAX_REGISTER_VALUE as i32 + BIAS) as u32;
+
 // create a [0, 1) float from its mantissa bit represenations
 pub fn mantissa_to_float(bits: u32) -> f32 {
     // create the exponent and mantissa bits
@@ -25,10 +28,40 @@ pub fn from_bits(bits: u32) -> f32 {
     quantile(random_unif)
 }
 
-// create a gumbel random value from a [0, 1) float
+// create a gumbel random value from a [0, 1) float; `q == 0` would give `-inf`,
+// so it is replaced by the smallest positive float
 #[inline(always)]
 pub fn quantile(q: f32) -> f32 {
-    -f32::ln(-f32::ln(q))
+    -libm::logf(-libm::logf(q.max(f32::MIN_POSITIVE)))
+}
+
+// create a [0, 1) double from the 52 mantissa bits at the top of `bits`
+pub fn mantissa_to_double(bits: u64) -> f64 {
+    // create the exponent and mantissa bits
+    let exponent_bits = 1023 << 52;
+    let mantissa_bits = bits >> 12;
+
+    // combine the bits
+    let bits = exponent_bits | mantissa_bits;
+
+    f64::from_bits(bits) - 1.0
+}
+
+// create a gumbel random value from the mantissa bit representation of a [0, 1) double,
+// which refines the float of `from_bits` if its bits are the upper half of `bits`
+#[inline(always)]
+pub fn from_bits_f64(bits: u64) -> f64 {
+    // create a random [0, 1) double
+    let random_unif = mantissa_to_double(bits);
+
+    // create a gumbel random variable
+    quantile_f64(random_unif)
+}
+
+// create a gumbel random value from a [0, 1) double, guarded against `q == 0` like `quantile`
+#[inline(always)]
+pub fn quantile_f64(q: f64) -> f64 {
+    -libm::log(-libm::log(q.max(f64::MIN_POSITIVE)))
 }
 
 // create a gumbel random value from a bit representation of a [0, 1) float,
@@ -50,5 +83,145 @@ pub fn quantile_rounded(q: f32, c: f32) -> u32 {
 // perform shift rounding of a value using the rounding value of `c`
 #[inline(always)]
 pub fn shift_round(value: f32, c: f32) -> u32 {
-    ((f32::floor(value + c).clamp(MIN_REGISTER_VALUE, MAX_REGISTER_VALUE) as i32) + BIAS) as u32
+    ((libm::floorf(value + c).clamp(MIN_REGISTER_VALUE, MAX_REGISTER_VALUE) as i32) + BIAS) as u32
+}
+
+// The maximum likelihood estimates of the load, i.e. the mean number of elements per register.
+// A register holds the maximum of its initial gumbel value and of the gumbel values of its
+// elements, whose number follows the Poisson distribution with the mean of the load `ν`, so that
+// the register is below `x` with the probability `exp(-u - ν (1 - exp(-u)))`, where `u = exp(-x)`.
+// The log-likelihood is concave in `ν`, so the root of its derivative is found by bisection.
+
+// the estimate from registers rounded by `shift_round` with their rounding values; a rounded
+// register only tells the interval its value lies in
+pub fn ml_load(registers: &[(u32, f32)]) -> f64 {
+    let min_rounded = (MIN_REGISTER_VALUE as i32 + BIAS) as u32;
+
+    maximise(|load| registers.iter()
+        .map(|&(val, c)| {
+            // the register value lies in `[x, x + 1)`
+            let x = (val as i32 - BIAS) as f64 - c as f64;
+            let (u_low, u_high) = (libm::exp(-x), libm::exp(-x - 1.0));
+            let (w_low, w_high) = (-libm::expm1(-u_low), -libm::expm1(-u_high));
+
+            if val == MAX_ROUNDED {
+                // the value lies above the largest register value
+                w_low / libm::expm1(u_low + load * w_low)
+            } else if val == min_rounded {
+                // the value lies below the smallest register value
+                -w_high
+            } else {
+                // the ratio of the probabilities of the register lying below both ends
+                let log_ratio = -(u_low - u_high) - load * (w_low - w_high);
+                (w_low * libm::exp(log_ratio) - w_high) / -libm::expm1(log_ratio)
+            }
+        })
+        .sum())
+}
+
+// the estimate from the exact register values
+pub fn ml_load_real(registers: &[f64]) -> f64 {
+    maximise(|load| registers.iter()
+        .map(|&val| {
+            let v = libm::exp(-libm::exp(-val));
+            -(1.0 - v) + v / (1.0 + load * v)
+        })
+        .sum())
+}
+
+// The load whose registers have the given mean after the gumbel location of `γ` is subtracted,
+// i.e. which solves `E[ln(1 + K)] = log_mean` for `K` following the Poisson distribution
+// with the mean of the load; `ln(1 + ν)` exceeds `E[ln(1 + K)]` by Jensen's inequality
+pub fn mean_load(log_mean: f64) -> f64 {
+    maximise(|load| log_mean - expected_log(load))
+}
+
+// `E[ln(1 + K)]` for `K` following the Poisson distribution with the mean of `load`
+fn expected_log(load: f64) -> f64 {
+    if load > 100.0 {
+        // the Taylor expansion of `ln(1 + k)` around the mean up to the fourth central moment
+        let x = 1.0 + load;
+        return libm::log(x) - load / (2.0 * x * x) + load / (3.0 * x * x * x)
+            - (3.0 * load * load + load) / (4.0 * x * x * x * x);
+    }
+
+    // sum the terms up to far beyond the mean, updating the Poisson probabilities iteratively
+    let mut probability = libm::exp(-load);
+    let mut sum = 0.0;
+    let last = (load + 15.0 * libm::sqrt(load) + 30.0) as usize;
+    for k in 1..=last {
+        probability *= load / k as f64;
+        sum += probability * libm::log(1.0 + k as f64);
+    }
+
+    sum
+}
+
+// finds the load maximising the log-likelihood given its derivative, which decreases with the load
+fn maximise<F: Fn(f64) -> f64>(score: F) -> f64 {
+    // the likelihood decreases already from an empty sketch
+    if score(0.0) <= 0.0 {
+        return 0.0;
+    }
+
+    // bisection on the logarithmic scale
+    let (mut low, mut high) = (-30.0_f64, 40.0_f64);
+    for _ in 0..100 {
+        let mid = (low + high) / 2.0;
+        if score(libm::exp(mid)) > 0.0 {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+
+    libm::exp((low + high) / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn zero_mantissa_gives_a_finite_value() {
+        // the bits below the mantissas are ignored
+        for bits in [0, 0xfff] {
+            assert_eq!(mantissa_to_double(bits), 0.0);
+            assert!(from_bits_f64(bits).is_finite());
+        }
+        assert_eq!(mantissa_to_float(0x1ff), 0.0);
+        assert!(from_bits(0x1ff).is_finite());
+        assert!(quantile(0.0).is_finite() && quantile_f64(0.0).is_finite());
+
+        // the smallest values stay below those of any positive mantissa
+        assert!(from_bits_f64(0) < from_bits_f64(1 << 12));
+        assert!(from_bits(0) < from_bits(1 << 9));
+    }
+
+    #[test]
+    fn f64_value_refines_the_f32_value() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let step = f64::powi(2.0, -23);
+
+        for _ in 0..100_000 {
+            let hash: u64 = rng.gen();
+            let (value, value_f32) = (from_bits_f64(hash), from_bits((hash >> 32) as u32) as f64);
+            let q = mantissa_to_float((hash >> 32) as u32) as f64;
+            if q == 0.0 {
+                continue;
+            }
+
+            // the 52-bit uniform value lies in the interval of the 23-bit one, and so does its gumbel value
+            assert!((q..q + step).contains(&mantissa_to_double(hash)));
+            assert!(quantile_f64(q) <= value && value <= quantile_f64(q + step), "hash {:#x}", hash);
+
+            // away from the ends of [0, 1), the slope of the quantile stays below 1024,
+            // which bounds the difference by about `1024 * step`
+            if (1.0 / 1024.0..1.0 - 1.0 / 1024.0).contains(&q) {
+                assert!((value - value_f32).abs() < 2e-4, "hash {:#x}: {} and {}", hash, value, value_f32);
+            }
+        }
+    }
 }
@@ -1,19 +1,288 @@
-use std::hash::{Hash, BuildHasher};
-use rand::{Rng, thread_rng};
-use rand::distributions::{Uniform};
+use core::hash::{Hash, BuildHasher};
+use alloc::vec::Vec;
+use rand::Rng;
+#[cfg(feature = "std")]
+use rand::thread_rng;
 
+use crate::builder::GumbelSketchBuilder;
+use crate::sketch::Strategy;
 use crate::common::*;
 use crate::gen_gumbel;
+use crate::serial::{self, Header, Reader};
 
-/// A cardinality estimator using the Gumbel distribution
-pub struct GHLLReal<B: BuildHasher> {
+/// The type of the registers of `GHLLReal`: the floating-point `f32` or `f64`, the latter generating
+/// its gumbel values from 52 bits of the hash instead of 23, which refine those of `f32`,
+/// or the fixed-point `Fixed<8>`, `Fixed<12>` or `Fixed<16>`
+pub trait RealRegister: sealed::Register {
+    /// The width of the register in bits
+    const WIDTH: u8;
+}
+
+impl RealRegister for f32 {
+    const WIDTH: u8 = 32;
+}
+
+impl RealRegister for f64 {
+    const WIDTH: u8 = 64;
+}
+
+/// Fixed-point registers of `BITS` bits, which quantise the gumbel values to the nearest of
+/// `2^BITS` levels evenly spaced over a range set by `GumbelSketchBuilder::fixed_range`,
+/// by default `GumbelSketchBuilder::DEFAULT_FIXED_RANGE`; values outside of the range are clamped
+#[derive(Debug, Clone, Copy)]
+pub struct Fixed<const BITS: u8>;
+
+impl RealRegister for Fixed<8> {
+    const WIDTH: u8 = 8;
+}
+
+impl RealRegister for Fixed<12> {
+    const WIDTH: u8 = 12;
+}
+
+impl RealRegister for Fixed<16> {
+    const WIDTH: u8 = 16;
+}
+
+pub(crate) mod sealed {
+    use alloc::vec::Vec;
+    use rand::Rng;
+    use rand::distributions::Uniform;
+
+    use super::Fixed;
+    use crate::common::{check_range, GumbelError};
+    use crate::gen_gumbel;
+    use crate::registers::PackedRegisters;
+    use crate::serial::{self, Reader};
+
+    // the operations of `GHLLReal` on its registers, hidden from the public interface
+    pub trait Register {
+        // the kind of the serialized estimator
+        const KIND: u8;
+
+        // whether the registers take a fixed-point range
+        const FIXED: bool;
+
+        type Registers: Clone;
+
+        // the registers initialised to the gumbel values of uniform [0, 1) values drawn
+        // from the generator; the range is ignored by the floating-point registers
+        fn random<G: Rng>(count: usize, range: (f64, f64), rng: &mut G) -> Self::Registers;
+
+        fn get(registers: &Self::Registers, index: usize) -> f64;
+
+        // updates the register to the max of its value and the gumbel value
+        // of the remainder of `hash_value_wide`
+        fn update(registers: &mut Self::Registers, index: usize, hash: u64);
+
+        fn merge(registers: &mut Self::Registers, other: &Self::Registers) -> Result<(), GumbelError>;
+
+        fn memory_bytes(registers: &Self::Registers) -> usize;
+
+        fn write(registers: &Self::Registers, bytes: &mut Vec<u8>);
+
+        fn read(reader: &mut Reader, count: usize) -> Result<Self::Registers, GumbelError>;
+    }
+
+    impl Register for f32 {
+        const KIND: u8 = serial::KIND_REAL;
+        const FIXED: bool = false;
+
+        type Registers = Vec<f32>;
+
+        fn random<G: Rng>(count: usize, _range: (f64, f64), rng: &mut G) -> Vec<f32> {
+            let unif = Uniform::new(0.0, 1.0);
+            (0..count).map(|_| gen_gumbel::quantile(rng.sample(unif))).collect()
+        }
+
+        fn get(registers: &Vec<f32>, index: usize) -> f64 {
+            registers[index] as f64
+        }
+
+        fn update(registers: &mut Vec<f32>, index: usize, hash: u64) {
+            let gumbel_value = gen_gumbel::from_bits((hash >> 32) as u32);
+            registers[index] = f32::max(registers[index], gumbel_value);
+        }
+
+        fn merge(registers: &mut Vec<f32>, other: &Vec<f32>) -> Result<(), GumbelError> {
+            for (register, &value) in registers.iter_mut().zip(other) {
+                *register = f32::max(*register, value);
+            }
+
+            Ok(())
+        }
+
+        fn memory_bytes(registers: &Vec<f32>) -> usize {
+            registers.len() * core::mem::size_of::<f32>()
+        }
+
+        fn write(registers: &Vec<f32>, bytes: &mut Vec<u8>) {
+            for register in registers {
+                bytes.extend_from_slice(&register.to_le_bytes());
+            }
+        }
+
+        fn read(reader: &mut Reader, count: usize) -> Result<Vec<f32>, GumbelError> {
+            let registers = (0..count)
+                .map(|_| reader.u32().map(f32::from_bits))
+                .collect::<Result<Vec<_>, _>>()?;
+            if registers.iter().any(|register| register.is_nan()) {
+                return Err(GumbelError::CorruptData { reason: "a register is not a number" });
+            }
+
+            Ok(registers)
+        }
+    }
+
+    impl Register for f64 {
+        const KIND: u8 = serial::KIND_REAL_F64;
+        const FIXED: bool = false;
+
+        type Registers = Vec<f64>;
+
+        fn random<G: Rng>(count: usize, _range: (f64, f64), rng: &mut G) -> Vec<f64> {
+            let unif = Uniform::new(0.0, 1.0);
+            (0..count).map(|_| gen_gumbel::quantile_f64(rng.sample(unif))).collect()
+        }
+
+        fn get(registers: &Vec<f64>, index: usize) -> f64 {
+            registers[index]
+        }
+
+        fn update(registers: &mut Vec<f64>, index: usize, hash: u64) {
+            let gumbel_value = gen_gumbel::from_bits_f64(hash);
+            registers[index] = f64::max(registers[index], gumbel_value);
+        }
+
+        fn merge(registers: &mut Vec<f64>, other: &Vec<f64>) -> Result<(), GumbelError> {
+            for (register, &value) in registers.iter_mut().zip(other) {
+                *register = f64::max(*register, value);
+            }
+
+            Ok(())
+        }
+
+        fn memory_bytes(registers: &Vec<f64>) -> usize {
+            registers.len() * core::mem::size_of::<f64>()
+        }
+
+        fn write(registers: &Vec<f64>, bytes: &mut Vec<u8>) {
+            for register in registers {
+                bytes.extend_from_slice(&register.to_le_bytes());
+            }
+        }
+
+        fn read(reader: &mut Reader, count: usize) -> Result<Vec<f64>, GumbelError> {
+            let registers = (0..count)
+                .map(|_| reader.u64().map(f64::from_bits))
+                .collect::<Result<Vec<_>, _>>()?;
+            if registers.iter().any(|register| register.is_nan()) {
+                return Err(GumbelError::CorruptData { reason: "a register is not a number" });
+            }
+
+            Ok(registers)
+        }
+    }
+
+    // the fixed-point registers, holding the levels of the values along with their range
+    #[derive(Clone)]
+    pub struct FixedRegisters<const BITS: u8> {
+        levels: PackedRegisters<BITS>,
+        range: (f64, f64),
+    }
+
+    impl<const BITS: u8> FixedRegisters<BITS> {
+        // the difference between consecutive levels
+        fn step(&self) -> f64 {
+            (self.range.1 - self.range.0) / PackedRegisters::<BITS>::MAX as f64
+        }
+
+        // the nearest level of the value
+        fn quantise(&self, value: f64) -> u32 {
+            let level = libm::round((value - self.range.0) / self.step());
+            level.clamp(0.0, PackedRegisters::<BITS>::MAX as f64) as u32
+        }
+    }
+
+    impl<const BITS: u8> Register for Fixed<BITS> {
+        const KIND: u8 = match BITS {
+            8 => serial::KIND_FIXED_8,
+            12 => serial::KIND_FIXED_12,
+            16 => serial::KIND_FIXED_16,
+            // `RealRegister` is only implemented for the above widths
+            _ => panic!("unsupported width of the fixed-point registers"),
+        };
+        const FIXED: bool = true;
+
+        type Registers = FixedRegisters<BITS>;
+
+        fn random<G: Rng>(count: usize, range: (f64, f64), rng: &mut G) -> FixedRegisters<BITS> {
+            let unif = Uniform::new(0.0, 1.0);
+            let mut registers = FixedRegisters { levels: PackedRegisters::new(count), range };
+            for index in 0..count {
+                let level = registers.quantise(gen_gumbel::quantile_f64(rng.sample(unif)));
+                registers.levels.set(index, level);
+            }
+
+            registers
+        }
+
+        fn get(registers: &FixedRegisters<BITS>, index: usize) -> f64 {
+            registers.range.0 + registers.levels.get(index) as f64 * registers.step()
+        }
+
+        fn update(registers: &mut FixedRegisters<BITS>, index: usize, hash: u64) {
+            let level = registers.quantise(gen_gumbel::from_bits_f64(hash));
+            registers.levels.set_greater(index, level);
+        }
+
+        fn merge(registers: &mut FixedRegisters<BITS>, other: &FixedRegisters<BITS>) -> Result<(), GumbelError> {
+            if registers.range != other.range {
+                return Err(GumbelError::IncompatibleMerge { property: "range" });
+            }
+
+            for (index, level) in other.levels.iter().enumerate() {
+                registers.levels.set_greater(index, level);
+            }
+
+            Ok(())
+        }
+
+        fn memory_bytes(registers: &FixedRegisters<BITS>) -> usize {
+            registers.levels.memory_bytes()
+        }
+
+        fn write(registers: &FixedRegisters<BITS>, bytes: &mut Vec<u8>) {
+            bytes.extend_from_slice(&registers.range.0.to_le_bytes());
+            bytes.extend_from_slice(&registers.range.1.to_le_bytes());
+            bytes.extend_from_slice(registers.levels.bytes());
+        }
+
+        fn read(reader: &mut Reader, count: usize) -> Result<FixedRegisters<BITS>, GumbelError> {
+            let range = (f64::from_bits(reader.u64()?), f64::from_bits(reader.u64()?));
+            check_range(range).map_err(|_| GumbelError::CorruptData { reason: "invalid range" })?;
+
+            let len = PackedRegisters::<BITS>::new(count).memory_bytes();
+            let levels = PackedRegisters::from_bytes(reader.take(len)?.to_vec(), count)
+                .ok_or(GumbelError::CorruptData { reason: "unused register bits are set" })?;
+
+            Ok(FixedRegisters { levels, range })
+        }
+    }
+}
+
+/// A cardinality estimator using the Gumbel distribution, storing the registers
+/// as `f32`, `f64` or fixed-point values
+#[derive(Clone)]
+pub struct GHLLReal<B: BuildHasher, R: RealRegister = f32> {
     builder: B,
     precision: u8,
     no_registers: usize,
-    registers: Vec<f32>,
+    registers: R::Registers,
+    pub(crate) strategy: Strategy,
 }
 
-impl<B: BuildHasher> GHLLReal<B> {
+impl<B: BuildHasher, R: RealRegister> GHLLReal<B, R> {
     /// Creates a new `GumbelEstimator` object with a custom precision and hash builder
     ///
     /// # Arguments
@@ -21,24 +290,31 @@ impl<B: BuildHasher> GHLLReal<B> {
     /// - `precision` - corresponds to the number of registers used by this estimator using the 
     ///   formula `no_registers = 2^precision`; the accepted values lie in the range {4, 5, ..., 16}
     /// - `builder` - this is a hash builder that will be used for hashing provided values
+    #[cfg(feature = "std")]
     pub fn with_precision(precision: u8, builder: B) -> Result<Self, GumbelError> {
-        // check if the provided precision is within the bounds
-        if !(MIN_PRECISION..=MAX_PRECISION).contains(&precision) {
-            return Err(GumbelError::InvalidPrecision);
-        }
+        Self::with_precision_and_rng(precision, builder, &mut thread_rng())
+    }
+
+    /// Creates a new `GHLLReal` object like `with_precision`, but draws the initial values
+    /// of the registers from the provided random number generator instead of `thread_rng`,
+    /// which makes the estimator reproducible from the generator's seed
+    /// and is the only constructor without the `std` feature
+    pub fn with_precision_and_rng<G: Rng>(precision: u8, builder: B, rng: &mut G) -> Result<Self, GumbelError> {
+        Self::with_range_and_rng(precision, builder, GumbelSketchBuilder::DEFAULT_FIXED_RANGE, rng)
+    }
+
+    // creates the estimator like `with_precision_and_rng`, with the given range
+    // of the fixed-point registers
+    pub(crate) fn with_range_and_rng<G: Rng>(precision: u8, builder: B, range: (f64, f64), rng: &mut G) -> Result<Self, GumbelError> {
+        // check if the provided precision and range are within the bounds
+        check_precision(precision)?;
+        check_range(range)?;
 
         // calculate the number of registers as `2^precision`
         let no_registers = 1 << precision;
 
-        // create a uniform [0, 1) rng
-        let mut rng = thread_rng();
-        let unif = Uniform::new(0.0, 1.0);
-
         // initialise the registers to random gumbel values
-        let registers: Vec<_> = (0..no_registers).map(|_| {
-            let q = rng.sample(unif);
-            gen_gumbel::quantile(q)
-        }).collect();
+        let registers = R::random(no_registers, range, rng);
 
         // create the estimator object
         Ok(Self {
@@ -46,39 +322,224 @@ impl<B: BuildHasher> GHLLReal<B> {
             precision,
             no_registers,
             registers,
+            strategy: Strategy::Geometric,
         })
     }
 
     pub fn add<H: Hash + ?Sized>(&mut self, value: &H) {
         // hash the value and separate the hash into the index and the remainder
-        let (index, hash) = hash_value(value, &self.builder, self.precision);
-
-        // create a gumbel random variable
-        let gumbel_value = gen_gumbel::from_bits(hash);
+        let (index, hash) = hash_value_wide(value, &self.builder, self.precision);
 
         // update the register to the max of the gumbel random variables
-        self.registers[index] = f32::max(self.registers[index], gumbel_value);
+        R::update(&mut self.registers, index, hash);
+    }
+
+    // the values of the registers
+    fn values(&self) -> impl Iterator<Item = f64> + '_ {
+        (0..self.no_registers).map(|index| R::get(&self.registers, index))
+    }
+
+    /// The number of bytes taken by the state of the estimator, i.e. by its registers
+    pub fn memory_bytes(&self) -> usize {
+        R::memory_bytes(&self.registers)
+    }
+
+    /// Merges the `other` estimator into this one, so that it estimates the cardinality of
+    /// the union of both streams
+    ///
+    /// Both estimators must use the same precision and hash builder, and start from the same
+    /// initial register values, i.e. be created by `with_precision_and_rng` with equally seeded
+    /// generators, and, for fixed-point registers, share their range; otherwise the initial values
+    /// of both act as additional elements
+    pub fn merge(&mut self, other: &Self) -> Result<(), GumbelError> {
+        if self.precision != other.precision {
+            return Err(GumbelError::IncompatibleMerge { property: "precision" });
+        }
+
+        R::merge(&mut self.registers, &other.registers)
+    }
+
+    /// Serializes the estimator into the versioned binary format read by `from_bytes`,
+    /// along with its strategy; the hash builder is not serialized
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        serial::write_header(&mut bytes, R::KIND, &Header {
+            precision: self.precision,
+            strategy: self.strategy,
+            flags: 0,
+        });
+
+        R::write(&self.registers, &mut bytes);
+
+        bytes
+    }
+
+    /// Deserializes an estimator written by `to_bytes`; the `builder` must be the hash builder
+    /// of the serialized estimator
+    pub fn from_bytes(bytes: &[u8], builder: B) -> Result<Self, GumbelError> {
+        let mut reader = Reader::new(bytes);
+        let header = serial::read_header(&mut reader, R::KIND)?;
+        if header.strategy == Strategy::Occupied || header.flags != 0 {
+            return Err(GumbelError::CorruptData { reason: "the configuration is not supported by GHLLReal" });
+        }
+
+        let no_registers = 1 << header.precision;
+        let registers = R::read(&mut reader, no_registers)?;
+        reader.finish()?;
+
+        Ok(Self {
+            builder,
+            precision: header.precision,
+            no_registers,
+            registers,
+            strategy: header.strategy,
+        })
+    }
+
+    /// Estimates the cardinality with the strategy set by `GumbelSketchBuilder::strategy`,
+    /// by default the geometric one
+    pub fn count(&self) -> f64 {
+        match self.strategy {
+            Strategy::Geometric => self.count_geo(),
+            Strategy::Harmonic => self.count_har(),
+            Strategy::MaximumLikelihood => self.count_ml(),
+            Strategy::BiasCorrected => self.count_bias_corrected(),
+            // the registers are not tracked as free, so that the builder rejects the strategy
+            Strategy::Occupied => unreachable!("GHLLReal does not support the occupied registers strategy"),
+        }
     }
 
     pub fn count_geo(&self) -> f64 {
-        // apply the second half of shift rounding
-        // and calculate the geometric mean of the `exp(register)` terms
-        let registers_sum = self.registers.iter()
-            .map(|&val| val as f64)
-            .sum::<f64>();
+        // the registers hold the gumbel values unrounded, or rounded to the nearest level by `Fixed<N>`,
+        // so the geometric mean of the `exp(register)` terms needs no correction of rounding
+        let registers_sum = self.values().sum::<f64>();
         let registers_mean = registers_sum / self.no_registers as f64;
         
-        self.no_registers as f64 * f64::exp(NEG_GAMMA + registers_mean)
+        self.no_registers as f64 * libm::exp(NEG_GAMMA + registers_mean)
     }
     
     pub fn count_har(&self) -> f64 {
-        // apply the second half of shift rounding
-        // and calculate the harmonic mean of the `exp(register)` terms
-        let registers_sum = self.registers.iter()
-            .map(|&val| f64::exp(-val as f64))
+        // calculate the harmonic mean of the `exp(register)` terms, as in `count_geo`
+        // without a correction of rounding
+        let registers_sum = self.values()
+            .map(|val| libm::exp(-val))
             .sum::<f64>();
         let registers_mean = registers_sum / self.no_registers as f64;
         
         self.no_registers as f64 / registers_mean - 1.0
     }
+
+    pub fn count_ml(&self) -> f64 {
+        self.no_registers as f64 * gen_gumbel::ml_load_real(&self.values().collect::<Vec<_>>())
+    }
+
+    pub fn count_bias_corrected(&self) -> f64 {
+        let m = self.no_registers as f64;
+        let registers_mean = self.values().sum::<f64>() / m;
+
+        // see `GumbelSketch::count_bias_corrected`
+        m * gen_gumbel::mean_load(registers_mean - m * ln_gamma_one_minus(1.0 / m))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::sealed::Register;
+    use ahash::RandomState;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    // fixed-point registers at the lowest level of the range
+    fn lowest<const BITS: u8>(range: (f64, f64), count: usize) -> <Fixed<BITS> as Register>::Registers
+    where
+        Fixed<BITS>: RealRegister,
+    {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&range.0.to_le_bytes());
+        bytes.extend_from_slice(&range.1.to_le_bytes());
+        bytes.resize(16 + (count * BITS as usize).div_ceil(8), 0);
+        <Fixed<BITS> as Register>::read(&mut Reader::new(&bytes), count).unwrap()
+    }
+
+    fn estimator<R: RealRegister>(seed: u64, count: u64) -> GHLLReal<RandomState, R> {
+        let mut estimator = GumbelSketchBuilder::new()
+            .precision(8)
+            .hasher(RandomState::with_seeds(seed, 2, 3, 4))
+            .seed(seed)
+            .build_ghll_real::<R>()
+            .unwrap();
+        for value in 0..count {
+            estimator.add(&value);
+        }
+
+        estimator
+    }
+
+    #[test]
+    fn fixed_values_are_clamped_to_the_range() {
+        // the largest hash gives a gumbel value of about 36
+        let mut registers = lowest::<8>((-4.0, 28.0), 4);
+        Fixed::<8>::update(&mut registers, 1, u64::MAX);
+        assert_eq!(Fixed::<8>::get(&registers, 1), 28.0);
+        assert_eq!(Fixed::<8>::get(&registers, 0), -4.0);
+
+        // nearly all gumbel values lie below the range
+        let registers = Fixed::<12>::random(64, (10.0, 11.0), &mut StdRng::seed_from_u64(0));
+        assert!((0..64).all(|index| Fixed::<12>::get(&registers, index) == 10.0));
+        let registers = Fixed::<12>::random(64, (-11.0, -10.0), &mut StdRng::seed_from_u64(0));
+        assert!((0..64).all(|index| Fixed::<12>::get(&registers, index) == -10.0));
+    }
+
+    #[test]
+    fn fixed_values_are_rounded_to_the_nearest_level() {
+        let range = (-4.0, 28.0);
+        let step = (range.1 - range.0) / 255.0;
+        let mut rng = StdRng::seed_from_u64(1);
+
+        for _ in 0..1000 {
+            let hash = rng.gen();
+            let value = gen_gumbel::from_bits_f64(hash);
+            let mut registers = lowest::<8>(range, 1);
+            Fixed::<8>::update(&mut registers, 0, hash);
+
+            let level = (Fixed::<8>::get(&registers, 0) - range.0) / step;
+            assert!((level - libm::round(level)).abs() < 1e-9, "{} is not a level", level);
+            assert!((Fixed::<8>::get(&registers, 0) - value.clamp(range.0, range.1)).abs() <= step / 2.0 + 1e-12);
+        }
+    }
+
+    #[test]
+    fn fixed_registers_are_packed_without_spare_bits() {
+        let registers = lowest::<12>((-4.0, 28.0), 3);
+        assert_eq!(Fixed::<12>::memory_bytes(&registers), 5);
+        assert_eq!(estimator::<Fixed<8>>(0, 0).memory_bytes(), 256);
+        assert_eq!(estimator::<Fixed<12>>(0, 0).memory_bytes(), 384);
+        assert_eq!(estimator::<Fixed<16>>(0, 0).memory_bytes(), 512);
+
+        // a register spanning two bytes leaves its neighbours unchanged
+        let mut registers = lowest::<12>((-4.0, 28.0), 3);
+        Fixed::<12>::update(&mut registers, 1, u64::MAX);
+        assert_eq!([0, 1, 2].map(|index| Fixed::<12>::get(&registers, index)), [-4.0, 28.0, -4.0]);
+    }
+
+    #[test]
+    fn fixed_registers_approach_f64_with_their_width() {
+        // the registers are the nearest levels of the `f64` registers fed the same hashes
+        let exact = estimator::<f64>(0, 10_000);
+        let fixed = estimator::<Fixed<12>>(0, 10_000);
+        let step = 32.0 / 4095.0;
+        assert!(exact.values().zip(fixed.values()).all(|(exact, fixed)| (exact - fixed).abs() <= step / 2.0 + 1e-12));
+
+        // the mean relative difference of the estimates from those of `f64`
+        let deviation = |estimates: &dyn Fn(u64) -> f64| (0..20)
+            .map(|seed| (estimates(seed) / estimator::<f64>(seed, 10_000).count_geo() - 1.0).abs())
+            .sum::<f64>() / 20.0;
+        let fixed_8 = deviation(&|seed| estimator::<Fixed<8>>(seed, 10_000).count_geo());
+        let fixed_12 = deviation(&|seed| estimator::<Fixed<12>>(seed, 10_000).count_geo());
+        let fixed_16 = deviation(&|seed| estimator::<Fixed<16>>(seed, 10_000).count_geo());
+
+        assert!(fixed_8 > fixed_12 && fixed_12 > fixed_16, "{} {} {}", fixed_8, fixed_12, fixed_16);
+        assert!(fixed_16 < 1e-4, "{}", fixed_16);
+    }
 }
@@ -1,11 +1,27 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod builder;
+mod coding;
 mod common;
+mod error;
 mod gen_gumbel;
 mod registers;
+mod serial;
 
+pub mod sketch;
 pub mod ghll;
 pub mod ghll_real;
 pub mod ghll_plus;
+pub mod ull;
 
+pub use sketch::{GumbelSketch, Strategy};
 pub use ghll::GHLL;
-pub use ghll_real::GHLLReal;
+pub use ghll_real::{Fixed, GHLLReal, RealRegister};
 pub use ghll_plus::GHLLPlus;
+pub use ull::ULL;
+pub use error::GumbelError;
+pub use builder::GumbelSketchBuilder;
+pub use common::{MIN_PRECISION, MAX_PRECISION};
+pub use serial::FORMAT_VERSION;
@@ -1,4 +1,4 @@
-use gumbel_estimation::{GHLL, GHLLPlus, GHLLReal};
+use gumbel_estimation::{GHLL, GHLLPlus, GHLLReal, ULL};
 use std::collections::hash_map::RandomState;
 use std::error::Error;
 use std::fs::File;
@@ -24,7 +24,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     let data = load_data(100_000, 10_000_000)?;
 
     {
-        let mut ghll = GHLL::<_>::with_precision(NO_REGISTERS, builder.clone()).unwrap();
+        let mut ghll = GHLL::<_>::with_precision(NO_REGISTERS, builder.clone())?;
         for d in data.iter() {
             ghll.add(&d);
         }
@@ -33,7 +33,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     }
     
     {
-        let mut ghllr = GHLLReal::<_>::with_precision(NO_REGISTERS, builder.clone()).unwrap();
+        let mut ghllr = GHLLReal::<_>::with_precision(NO_REGISTERS, builder.clone())?;
         for d in data.iter() {
             ghllr.add(&d);
         }
@@ -42,12 +42,20 @@ fn main() -> Result<(), Box<dyn Error>> {
     }
 
     {
-        let mut ghllp = GHLLPlus::<_>::with_precision(NO_REGISTERS, builder.clone()).unwrap();
+        let mut ghllp = GHLLPlus::<_>::with_precision(NO_REGISTERS, builder.clone())?;
         for d in data.iter() {
             ghllp.add(&d);
         }
         println!("GHLL Plus: {}", ghllp.count());
     }
 
+    {
+        let mut ull = ULL::<_>::with_precision(NO_REGISTERS, builder.clone())?;
+        for d in data.iter() {
+            ull.add(&d);
+        }
+        println!("ULL: {}", ull.count());
+    }
+
     Ok(())
 }
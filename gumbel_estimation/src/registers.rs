@@ -1,3 +1,6 @@
+use alloc::{vec, vec::Vec};
+
+#[derive(Clone)]
 pub struct Registers {
     buf: Vec<u32>,
     count: usize,
@@ -11,7 +14,7 @@ impl Registers {
     
     pub fn new(count: usize) -> Self {
         Self {
-            buf: vec![0; ceil(count, Self::COUNT_PER_WORD)],
+            buf: vec![0; count.div_ceil(Self::COUNT_PER_WORD)],
             count,
             // zeros: count,
         }
@@ -24,7 +27,7 @@ impl Registers {
             index % Self::COUNT_PER_WORD,
         );
 
-        (self.buf[quot] >> (rem * Self::COUNT_PER_WORD)) & Self::MASK
+        (self.buf[quot] >> (rem * Self::SIZE)) & Self::MASK
     }
 
     pub fn set(&mut self, index: usize, mut value: u32) {
@@ -49,9 +52,7 @@ impl Registers {
             index % Self::COUNT_PER_WORD,
         );
 
-        let curr = (self.buf[quot] >> (rem * Self::SIZE)) & Self::MASK;
-
-        if value > curr {
+        if value > self.get(index) {
             let mask = Self::MASK << (rem * Self::SIZE);
 
             self.buf[quot] = (self.buf[quot] & !mask)
@@ -59,12 +60,17 @@ impl Registers {
         }
     }
 
-    /*
-     *#[inline]
-     *pub fn iter(&self) -> RegistersIterator {
-     *    RegistersIterator{registers: self, index: 0}
-     *}
-     */
+    /// Sets every register to the maximum of its value and the value of the same register in `other`
+    pub fn merge(&mut self, other: &Registers) {
+        for (index, value) in other.iter().enumerate() {
+            self.set_greater(index, value);
+        }
+    }
+
+    /// The number of bytes taken by the registers
+    pub fn memory_bytes(&self) -> usize {
+        self.buf.len() * core::mem::size_of::<u32>()
+    }
 
     pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
         self.buf.iter()
@@ -75,29 +81,145 @@ impl Registers {
             })
             .take(self.count)
     }
+
+    /// The words holding the registers, `COUNT_PER_WORD` registers per word
+    /// starting from the lowest bits
+    pub fn words(&self) -> &[u32] {
+        &self.buf
+    }
+
+    /// Creates the registers from the words returned by `words`, or `None` if their number
+    /// does not match the count or any unused bit is set
+    pub fn from_words(words: Vec<u32>, count: usize) -> Option<Self> {
+        let registers = Self::new(count);
+        if words.len() != registers.buf.len() {
+            return None;
+        }
+
+        // the bits above the last register of every word must be unset
+        let last = count - (words.len() - 1) * Self::COUNT_PER_WORD;
+        let unused = words.iter()
+            .enumerate()
+            .any(|(i, &word)| {
+                let used = if i + 1 == words.len() { last } else { Self::COUNT_PER_WORD };
+                word >> (used * Self::SIZE) != 0
+            });
+        if unused {
+            return None;
+        }
+
+        Some(Self { buf: words, count })
+    }
 }
 
-pub struct RegistersIterator<'a> {
-    registers: &'a Registers,
-    index: usize,
+/// Registers of `BITS` bits each, packed one after another into bytes starting from the lowest bits,
+/// so that no bits are left unused between them
+#[derive(Clone)]
+pub struct PackedRegisters<const BITS: u8> {
+    bytes: Vec<u8>,
+    count: usize,
 }
 
-impl<'a> Iterator for RegistersIterator<'a> {
-    type Item = u32;
+impl<const BITS: u8> PackedRegisters<BITS> {
+    pub const MAX: u32 = (1 << BITS) - 1;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.index < self.registers.count {
-            let value = self.registers.get(self.index);
-            self.index += 1;
-            Some(value)
-        } else {
-            None
+    pub fn new(count: usize) -> Self {
+        Self {
+            bytes: vec![0; (count * BITS as usize).div_ceil(8)],
+            count,
         }
     }
-}
 
-#[inline(always)]
-pub fn ceil(num: usize, denom: usize) -> usize {
-    (num + denom - 1) / denom
+    // the byte holding the lowest bit of the register, the position of that bit
+    // and the number of bytes the register spans
+    #[inline]
+    fn locate(index: usize) -> (usize, usize, usize) {
+        let bit = index * BITS as usize;
+        let shift = bit % 8;
+
+        (bit / 8, shift, (shift + BITS as usize).div_ceil(8))
+    }
+
+    #[inline]
+    pub fn get(&self, index: usize) -> u32 {
+        let (start, shift, len) = Self::locate(index);
+        let word = self.bytes[start..start + len].iter()
+            .rev()
+            .fold(0, |word, &byte| (word << 8) | byte as u32);
+
+        (word >> shift) & Self::MAX
+    }
+
+    pub fn set(&mut self, index: usize, value: u32) {
+        let (start, shift, len) = Self::locate(index);
+        let (value, mask) = (u32::min(value, Self::MAX) << shift, Self::MAX << shift);
+
+        for (i, byte) in self.bytes[start..start + len].iter_mut().enumerate() {
+            let (value, mask) = ((value >> (8 * i)) as u8, (mask >> (8 * i)) as u8);
+            *byte = (*byte & !mask) | value;
+        }
+    }
+
+    pub fn set_greater(&mut self, index: usize, value: u32) {
+        if value > self.get(index) {
+            self.set(index, value);
+        }
+    }
+
+    /// The number of bytes taken by the registers
+    pub fn memory_bytes(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        (0..self.count).map(|index| self.get(index))
+    }
+
+    /// The bytes holding the registers
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Creates the registers from the bytes returned by `bytes`, or `None` if their number
+    /// does not match the count or any unused bit is set
+    pub fn from_bytes(bytes: Vec<u8>, count: usize) -> Option<Self> {
+        let registers = Self::new(count);
+        if bytes.len() != registers.bytes.len() {
+            return None;
+        }
+
+        // the bits above the last register must be unset
+        let used = count * BITS as usize - (bytes.len() - 1) * 8;
+        if bytes.last().is_some_and(|&byte| used < 8 && byte >> used != 0) {
+            return None;
+        }
+
+        Some(Self { bytes, count })
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_reads_every_register_of_a_word() {
+        // distinct values in all registers of several words, including the partial last one
+        let count = 4 * Registers::COUNT_PER_WORD + 1;
+        let mut registers = Registers::new(count);
+        for index in 0..count {
+            registers.set(index, (index as u32 * 7 + 3) & Registers::MASK);
+        }
+
+        for index in 0..count {
+            assert_eq!(registers.get(index), (index as u32 * 7 + 3) & Registers::MASK, "register {}", index);
+        }
+        assert!(registers.iter().enumerate().all(|(index, value)| value == registers.get(index)));
+
+        registers.set_greater(1, 0);
+        registers.set_greater(2, Registers::MASK);
+        assert_eq!(registers.get(1), 10);
+        assert_eq!(registers.get(2), Registers::MASK);
+        assert_eq!(registers.get(3), 24);
+    }
+}
@@ -0,0 +1,299 @@
+use alloc::vec::Vec;
+
+use crate::common::*;
+use crate::sketch::Strategy;
+
+// The binary format of the serialized estimators, starting with the header
+// `[version, kind, precision, strategy, flags]` followed by the registers of the kind;
+// all multi-byte values are little-endian
+
+/// The version of the format written by `to_bytes`
+pub const FORMAT_VERSION: u8 = 1;
+
+// the kinds of the serialized estimators
+pub const KIND_SKETCH: u8 = 0;
+pub const KIND_REAL: u8 = 1;
+pub const KIND_REAL_F64: u8 = 2;
+pub const KIND_FIXED_8: u8 = 3;
+pub const KIND_FIXED_12: u8 = 4;
+pub const KIND_FIXED_16: u8 = 5;
+
+// the flags of the low-range correction, of the exact mode, which is followed
+// by its threshold, the number of its hashes and the hashes after the registers,
+// and of the entropy-coded registers written by `to_compressed_bytes`
+pub const FLAG_LOW_RANGE_CORRECTION: u8 = 1;
+pub const FLAG_EXACT: u8 = 2;
+pub const FLAG_COMPRESSED: u8 = 4;
+
+/// The header of a serialized estimator
+pub struct Header {
+    pub precision: u8,
+    pub strategy: Strategy,
+    pub flags: u8,
+}
+
+/// Writes the header of an estimator of the given kind
+pub fn write_header(bytes: &mut Vec<u8>, kind: u8, header: &Header) {
+    bytes.extend_from_slice(&[FORMAT_VERSION, kind, header.precision, header.strategy as u8, header.flags]);
+}
+
+/// Reads and validates the header of an estimator of the given kind
+pub fn read_header(reader: &mut Reader, kind: u8) -> Result<Header, GumbelError> {
+    let version = reader.u8()?;
+    if version != FORMAT_VERSION {
+        return Err(GumbelError::UnsupportedVersion { version });
+    }
+    if reader.u8()? != kind {
+        return Err(GumbelError::CorruptData { reason: "the data holds a different estimator" });
+    }
+
+    let precision = reader.u8()?;
+    check_precision(precision)?;
+
+    // the strategies are written as their positions in `Strategy::ALL`
+    let strategy = Strategy::ALL.get(reader.u8()? as usize)
+        .copied()
+        .ok_or(GumbelError::CorruptData { reason: "unknown strategy" })?;
+
+    let flags = reader.u8()?;
+    if flags & !(FLAG_LOW_RANGE_CORRECTION | FLAG_EXACT | FLAG_COMPRESSED) != 0 {
+        return Err(GumbelError::CorruptData { reason: "unknown flags" });
+    }
+
+    Ok(Header { precision, strategy, flags })
+}
+
+/// Reads the values of a serialized estimator, failing with `GumbelError::CorruptData`
+/// when the data ends early
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    pub fn take(&mut self, len: usize) -> Result<&'a [u8], GumbelError> {
+        if self.bytes.len() < len {
+            return Err(GumbelError::CorruptData { reason: "the data is truncated" });
+        }
+
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+
+        Ok(taken)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, GumbelError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn u32(&mut self) -> Result<u32, GumbelError> {
+        let mut word = [0; 4];
+        word.copy_from_slice(self.take(4)?);
+
+        Ok(u32::from_le_bytes(word))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, GumbelError> {
+        let mut word = [0; 8];
+        word.copy_from_slice(self.take(8)?);
+
+        Ok(u64::from_le_bytes(word))
+    }
+
+    /// The data not read yet
+    pub fn rest(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Checks that the whole data has been read
+    pub fn finish(self) -> Result<(), GumbelError> {
+        if !self.bytes.is_empty() {
+            return Err(GumbelError::CorruptData { reason: "the data has trailing bytes" });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ahash::RandomState;
+    use crate::{Fixed, GumbelSketch, GumbelSketchBuilder, GHLLReal, RealRegister};
+
+    fn builder() -> RandomState {
+        RandomState::with_seeds(1, 2, 3, 4)
+    }
+
+    fn sketch(strategy: Strategy, low_range_correction: bool, exact_threshold: usize) -> GumbelSketch<RandomState> {
+        let mut sketch = GumbelSketchBuilder::new()
+            .precision(8)
+            .hasher(builder())
+            .seed(0)
+            .build_ghll()
+            .unwrap()
+            .into_sketch();
+        sketch.set_strategy(strategy);
+        sketch.set_low_range_correction(low_range_correction);
+        sketch.set_exact_threshold(exact_threshold);
+        for value in 0..100_u64 {
+            sketch.add(&value);
+        }
+
+        sketch
+    }
+
+    fn real<R: RealRegister>(strategy: Strategy) -> GHLLReal<RandomState, R> {
+        let mut estimator = GumbelSketchBuilder::new()
+            .precision(8)
+            .hasher(builder())
+            .seed(0)
+            .strategy(strategy)
+            .build_ghll_real::<R>()
+            .unwrap();
+        for value in 0..1000_u64 {
+            estimator.add(&value);
+        }
+
+        estimator
+    }
+
+    fn real_round_trip<R: RealRegister>() {
+        for strategy in [Strategy::Geometric, Strategy::Harmonic] {
+            let estimator = real::<R>(strategy);
+            let restored = GHLLReal::<_, R>::from_bytes(&estimator.to_bytes(), builder()).unwrap();
+            assert_eq!(restored.to_bytes(), estimator.to_bytes());
+            assert_eq!(restored.count(), estimator.count());
+        }
+    }
+
+    // the error of deserializing a sketch from the bytes
+    fn sketch_error(bytes: &[u8]) -> GumbelError {
+        GumbelSketch::from_bytes(bytes, builder()).err().expect("the data must be rejected")
+    }
+
+    fn is_corrupt(error: GumbelError) -> bool {
+        matches!(error, GumbelError::CorruptData { .. })
+    }
+
+    #[test]
+    fn sketch_round_trip() {
+        for (strategy, low_range_correction, exact_threshold) in Strategy::ALL.into_iter()
+            .flat_map(|strategy| [(strategy, false, 0), (strategy, true, 0), (strategy, true, 200)])
+        {
+            let sketch = sketch(strategy, low_range_correction, exact_threshold);
+            let bytes = sketch.to_bytes();
+            assert_eq!(bytes[..5], [FORMAT_VERSION, KIND_SKETCH, 8, strategy as u8, bytes[4]]);
+
+            let restored = GumbelSketch::from_bytes(&bytes, builder()).unwrap();
+            assert_eq!(restored.strategy(), strategy);
+            assert_eq!(restored.low_range_correction(), low_range_correction);
+            assert_eq!(restored.exact_threshold(), exact_threshold);
+            assert_eq!(restored.is_exact(), exact_threshold > 0);
+            assert_eq!(restored.count(), sketch.count());
+            assert_eq!(restored.to_bytes(), bytes);
+        }
+    }
+
+    #[test]
+    fn real_round_trip_of_all_registers() {
+        real_round_trip::<f32>();
+        real_round_trip::<f64>();
+        real_round_trip::<Fixed<8>>();
+        real_round_trip::<Fixed<12>>();
+        real_round_trip::<Fixed<16>>();
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let mut bytes = sketch(Strategy::Geometric, false, 0).to_bytes();
+        for version in [0, FORMAT_VERSION + 1, u8::MAX] {
+            bytes[0] = version;
+            assert_eq!(sketch_error(&bytes), GumbelError::UnsupportedVersion { version });
+        }
+
+        let mut bytes = real::<f32>(Strategy::Geometric).to_bytes();
+        bytes[0] = FORMAT_VERSION + 1;
+        let error = GHLLReal::<_, f32>::from_bytes(&bytes, builder()).err().unwrap();
+        assert_eq!(error, GumbelError::UnsupportedVersion { version: FORMAT_VERSION + 1 });
+    }
+
+    #[test]
+    fn rejects_corrupt_headers() {
+        let bytes = sketch(Strategy::Geometric, true, 0).to_bytes();
+
+        // a truncated header
+        for len in 0..5 {
+            assert!(is_corrupt(sketch_error(&bytes[..len])));
+        }
+
+        // another kind of estimator, in both directions and between the register types
+        let mut other = bytes.clone();
+        other[1] = KIND_REAL;
+        assert!(is_corrupt(sketch_error(&other)));
+        let real_bytes = real::<f32>(Strategy::Geometric).to_bytes();
+        assert!(is_corrupt(sketch_error(&real_bytes)));
+        assert!(is_corrupt(GHLLReal::<_, f32>::from_bytes(&bytes, builder()).err().unwrap()));
+        assert!(is_corrupt(GHLLReal::<_, f64>::from_bytes(&real_bytes, builder()).err().unwrap()));
+        assert!(is_corrupt(GHLLReal::<_, Fixed<8>>::from_bytes(&real::<Fixed<12>>(Strategy::Geometric).to_bytes(), builder()).err().unwrap()));
+
+        // a precision out of range
+        let mut other = bytes.clone();
+        other[2] = 17;
+        assert!(matches!(sketch_error(&other), GumbelError::InvalidPrecision { precision: 17, .. }));
+
+        // an unknown strategy and unknown flags
+        let mut other = bytes.clone();
+        other[3] = Strategy::ALL.len() as u8;
+        assert!(is_corrupt(sketch_error(&other)));
+        let mut other = bytes.clone();
+        other[4] |= 0x80;
+        assert!(is_corrupt(sketch_error(&other)));
+
+        // the strategy and flags GHLLReal does not support
+        let mut other = real_bytes.clone();
+        other[3] = Strategy::Occupied as u8;
+        assert!(is_corrupt(GHLLReal::<_, f32>::from_bytes(&other, builder()).err().unwrap()));
+        let mut other = real_bytes.clone();
+        other[4] = FLAG_LOW_RANGE_CORRECTION;
+        assert!(is_corrupt(GHLLReal::<_, f32>::from_bytes(&other, builder()).err().unwrap()));
+    }
+
+    #[test]
+    fn rejects_corrupt_bodies() {
+        for bytes in [sketch(Strategy::Geometric, false, 0).to_bytes(), sketch(Strategy::Occupied, true, 200).to_bytes()] {
+            // truncated and trailing data
+            for len in 5..bytes.len() {
+                assert!(is_corrupt(sketch_error(&bytes[..len])));
+            }
+            let mut longer = bytes.clone();
+            longer.push(0);
+            assert!(is_corrupt(sketch_error(&longer)));
+
+            // the unused bits of the last word of the registers; 256 registers take 43 words,
+            // the last holding 4 registers in its lowest 20 bits
+            let mut other = bytes.clone();
+            other[5 + 42 * 4 + 3] |= 0x80;
+            assert!(is_corrupt(sketch_error(&other)));
+        }
+
+        let real_bytes = real::<f64>(Strategy::Geometric).to_bytes();
+        assert!(is_corrupt(GHLLReal::<_, f64>::from_bytes(&real_bytes[..real_bytes.len() - 1], builder()).err().unwrap()));
+
+        // the hashes of the exact mode, which must be sorted and within the threshold
+        let bytes = sketch(Strategy::Occupied, true, 200).to_bytes();
+        let exact = 5 + 43 * 4 + 256 / 8;
+        let mut other = bytes.clone();
+        other[exact..exact + 4].copy_from_slice(&99_u32.to_le_bytes());
+        assert!(is_corrupt(sketch_error(&other)));
+        let mut other = bytes.clone();
+        let (first, second) = (exact + 8, exact + 16);
+        let hash: [u8; 8] = other[first..second].try_into().unwrap();
+        other.copy_within(second..second + 8, first);
+        other[second..second + 8].copy_from_slice(&hash);
+        assert!(is_corrupt(sketch_error(&other)));
+    }
+}
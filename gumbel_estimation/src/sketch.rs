@@ -0,0 +1,791 @@
+use bitvec::prelude::*;
+use alloc::{vec, vec::Vec};
+use core::f64::consts::E;
+use core::hash::{Hash, BuildHasher};
+use rand::Rng;
+#[cfg(feature = "std")]
+use rand::thread_rng;
+use rand::distributions::{Uniform};
+
+use crate::coding::{self, BitReader, BitWriter, HuffmanDecoder};
+use crate::common::*;
+use crate::gen_gumbel;
+use crate::registers::Registers;
+use crate::serial::{self, Header, Reader};
+
+/// The strategy estimating the cardinality from the registers of a `GumbelSketch`
+///
+/// Any strategy becomes a linear-counting hybrid with the low-range correction of the sketch,
+/// which performs linear counting while at least `m/e` of the `m` registers are still free;
+/// `GHLLPlus` is the `Occupied` strategy with the correction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// the geometric mean of the `exp(register)` terms
+    Geometric,
+    /// the harmonic mean of the `exp(register)` terms
+    Harmonic,
+    /// the maximum likelihood estimate, taking the rounding of the registers into account
+    MaximumLikelihood,
+    /// the geometric mean corrected for its bias, i.e. the bias of the mean of finitely
+    /// many registers, of the uneven split of the elements among the registers and of
+    /// the initial register values
+    BiasCorrected,
+    /// the geometric mean of the registers which received any element, as in GHLL+
+    Occupied,
+}
+
+impl Strategy {
+    pub const ALL: [Strategy; 5] = [
+        Strategy::Geometric,
+        Strategy::Harmonic,
+        Strategy::MaximumLikelihood,
+        Strategy::BiasCorrected,
+        Strategy::Occupied,
+    ];
+
+    // the description of the strategy in error messages
+    pub(crate) fn description(self) -> &'static str {
+        match self {
+            Strategy::Geometric => "the geometric strategy",
+            Strategy::Harmonic => "the harmonic strategy",
+            Strategy::MaximumLikelihood => "the maximum likelihood strategy",
+            Strategy::BiasCorrected => "the bias-corrected strategy",
+            Strategy::Occupied => "the occupied registers strategy",
+        }
+    }
+}
+
+/// The sketch of the Gumbel estimators: discretized registers holding the maxima of the Gumbel
+/// values of the added elements, along with the bit vector of the registers without any element
+///
+/// The estimate is computed by a `Strategy`; `count` uses the strategy of the sketch and
+/// `estimate` any other, so that several strategies can be compared on the same registers.
+/// In the exact mode, the sketch additionally keeps the distinct hashes of the first elements
+/// and counts them exactly, until their number exceeds a threshold and the registers take over.
+#[derive(Clone)]
+pub struct GumbelSketch<B: BuildHasher> {
+    builder: B,
+    precision: u8,
+    no_registers: usize,
+    registers: Registers,
+    free: BitVec,
+    strategy: Strategy,
+    low_range_correction: bool,
+    // the sorted distinct hashes of the elements, kept in the exact mode until the threshold
+    exact: Option<Vec<u64>>,
+    exact_threshold: usize,
+}
+
+impl<B: BuildHasher> GumbelSketch<B> {
+    /// Creates a new `GumbelSketch` object with a custom precision and hash builder, estimating
+    /// with the geometric strategy without the low-range correction
+    ///
+    /// # Arguments
+    ///
+    /// - `precision` - corresponds to the number of registers used by this estimator using the
+    ///   formula `no_registers = 2^precision`; the accepted values lie in the range {4, 5, ..., 16}
+    /// - `builder` - this is a hash builder that will be used for hashing provided values
+    #[cfg(feature = "std")]
+    pub fn with_precision(precision: u8, builder: B) -> Result<Self, GumbelError> {
+        Self::with_precision_and_rng(precision, builder, &mut thread_rng())
+    }
+
+    /// Creates a new `GumbelSketch` object like `with_precision`, but draws the initial values
+    /// of the registers from the provided random number generator instead of `thread_rng`,
+    /// which makes the estimator reproducible from the generator's seed
+    /// and is the only constructor without the `std` feature
+    pub fn with_precision_and_rng<R: Rng>(precision: u8, builder: B, rng: &mut R) -> Result<Self, GumbelError> {
+        // check if the provided precision is within the bounds
+        check_precision(precision)?;
+
+        // calculate the number of registers as `2^precision`
+        let no_registers = 1 << precision;
+
+        // create a uniform [0, 1) distribution
+        let unif = Uniform::new(0.0, 1.0);
+
+        // initialise the registers to random gumbel values
+        let mut registers = Registers::new(no_registers);
+        for i in 0..no_registers {
+            let q = rng.sample(unif);
+            let c = gen_gumbel::mantissa_to_float(builder.hash_one(i) as u32);
+            let gumbel_value = gen_gumbel::quantile_rounded(q, c);
+            registers.set(i, gumbel_value);
+        }
+
+        // mark all registers as free
+        let free = bitvec![1; no_registers];
+
+        // create the estimator object
+        Ok(Self {
+            builder,
+            precision,
+            no_registers,
+            registers,
+            free,
+            strategy: Strategy::Geometric,
+            low_range_correction: false,
+            exact: None,
+            exact_threshold: 0,
+        })
+    }
+
+    /// The strategy used by `count`
+    pub fn strategy(&self) -> Strategy {
+        self.strategy
+    }
+
+    pub fn set_strategy(&mut self, strategy: Strategy) {
+        self.strategy = strategy;
+    }
+
+    /// Whether the strategies perform linear counting while many registers are free
+    pub fn low_range_correction(&self) -> bool {
+        self.low_range_correction
+    }
+
+    pub fn set_low_range_correction(&mut self, enabled: bool) {
+        self.low_range_correction = enabled;
+    }
+
+    /// The number of distinct elements counted exactly in the exact mode, or 0 if disabled
+    pub fn exact_threshold(&self) -> usize {
+        self.exact_threshold
+    }
+
+    /// Enables the exact mode for up to `threshold` distinct elements, or disables it if 0;
+    /// the mode can only be enabled while the sketch still knows all of its elements,
+    /// i.e. before any element is added, or in the exact mode with at most `threshold` elements
+    pub fn set_exact_threshold(&mut self, threshold: usize) {
+        self.exact_threshold = threshold;
+        self.exact = match self.exact.take() {
+            _ if threshold == 0 => None,
+            Some(hashes) => (hashes.len() <= threshold).then_some(hashes),
+            None if self.free.all() => Some(Vec::new()),
+            None => None,
+        };
+    }
+
+    /// Whether the estimates are still exact counts of the distinct elements
+    pub fn is_exact(&self) -> bool {
+        self.exact.is_some()
+    }
+
+    pub fn add<H: Hash + ?Sized>(&mut self, value: &H) {
+        // keep the distinct hashes in the exact mode, or hand over to the registers
+        // once their number would exceed the threshold
+        if let Some(hashes) = &mut self.exact {
+            let hash = self.builder.hash_one(value);
+            if let Err(position) = hashes.binary_search(&hash) {
+                if hashes.len() < self.exact_threshold {
+                    hashes.insert(position, hash);
+                } else {
+                    self.exact = None;
+                }
+            }
+        }
+
+        // hash the value and separate the hash into the index and the remainder
+        let (index, hash) = hash_value(value, &self.builder, self.precision);
+
+        // mark the register as taken
+        self.free.set(index, false);
+
+        // create a gumbel random variable
+        let gumbel_value = gen_gumbel::from_bits_rounded(
+            hash,
+            gen_gumbel::mantissa_to_float(self.builder.hash_one(index) as u32)
+        );
+
+        // update the register to the max of the gumbel random variables
+        self.registers.set_greater(index, gumbel_value);
+    }
+
+    /// Checks if any register reached the largest value it can hold, in which case the estimate
+    /// is only a lower bound of the cardinality; this happens once the cardinality per register
+    /// exceeds about `e^15`
+    pub fn check_saturation(&self) -> Result<(), GumbelError> {
+        let count = self.registers.iter().filter(|&val| val == gen_gumbel::MAX_ROUNDED).count();
+        if count > 0 {
+            return Err(GumbelError::SaturatedRegisters { count });
+        }
+
+        Ok(())
+    }
+
+    /// The number of bytes taken by the state of the estimator, i.e. by its registers,
+    /// the bit vector of the free registers and, in the exact mode, the hashes of up to
+    /// `exact_threshold` elements, which the mode may grow to before the registers take over
+    pub fn memory_bytes(&self) -> usize {
+        let exact = self.exact.as_ref()
+            .map_or(0, |_| self.exact_threshold.saturating_mul(core::mem::size_of::<u64>()));
+
+        (self.registers.memory_bytes() + core::mem::size_of_val(self.free.as_raw_slice())).saturating_add(exact)
+    }
+
+    /// Merges the `other` estimator into this one, so that it estimates the cardinality of
+    /// the union of both streams; the strategy of this estimator is kept
+    ///
+    /// Both estimators must use the same precision and hash builder, and start from the same
+    /// initial register values, i.e. be created by `with_precision_and_rng` with equally seeded
+    /// generators; otherwise the initial values of both act as additional elements
+    pub fn merge(&mut self, other: &Self) -> Result<(), GumbelError> {
+        if self.precision != other.precision {
+            return Err(GumbelError::IncompatibleMerge { property: "precision" });
+        }
+
+        self.registers.merge(&other.registers);
+        self.free &= other.free.as_bitslice();
+
+        // the union stays exact only if both estimators are, and it does not exceed the threshold
+        self.exact = match (self.exact.take(), &other.exact) {
+            (Some(mut hashes), Some(other_hashes)) => {
+                hashes.extend_from_slice(other_hashes);
+                hashes.sort_unstable();
+                hashes.dedup();
+                (hashes.len() <= self.exact_threshold).then_some(hashes)
+            }
+            _ => None,
+        };
+
+        Ok(())
+    }
+
+    /// Serializes the sketch into the versioned binary format read by `from_bytes`,
+    /// along with its strategy, low-range correction and exact mode; the hash builder
+    /// is not serialized
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.serialize(false)
+    }
+
+    /// Serializes the sketch like `to_bytes`, but with entropy-coded registers: the register values
+    /// are written with a Huffman code of their frequencies in the sketch, and the free registers
+    /// as the Golomb-Rice coded gaps between the rarer of the free and the taken registers;
+    /// `from_bytes` reads both formats
+    ///
+    /// From precision 8 on, a register takes about 2.4 bits along with its free bit, instead of
+    /// the 6.3 bits of `to_bytes`, rising to about 3.5 bits while the registers have a few elements
+    /// each, where the free bits are the least predictable. At lower precisions the code lengths
+    /// written with the registers take a large share, e.g. 9 to 12 bytes instead of 14 at precision 4.
+    pub fn to_compressed_bytes(&self) -> Vec<u8> {
+        self.serialize(true)
+    }
+
+    fn serialize(&self, compressed: bool) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        serial::write_header(&mut bytes, serial::KIND_SKETCH, &Header {
+            precision: self.precision,
+            strategy: self.strategy,
+            flags: if self.low_range_correction { serial::FLAG_LOW_RANGE_CORRECTION } else { 0 }
+                | if self.exact.is_some() { serial::FLAG_EXACT } else { 0 }
+                | if compressed { serial::FLAG_COMPRESSED } else { 0 },
+        });
+
+        if compressed {
+            bytes.extend_from_slice(&self.encode_registers());
+        } else {
+            for word in self.registers.words() {
+                bytes.extend_from_slice(&word.to_le_bytes());
+            }
+
+            // the free registers as bits, starting from the lowest bit of the first byte
+            for chunk in self.free.chunks(8) {
+                bytes.push(chunk.iter().by_vals().rev().fold(0, |byte, free| byte << 1 | free as u8));
+            }
+        }
+
+        if let Some(hashes) = &self.exact {
+            let threshold = u32::try_from(self.exact_threshold).unwrap_or(u32::MAX);
+            bytes.extend_from_slice(&threshold.to_le_bytes());
+            bytes.extend_from_slice(&(hashes.len() as u32).to_le_bytes());
+            for hash in hashes {
+                bytes.extend_from_slice(&hash.to_le_bytes());
+            }
+        }
+
+        bytes
+    }
+
+    // the entropy-coded registers and free registers of `to_compressed_bytes`
+    fn encode_registers(&self) -> Vec<u8> {
+        let mut writer = BitWriter::new();
+
+        // the register values as offsets from the smallest one, written with the Huffman code
+        // of their frequencies, preceded by the lengths of the codes of all offsets up to the largest
+        let (min, max) = self.registers.iter().fold((u32::MAX, 0), |(min, max), val| (min.min(val), max.max(val)));
+        writer.bits(min, Registers::SIZE as u8);
+        writer.bits(max - min, Registers::SIZE as u8);
+        if max > min {
+            let mut frequencies = vec![0; (max - min + 1) as usize];
+            for val in self.registers.iter() {
+                frequencies[(val - min) as usize] += 1;
+            }
+
+            let lengths = coding::code_lengths(&frequencies);
+            for &length in &lengths {
+                writer.bits(length as u32, 4);
+            }
+
+            let codes = coding::canonical_codes(&lengths);
+            for val in self.registers.iter() {
+                let offset = (val - min) as usize;
+                writer.bits(codes[offset], lengths[offset]);
+            }
+        }
+
+        // the rarer of the free and taken registers, whose number fits in `precision` bits,
+        // as the gaps between their positions
+        let no_free = self.free.count_ones();
+        let rare = no_free <= self.no_registers - no_free;
+        let gaps: Vec<u32> = self.free.iter()
+            .by_vals()
+            .enumerate()
+            .filter(|&(_, free)| free == rare)
+            .scan(0, |next, (position, _)| {
+                let gap = position - *next;
+                *next = position + 1;
+                Some(gap as u32)
+            })
+            .collect();
+
+        writer.bit(rare);
+        writer.bits(gaps.len() as u32, self.precision);
+        if !gaps.is_empty() {
+            let k = coding::rice_parameter(&gaps);
+            writer.bits(k as u32, 4);
+            for &gap in &gaps {
+                coding::write_rice(&mut writer, gap, k);
+            }
+        }
+
+        writer.finish()
+    }
+
+    // reads the registers and free registers written by `encode_registers`
+    fn decode_registers(reader: &mut BitReader, precision: u8) -> Result<(Registers, BitVec), GumbelError> {
+        let no_registers = 1 << precision;
+
+        let mut registers = Registers::new(no_registers);
+        let min = reader.bits(Registers::SIZE as u8)?;
+        let span = reader.bits(Registers::SIZE as u8)?;
+        if min + span > gen_gumbel::MAX_ROUNDED {
+            return Err(GumbelError::CorruptData { reason: "a register is out of range" });
+        }
+        if span > 0 {
+            let lengths = (0..=span)
+                .map(|_| reader.bits(4).map(|length| length as u8))
+                .collect::<Result<Vec<_>, _>>()?;
+            let decoder = HuffmanDecoder::new(&lengths)?;
+            for i in 0..no_registers {
+                registers.set(i, min + decoder.decode(reader)? as u32);
+            }
+        } else {
+            for i in 0..no_registers {
+                registers.set(i, min);
+            }
+        }
+
+        let rare = reader.bit()?;
+        let count = reader.bits(precision)?;
+        let mut free = BitVec::repeat(!rare, no_registers);
+        if count > 0 {
+            let k = reader.bits(4)? as u8;
+            let mut next = 0;
+            for _ in 0..count {
+                let max = (no_registers - next).checked_sub(1)
+                    .ok_or(GumbelError::CorruptData { reason: "a coded value is out of range" })?;
+                let position = next + coding::read_rice(reader, k, max as u32)? as usize;
+                free.set(position, rare);
+                next = position + 1;
+            }
+        }
+
+        Ok((registers, free))
+    }
+
+    /// Deserializes a sketch written by `to_bytes` or `to_compressed_bytes`; the `builder` must be
+    /// the hash builder of the serialized sketch, since the hashes select the registers and their rounding
+    pub fn from_bytes(bytes: &[u8], builder: B) -> Result<Self, GumbelError> {
+        let mut reader = Reader::new(bytes);
+        let header = serial::read_header(&mut reader, serial::KIND_SKETCH)?;
+
+        let no_registers = 1 << header.precision;
+        let (registers, free) = if header.flags & serial::FLAG_COMPRESSED != 0 {
+            let mut bits = BitReader::new(reader.rest());
+            let decoded = Self::decode_registers(&mut bits, header.precision)?;
+            reader.take(bits.bytes_read())?;
+
+            decoded
+        } else {
+            let no_words = Registers::new(no_registers).words().len();
+            let words = (0..no_words)
+                .map(|_| reader.u32())
+                .collect::<Result<Vec<_>, _>>()?;
+            let registers = Registers::from_words(words, no_registers)
+                .ok_or(GumbelError::CorruptData { reason: "the registers have unused bits set" })?;
+
+            let free = BitVec::from_iter(reader.take(no_registers / 8)?
+                .iter()
+                .flat_map(|byte| (0..8).map(move |i| byte >> i & 1 == 1)));
+
+            (registers, free)
+        };
+
+        // the threshold and the hashes of the exact mode
+        let (exact, exact_threshold) = if header.flags & serial::FLAG_EXACT != 0 {
+            let threshold = reader.u32()? as usize;
+            let len = reader.u32()? as usize;
+            if len > threshold {
+                return Err(GumbelError::CorruptData { reason: "the exact mode exceeds its threshold" });
+            }
+            let hashes = (0..len)
+                .map(|_| reader.u64())
+                .collect::<Result<Vec<_>, _>>()?;
+            if hashes.windows(2).any(|pair| pair[0] >= pair[1]) {
+                return Err(GumbelError::CorruptData { reason: "the hashes of the exact mode are not sorted" });
+            }
+
+            (Some(hashes), threshold)
+        } else {
+            (None, 0)
+        };
+
+        reader.finish()?;
+
+        Ok(Self {
+            builder,
+            precision: header.precision,
+            no_registers,
+            registers,
+            free,
+            strategy: header.strategy,
+            low_range_correction: header.flags & serial::FLAG_LOW_RANGE_CORRECTION != 0,
+            exact,
+            exact_threshold,
+        })
+    }
+
+    /// Estimates the cardinality with the strategy of the sketch
+    pub fn count(&self) -> f64 {
+        self.estimate(self.strategy)
+    }
+
+    /// Estimates the cardinality with the given strategy, applying the low-range correction
+    /// of the sketch if enabled; in the exact mode, the exact count is returned instead
+    pub fn estimate(&self, strategy: Strategy) -> f64 {
+        if let Some(hashes) = &self.exact {
+            return hashes.len() as f64;
+        }
+
+        if self.low_range_correction {
+            if let Some(estimate) = self.linear_counting() {
+                return estimate;
+            }
+        }
+
+        match strategy {
+            Strategy::Geometric => self.count_geo(),
+            Strategy::Harmonic => self.count_har(),
+            Strategy::MaximumLikelihood => self.count_ml(),
+            Strategy::BiasCorrected => self.count_bias_corrected(),
+            Strategy::Occupied => self.count_occupied(),
+        }
+    }
+
+    // the linear counting estimate if at least `m/e` registers are free
+    fn linear_counting(&self) -> Option<f64> {
+        // compute the numbers of free registers
+        let no_free = self.free.count_ones();
+
+        if no_free as f64 >= self.no_registers as f64 / E {
+            Some(self.no_registers as f64 * libm::log(self.no_registers as f64 / no_free as f64))
+        } else {
+            None
+        }
+    }
+
+    // the registers with the second half of shift rounding applied, i.e. the estimates
+    // of the maxima of the gumbel values
+    fn unrounded(&self) -> impl Iterator<Item = f64> + '_ {
+        self.registers.iter()
+            .enumerate()
+            .map(|(i, val)| (val as i32 - gen_gumbel::BIAS) as f64 + 0.5 - gen_gumbel::mantissa_to_float(self.builder.hash_one(i) as u32) as f64)
+    }
+
+    pub fn count_geo(&self) -> f64 {
+        // calculate the geometric mean of the `exp(register)` terms
+        let registers_mean = self.unrounded().sum::<f64>() / self.no_registers as f64;
+
+        self.no_registers as f64 * libm::exp(NEG_GAMMA + registers_mean)
+    }
+
+    pub fn count_har(&self) -> f64 {
+        // calculate the harmonic mean of the `exp(register)` terms
+        let registers_sum = self.unrounded()
+            .map(|val| libm::exp(-val))
+            .sum::<f64>();
+        let registers_mean = registers_sum / self.no_registers as f64;
+
+        self.no_registers as f64 / registers_mean - 1.0
+    }
+
+    pub fn count_ml(&self) -> f64 {
+        let registers: Vec<_> = self.registers.iter()
+            .enumerate()
+            .map(|(i, val)| (val, gen_gumbel::mantissa_to_float(self.builder.hash_one(i) as u32)))
+            .collect();
+
+        self.no_registers as f64 * gen_gumbel::ml_load(&registers)
+    }
+
+    pub fn count_bias_corrected(&self) -> f64 {
+        let m = self.no_registers as f64;
+        let registers_mean = self.unrounded().sum::<f64>() / m;
+
+        // a gumbel value `G` with the location `μ` has `E[exp(t·G)] = exp(t·μ)·Γ(1 - t)`, so the
+        // exponential of the mean of `m` registers exceeds that of their mean location by the factor
+        // `Γ(1 - 1/m)^m` on average; subtracting `m·ln(Γ(1 - 1/m)) = γ + ζ(2)/(2m) + ...` instead of `γ`
+        // removes the resulting bias of about `ζ(2)/(2m)`. The locations depend on the uneven numbers
+        // of elements of the registers, which `mean_load` takes into account
+        m * gen_gumbel::mean_load(registers_mean - m * ln_gamma_one_minus(1.0 / m))
+    }
+
+    pub fn count_occupied(&self) -> f64 {
+        // count the number of occupied registers
+        let no_occupied = self.free.count_zeros();
+        if no_occupied == 0 {
+            return 0.0;
+        }
+
+        // calculate the geometric mean of the `exp(register)` terms of the occupied registers
+        let registers_sum = self.unrounded()
+            .zip(self.free.iter())
+            .filter_map(|(val, free)| if *free { None } else { Some(val) })
+            .sum::<f64>();
+        let registers_mean = registers_sum / no_occupied as f64;
+
+        no_occupied as f64 * libm::exp(NEG_GAMMA + registers_mean) - self.no_registers as f64 / 2.0 - 0.5
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ahash::RandomState;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    fn builder() -> RandomState {
+        RandomState::with_seeds(1, 2, 3, 4)
+    }
+
+    fn sketch(precision: u8, cardinality: u64) -> GumbelSketch<RandomState> {
+        let mut sketch = GumbelSketch::with_precision_and_rng(precision, builder(), &mut StdRng::seed_from_u64(0)).unwrap();
+        for value in 0..cardinality {
+            sketch.add(&value);
+        }
+
+        sketch
+    }
+
+    // the sketch decoded from its compressed form, which must equal the sketch
+    fn compressed_round_trip(sketch: &GumbelSketch<RandomState>) -> usize {
+        let bytes = sketch.to_compressed_bytes();
+        let restored = GumbelSketch::from_bytes(&bytes, builder()).unwrap();
+        assert_eq!(restored.to_bytes(), sketch.to_bytes());
+
+        bytes.len()
+    }
+
+    fn assert_corrupt(bytes: &[u8]) {
+        match GumbelSketch::from_bytes(bytes, builder()) {
+            Err(GumbelError::CorruptData { .. }) => {}
+            Err(e) => panic!("expected corrupt data, got {}", e),
+            Ok(_) => panic!("expected corrupt data, got a sketch"),
+        }
+    }
+
+    // the header of a compressed sketch followed by the bits written by `write`
+    fn compressed(precision: u8, write: impl FnOnce(&mut BitWriter)) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        serial::write_header(&mut bytes, serial::KIND_SKETCH, &Header {
+            precision,
+            strategy: Strategy::Geometric,
+            flags: serial::FLAG_COMPRESSED,
+        });
+        let mut writer = BitWriter::new();
+        write(&mut writer);
+        bytes.extend_from_slice(&writer.finish());
+
+        bytes
+    }
+
+    #[test]
+    fn compressed_round_trip_at_all_precisions() {
+        for precision in MIN_PRECISION..=MAX_PRECISION {
+            let m = 1_u64 << precision;
+            for cardinality in [0, 1, 10, m / 2, m, 4 * m] {
+                let size = compressed_round_trip(&sketch(precision, cardinality));
+                assert!(size < sketch(precision, cardinality).to_bytes().len(), "precision {}, cardinality {}", precision, cardinality);
+            }
+        }
+    }
+
+    #[test]
+    fn compressed_round_trip_of_special_registers() {
+        for precision in [MIN_PRECISION, 10, MAX_PRECISION] {
+            let mut sketch = sketch(precision, 0);
+
+            // all registers equal and free, or equal and taken, at the smallest and largest values
+            for (value, free) in [(0, true), (7, true), (7, false), (gen_gumbel::MAX_ROUNDED, false)] {
+                for i in 0..sketch.no_registers {
+                    sketch.registers.set(i, value);
+                }
+                sketch.free = BitVec::repeat(free, sketch.no_registers);
+                compressed_round_trip(&sketch);
+            }
+
+            // saturated registers next to the smallest values, with every other register free
+            for i in 0..sketch.no_registers {
+                sketch.registers.set(i, if i % 3 == 0 { gen_gumbel::MAX_ROUNDED } else { 0 });
+                sketch.free.set(i, i % 2 == 0);
+            }
+            assert!(sketch.check_saturation().is_err());
+            compressed_round_trip(&sketch);
+
+            // the exact mode follows the compressed registers
+            let mut exact = self::sketch(precision, 0);
+            exact.set_exact_threshold(100);
+            for value in 0..50 {
+                exact.add(&value);
+            }
+            compressed_round_trip(&exact);
+        }
+    }
+
+    #[test]
+    fn compressed_rejects_truncated_and_trailing_data() {
+        for sketch in [sketch(4, 0), sketch(8, 1000), sketch(12, 100)] {
+            let bytes = sketch.to_compressed_bytes();
+            for len in 0..bytes.len() {
+                assert_corrupt(&bytes[..len]);
+            }
+
+            let mut longer = bytes.clone();
+            longer.push(0);
+            assert_corrupt(&longer);
+        }
+    }
+
+    #[test]
+    fn compressed_rejects_corrupt_data() {
+        // a register above the largest value
+        assert_corrupt(&compressed(4, |writer| {
+            writer.bits(gen_gumbel::MAX_ROUNDED, 5);
+            writer.bits(1, 5);
+        }));
+
+        // code lengths which do not form a prefix code
+        assert_corrupt(&compressed(4, |writer| {
+            writer.bits(0, 5);
+            writer.bits(2, 5);
+            for _ in 0..3 {
+                writer.bits(1, 4);
+            }
+        }));
+
+        // more free registers than there are positions
+        assert_corrupt(&compressed(4, |writer| {
+            writer.bits(3, 5);
+            writer.bits(0, 5);
+            writer.bit(true);
+            writer.bits(15, 4);
+            writer.bits(0, 4);
+            for _ in 0..15 {
+                coding::write_rice(writer, 1, 0);
+            }
+        }));
+
+        // flipped bits lead either to another valid sketch or to an error, never to a panic
+        let bytes = sketch(8, 1000).to_compressed_bytes();
+        for bit in 8 * 5..8 * bytes.len() {
+            let mut flipped = bytes.clone();
+            flipped[bit / 8] ^= 1 << (bit % 8);
+            if let Err(e) = GumbelSketch::from_bytes(&flipped, builder()) {
+                assert!(matches!(e, GumbelError::CorruptData { .. }), "{}", e);
+            }
+        }
+    }
+
+    // sketches of the same precision fed disjoint sets of `cardinality` values
+    fn sketches(precision: u8, cardinality: u64, count: u64) -> impl Iterator<Item = GumbelSketch<RandomState>> {
+        (0..count).map(move |trial| {
+            let mut sketch = GumbelSketch::with_precision_and_rng(precision, builder(), &mut StdRng::seed_from_u64(trial)).unwrap();
+            for value in 0..cardinality {
+                sketch.add(&(value + (trial << 32)));
+            }
+
+            sketch
+        })
+    }
+
+    #[test]
+    fn strategies_estimate_mid_range_cardinalities() {
+        let estimates: Vec<_> = sketches(10, 50_000, 10)
+            .map(|sketch| Strategy::ALL.map(|strategy| sketch.estimate(strategy) / 50_000.0))
+            .collect();
+
+        for (i, strategy) in Strategy::ALL.into_iter().enumerate() {
+            let relative: Vec<_> = estimates.iter().map(|estimates| estimates[i]).collect();
+            assert!(relative.iter().all(|r| (r - 1.0).abs() < 0.12), "{}: {:?}", strategy.description(), relative);
+            let mean = relative.iter().sum::<f64>() / relative.len() as f64;
+            assert!((mean - 1.0).abs() < 0.04, "{}: {}", strategy.description(), mean);
+        }
+    }
+
+    #[test]
+    fn occupied_strategy_counts_small_cardinalities() {
+        for cardinality in [1, 2, 10, 50, 100] {
+            for mut sketch in sketches(10, cardinality, 5) {
+                sketch.set_low_range_correction(true);
+                let estimate = sketch.estimate(Strategy::Occupied);
+                let tolerance = f64::max(0.5, 0.05 * cardinality as f64);
+                assert!((estimate - cardinality as f64).abs() <= tolerance, "{} for {}", estimate, cardinality);
+            }
+        }
+    }
+
+    #[test]
+    fn bias_correction_is_the_log_moment_of_the_mean_gumbel() {
+        for precision in MIN_PRECISION..=MAX_PRECISION {
+            let m = (1_u64 << precision) as f64;
+            let exact = libm::lgamma(1.0 - 1.0 / m);
+            assert!((ln_gamma_one_minus(1.0 / m) - exact).abs() <= 1e-12 * exact.abs(), "precision {}", precision);
+        }
+
+        // `ln(E[exp(mean of m standard gumbel values)])` by simulation
+        let mut rng = StdRng::seed_from_u64(0);
+        let trials = 100_000;
+        let moment = (0..trials)
+            .map(|_| libm::exp((0..16).map(|_| gen_gumbel::quantile_f64(rng.gen())).sum::<f64>() / 16.0))
+            .sum::<f64>() / trials as f64;
+        let correction = 16.0 * ln_gamma_one_minus(1.0 / 16.0);
+        assert!((libm::log(moment) - correction).abs() < 0.005, "{} {}", libm::log(moment), correction);
+        assert!(correction - (-NEG_GAMMA) > 0.04);
+    }
+
+    #[test]
+    fn bias_corrected_strategy_is_unbiased_at_low_precision() {
+        let (mut corrected, mut gamma) = (0.0, 0.0);
+        for sketch in sketches(4, 300, 3000) {
+            corrected += sketch.count_bias_corrected() / 300.0;
+
+            // the same estimate corrected by `γ` only
+            let mean = sketch.unrounded().sum::<f64>() / 16.0;
+            gamma += 16.0 * gen_gumbel::mean_load(mean + NEG_GAMMA) / 300.0;
+        }
+
+        assert!((corrected / 3000.0 - 1.0).abs() < 0.02, "{}", corrected / 3000.0);
+        assert!(gamma / 3000.0 - 1.0 > 0.035, "{}", gamma / 3000.0);
+    }
+}
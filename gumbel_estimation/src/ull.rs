@@ -0,0 +1,228 @@
+use core::hash::{Hash, BuildHasher};
+use alloc::{vec, vec::Vec};
+
+use crate::common::*;
+
+/// A cardinality estimator using the UltraLogLog registers, see: Ertl, Otmar.
+/// "UltraLogLog: A Practical and More Space-Efficient Alternative to HyperLogLog for
+/// Approximate Distinct Counting." Proceedings of the VLDB Endowment 17.7 (2024): 1655-1668.
+///
+/// Each register keeps the maximal update value along with whether the two
+/// preceding values have been observed, and the cardinality is estimated
+/// by the maximum likelihood method
+#[derive(Clone)]
+pub struct ULL<B: BuildHasher> {
+    builder: B,
+    precision: u8,
+    registers: Vec<u8>,
+}
+
+impl<B: BuildHasher> ULL<B> {
+    /// Creates a new `ULL` object with a custom precision and hash builder
+    ///
+    /// # Arguments
+    ///
+    /// - `precision` - corresponds to the number of registers used by this estimator using the
+    ///   formula `no_registers = 2^precision`; the accepted values lie in the range {4, 5, ..., 16}
+    /// - `builder` - this is a hash builder that will be used for hashing provided values
+    pub fn with_precision(precision: u8, builder: B) -> Result<Self, GumbelError> {
+        // check if the provided precision is within the bounds
+        check_precision(precision)?;
+
+        // create the estimator object with all registers empty
+        Ok(Self {
+            builder,
+            precision,
+            registers: vec![0; 1 << precision],
+        })
+    }
+
+    // the maximal update value; the remaining `32 - precision` bits of the hash
+    // give the update values {1, 2, ..., 33 - precision}
+    fn max_update(&self) -> u32 {
+        33 - self.precision as u32
+    }
+
+    pub fn add<H: Hash + ?Sized>(&mut self, value: &H) {
+        // hash the value and separate the hash into the index and the remainder
+        let (index, hash) = hash_value(value, &self.builder, self.precision);
+
+        // the update value is the position of the leftmost 1-bit of the remainder
+        let update = u32::min(hash.leading_zeros() + 1, self.max_update());
+
+        // add the update value to the set of observed values and keep its three largest elements
+        let observed = unpack(self.registers[index]) | 1 << update;
+        self.registers[index] = pack(observed);
+    }
+
+    /// Merges the `other` estimator into this one, so that it estimates the cardinality of
+    /// the union of both streams; both estimators must use the same precision and hash builder
+    pub fn merge(&mut self, other: &Self) -> Result<(), GumbelError> {
+        if self.precision != other.precision {
+            return Err(GumbelError::IncompatibleMerge { property: "precision" });
+        }
+
+        // the observed values of the merged register are the union of the observed values of both
+        for (register, &value) in self.registers.iter_mut().zip(&other.registers) {
+            *register = pack(unpack(*register) | unpack(value));
+        }
+
+        Ok(())
+    }
+
+    /// Checks if any register observed the maximal update value, i.e. a hash without any
+    /// set bits after the index, in which case the estimate is only a lower bound of the cardinality
+    pub fn check_saturation(&self) -> Result<(), GumbelError> {
+        let max_update = self.max_update() as u8;
+        let count = self.registers.iter().filter(|&&register| register >> 2 == max_update).count();
+        if count > 0 {
+            return Err(GumbelError::SaturatedRegisters { count });
+        }
+
+        Ok(())
+    }
+
+    /// The number of bytes taken by the state of the estimator, i.e. by its registers
+    pub fn memory_bytes(&self) -> usize {
+        self.registers.len()
+    }
+
+    pub fn count(&self) -> f64 {
+        let no_registers = self.registers.len() as f64;
+        let max_update = self.max_update();
+
+        // under the Poisson model, the update value `k` of a register is observed independently
+        // of the others with probability `1 - exp(-x * rho(k))`, where `x = n / no_registers`;
+        // collect the number of observed values for each `k` and the total rate of the unobserved ones
+        let rho = |k: u32| libm::exp2(-(u32::min(k, max_update - 1) as f64));
+        let mut observed = vec![0_usize; max_update as usize + 1];
+        let mut unobserved = 0.0;
+        for &register in &self.registers {
+            let max = (register >> 2) as u32;
+            if max == 0 {
+                unobserved += 1.0;
+                continue;
+            }
+
+            // all values above the maximum have not been observed
+            if max < max_update {
+                unobserved += libm::exp2(-(max as f64));
+            }
+            observed[max as usize] += 1;
+
+            // the two values preceding the maximum are known as well
+            for (k, bit) in [(max - 1, 0b10), (max.wrapping_sub(2), 0b01)] {
+                if k == 0 || k > max {
+                    continue;
+                }
+                if register & bit != 0 {
+                    observed[k as usize] += 1;
+                } else {
+                    unobserved += rho(k);
+                }
+            }
+        }
+
+        if unobserved == 0.0 {
+            return f64::INFINITY;
+        }
+        if observed.iter().all(|&c| c == 0) {
+            return 0.0;
+        }
+
+        // the derivative of the log-likelihood, which is decreasing in `x`
+        let derivative = |x: f64| observed.iter()
+            .enumerate()
+            .filter(|(_, &c)| c > 0)
+            .map(|(k, &c)| c as f64 * rho(k as u32) / libm::expm1(x * rho(k as u32)))
+            .sum::<f64>() - unobserved;
+
+        // find its root by bisection on the logarithmic scale
+        let (mut low, mut high) = (-40.0_f64, 40.0_f64);
+        for _ in 0..64 {
+            let mid = (low + high) / 2.0;
+            if derivative(libm::exp2(mid)) > 0.0 {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+
+        no_registers * libm::exp2((low + high) / 2.0)
+    }
+}
+
+// the set of observed update values as a bit mask, with the bit `k` set if the value `k`
+// has been observed; only the maximum and the two values preceding it are known
+fn unpack(register: u8) -> u64 {
+    let max = register >> 2;
+    if max == 0 {
+        return 0;
+    }
+
+    ((0b100 | (register & 0b11) as u64) << max) >> 2
+}
+
+// store the maximal observed value and whether the two preceding values have been observed;
+// an empty set is stored as the empty register
+fn pack(observed: u64) -> u8 {
+    if observed == 0 {
+        return 0;
+    }
+
+    let max = 63 - observed.leading_zeros() as u8;
+
+    (max << 2) | ((observed << 2 >> max) & 0b11) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ahash::RandomState;
+
+    fn ull(precision: u8, values: core::ops::Range<u64>) -> ULL<RandomState> {
+        let mut estimator = ULL::with_precision(precision, RandomState::with_seeds(1, 2, 3, 4)).unwrap();
+        for value in values {
+            estimator.add(&value);
+        }
+
+        estimator
+    }
+
+    #[test]
+    fn pack_round_trips_the_known_values() {
+        assert_eq!(pack(0), 0);
+
+        // the registers with update values in {1, 2, ..., 33}, where the preceding values
+        // can only have been observed if they are positive
+        for max in 1..=33u8 {
+            for bits in 0..4u8 {
+                if (bits & 0b10 != 0 && max < 2) || (bits & 0b01 != 0 && max < 3) {
+                    continue;
+                }
+                let register = max << 2 | bits;
+                assert_eq!(pack(unpack(register)), register);
+            }
+        }
+    }
+
+    #[test]
+    fn merge_keeps_registers_empty_in_both() {
+        // most of the 4096 registers are empty in both estimators
+        let mut first = ull(12, 0..100);
+        let second = ull(12, 100..200);
+        first.merge(&second).unwrap();
+
+        assert_eq!(first.registers, ull(12, 0..200).registers);
+        assert!(first.registers.contains(&0));
+    }
+
+    #[test]
+    fn merge_of_empty_estimators_is_empty() {
+        let mut first = ull(4, 0..0);
+        first.merge(&ull(4, 0..0)).unwrap();
+
+        assert!(first.registers.iter().all(|&register| register == 0));
+        assert_eq!(first.count(), 0.0);
+    }
+}
@@ -0,0 +1,14 @@
+use std::env;
+use std::path::PathBuf;
+
+fn main() {
+    let crate_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+
+    // the header is kept in the repository, so that C users need not build the crate to get it
+    cbindgen::generate(&crate_dir)
+        .expect("unable to generate the C header")
+        .write_to_file(crate_dir.join("include").join("gumbel_ffi.h"));
+
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+}
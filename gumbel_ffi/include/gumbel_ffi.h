@@ -0,0 +1,129 @@
+#ifndef GUMBEL_FFI_H
+#define GUMBEL_FFI_H
+
+/* This file is generated by the build script of gumbel_ffi; do not edit it by hand. */
+
+#include <stddef.h>
+#include <stdint.h>
+
+// The result of an operation
+typedef enum GumbelStatus {
+  GUMBEL_STATUS_OK = 0,
+  // a required pointer is null
+  GUMBEL_STATUS_NULL_POINTER = 1,
+  // the precision lies outside of the range {4, 5, ..., 16}
+  GUMBEL_STATUS_INVALID_PRECISION = 2,
+  // the estimators differ in their precision or hash seed
+  GUMBEL_STATUS_INCOMPATIBLE_MERGE = 3,
+  // the serialized estimator is malformed, e.g. truncated
+  GUMBEL_STATUS_CORRUPT_DATA = 4,
+  // the serialized estimator was written in a newer format version
+  GUMBEL_STATUS_UNSUPPORTED_VERSION = 5,
+  // the buffer cannot hold the serialized estimator
+  GUMBEL_STATUS_BUFFER_TOO_SMALL = 6,
+  // any other error
+  GUMBEL_STATUS_OTHER = 7,
+} GumbelStatus;
+
+// An estimator along with the seed of its hash function
+typedef struct GumbelHandle GumbelHandle;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Creates a GHLL estimator with `2^precision` registers, estimating with the geometric mean
+// of the registers. Estimators can be merged if they share the precision, the `seed`
+// of their initial register values and the `hash_seed`.
+//
+// Returns null on failure, with the reason stored in `status` unless it is null.
+//
+// # Safety
+//
+// `status` must be null or point to a writable `GumbelStatus`.
+struct GumbelHandle *gumbel_ghll_new(uint8_t precision,
+                                     uint64_t seed,
+                                     uint64_t hash_seed,
+                                     enum GumbelStatus *status);
+
+// Creates a GHLL+ estimator, i.e. a GHLL estimator with the corrections of GHLL+;
+// see `gumbel_ghll_new`.
+//
+// # Safety
+//
+// `status` must be null or point to a writable `GumbelStatus`.
+struct GumbelHandle *gumbel_ghll_plus_new(uint8_t precision,
+                                          uint64_t seed,
+                                          uint64_t hash_seed,
+                                          enum GumbelStatus *status);
+
+// Releases an estimator; null is ignored.
+//
+// # Safety
+//
+// `handle` must be null or a handle which has not been released yet.
+void gumbel_free(struct GumbelHandle *handle);
+
+// Adds a value given by its bytes.
+//
+// # Safety
+//
+// `handle` must be a valid handle and `data` must point to `len` readable bytes,
+// or be null if `len` is zero.
+enum GumbelStatus gumbel_add_bytes(struct GumbelHandle *handle, const uint8_t *data, size_t len);
+
+// Adds a 64-bit integer value.
+//
+// # Safety
+//
+// `handle` must be a valid handle.
+enum GumbelStatus gumbel_add_u64(struct GumbelHandle *handle, uint64_t value);
+
+// Estimates the number of distinct values added; returns NaN if `handle` is null.
+//
+// # Safety
+//
+// `handle` must be null or a valid handle.
+double gumbel_estimate(const struct GumbelHandle *handle);
+
+// Merges the `other` estimator into `handle`, so that it estimates the number of distinct
+// values added to either of them. Merging an estimator with itself leaves it unchanged.
+//
+// # Safety
+//
+// `handle` and `other` must be valid handles, possibly the same one.
+enum GumbelStatus gumbel_merge(struct GumbelHandle *handle, const struct GumbelHandle *other);
+
+// Serializes an estimator into `buffer` of `capacity` bytes and stores the length
+// of the serialized estimator in `length`. If the buffer is too small, nothing is written
+// to it and `GUMBEL_STATUS_BUFFER_TOO_SMALL` is returned, so that calling the function
+// with a null buffer and zero capacity queries the required length.
+// The hash seed is not serialized.
+//
+// # Safety
+//
+// `handle` must be a valid handle, `buffer` must point to `capacity` writable bytes
+// or be null, and `length` must point to a writable `size_t`.
+enum GumbelStatus gumbel_serialize(const struct GumbelHandle *handle,
+                                   uint8_t *buffer,
+                                   size_t capacity,
+                                   size_t *length);
+
+// Deserializes an estimator written by `gumbel_serialize`, whose hash seed was `hash_seed`.
+//
+// Returns null on failure, with the reason stored in `status` unless it is null.
+//
+// # Safety
+//
+// `data` must point to `len` readable bytes, and `status` must be null or point to
+// a writable `GumbelStatus`.
+struct GumbelHandle *gumbel_deserialize(const uint8_t *data,
+                                        size_t len,
+                                        uint64_t hash_seed,
+                                        enum GumbelStatus *status);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* GUMBEL_FFI_H */
@@ -0,0 +1,248 @@
+//! A C interface to the Gumbel estimators; the header `include/gumbel_ffi.h` is generated
+//! from this file by the build script.
+//!
+//! Estimators are used through opaque `GumbelHandle` pointers, which are created by
+//! `gumbel_ghll_new`, `gumbel_ghll_plus_new` or `gumbel_deserialize` and released by `gumbel_free`.
+//! The handles are not synchronised; a handle may be used by a single thread at a time.
+
+use ahash::RandomState;
+use gumbel_estimation::{GumbelError, GumbelSketch, GHLL, GHLLPlus};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use std::{ptr, slice};
+
+/// The result of an operation
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GumbelStatus {
+    Ok = 0,
+    /// a required pointer is null
+    NullPointer = 1,
+    /// the precision lies outside of the range {4, 5, ..., 16}
+    InvalidPrecision = 2,
+    /// the estimators differ in their precision or hash seed
+    IncompatibleMerge = 3,
+    /// the serialized estimator is malformed, e.g. truncated
+    CorruptData = 4,
+    /// the serialized estimator was written in a newer format version
+    UnsupportedVersion = 5,
+    /// the buffer cannot hold the serialized estimator
+    BufferTooSmall = 6,
+    /// any other error
+    Other = 7,
+}
+
+impl From<GumbelError> for GumbelStatus {
+    fn from(error: GumbelError) -> Self {
+        match error {
+            GumbelError::InvalidPrecision { .. } => GumbelStatus::InvalidPrecision,
+            GumbelError::IncompatibleMerge { .. } => GumbelStatus::IncompatibleMerge,
+            GumbelError::CorruptData { .. } => GumbelStatus::CorruptData,
+            GumbelError::UnsupportedVersion { .. } => GumbelStatus::UnsupportedVersion,
+            _ => GumbelStatus::Other,
+        }
+    }
+}
+
+/// An estimator along with the seed of its hash function
+pub struct GumbelHandle {
+    sketch: GumbelSketch<RandomState>,
+    hash_seed: u64,
+}
+
+// the hash function indexed by a seed, built as in the comparison, so that the same seed
+// gives the same hash function in every process
+fn hasher(hash_seed: u64) -> RandomState {
+    RandomState::with_seeds(hash_seed, !hash_seed, hash_seed.rotate_left(32), !hash_seed.rotate_left(32))
+}
+
+// stores the status if the pointer is not null
+unsafe fn report(status: *mut GumbelStatus, value: GumbelStatus) {
+    if !status.is_null() {
+        *status = value;
+    }
+}
+
+// moves the result into a new handle, or reports the error and returns null
+unsafe fn into_handle(result: Result<GumbelHandle, GumbelError>, status: *mut GumbelStatus) -> *mut GumbelHandle {
+    match result {
+        Ok(handle) => {
+            report(status, GumbelStatus::Ok);
+            Box::into_raw(Box::new(handle))
+        }
+        Err(error) => {
+            report(status, error.into());
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Creates a GHLL estimator with `2^precision` registers, estimating with the geometric mean
+/// of the registers. Estimators can be merged if they share the precision, the `seed`
+/// of their initial register values and the `hash_seed`.
+///
+/// Returns null on failure, with the reason stored in `status` unless it is null.
+///
+/// # Safety
+///
+/// `status` must be null or point to a writable `GumbelStatus`.
+#[no_mangle]
+pub unsafe extern "C" fn gumbel_ghll_new(precision: u8, seed: u64, hash_seed: u64, status: *mut GumbelStatus) -> *mut GumbelHandle {
+    let result = GHLL::with_precision_and_rng(precision, hasher(hash_seed), &mut StdRng::seed_from_u64(seed))
+        .map(|estimator| GumbelHandle { sketch: estimator.into_sketch(), hash_seed });
+
+    into_handle(result, status)
+}
+
+/// Creates a GHLL+ estimator, i.e. a GHLL estimator with the corrections of GHLL+;
+/// see `gumbel_ghll_new`.
+///
+/// # Safety
+///
+/// `status` must be null or point to a writable `GumbelStatus`.
+#[no_mangle]
+pub unsafe extern "C" fn gumbel_ghll_plus_new(precision: u8, seed: u64, hash_seed: u64, status: *mut GumbelStatus) -> *mut GumbelHandle {
+    let result = GHLLPlus::with_precision_and_rng(precision, hasher(hash_seed), &mut StdRng::seed_from_u64(seed))
+        .map(|estimator| GumbelHandle { sketch: estimator.into_sketch(), hash_seed });
+
+    into_handle(result, status)
+}
+
+/// Releases an estimator; null is ignored.
+///
+/// # Safety
+///
+/// `handle` must be null or a handle which has not been released yet.
+#[no_mangle]
+pub unsafe extern "C" fn gumbel_free(handle: *mut GumbelHandle) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+/// Adds a value given by its bytes.
+///
+/// # Safety
+///
+/// `handle` must be a valid handle and `data` must point to `len` readable bytes,
+/// or be null if `len` is zero.
+#[no_mangle]
+pub unsafe extern "C" fn gumbel_add_bytes(handle: *mut GumbelHandle, data: *const u8, len: usize) -> GumbelStatus {
+    let Some(handle) = handle.as_mut() else {
+        return GumbelStatus::NullPointer;
+    };
+    let value = match (data.is_null(), len) {
+        (true, 0) => &[][..],
+        (true, _) => return GumbelStatus::NullPointer,
+        (false, _) => slice::from_raw_parts(data, len),
+    };
+
+    handle.sketch.add(value);
+    GumbelStatus::Ok
+}
+
+/// Adds a 64-bit integer value.
+///
+/// # Safety
+///
+/// `handle` must be a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn gumbel_add_u64(handle: *mut GumbelHandle, value: u64) -> GumbelStatus {
+    let Some(handle) = handle.as_mut() else {
+        return GumbelStatus::NullPointer;
+    };
+
+    handle.sketch.add(&value);
+    GumbelStatus::Ok
+}
+
+/// Estimates the number of distinct values added; returns NaN if `handle` is null.
+///
+/// # Safety
+///
+/// `handle` must be null or a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn gumbel_estimate(handle: *const GumbelHandle) -> f64 {
+    match handle.as_ref() {
+        Some(handle) => handle.sketch.count(),
+        None => f64::NAN,
+    }
+}
+
+/// Merges the `other` estimator into `handle`, so that it estimates the number of distinct
+/// values added to either of them. Merging an estimator with itself leaves it unchanged.
+///
+/// # Safety
+///
+/// `handle` and `other` must be valid handles, possibly the same one.
+#[no_mangle]
+pub unsafe extern "C" fn gumbel_merge(handle: *mut GumbelHandle, other: *const GumbelHandle) -> GumbelStatus {
+    if handle.is_null() || other.is_null() {
+        return GumbelStatus::NullPointer;
+    }
+
+    // the union of an estimator with itself is the estimator, and borrowing the handle
+    // both mutably and immutably would alias
+    if ptr::eq(handle, other) {
+        return GumbelStatus::Ok;
+    }
+
+    let (Some(handle), Some(other)) = (handle.as_mut(), other.as_ref()) else {
+        return GumbelStatus::NullPointer;
+    };
+    if handle.hash_seed != other.hash_seed {
+        return GumbelStatus::IncompatibleMerge;
+    }
+
+    match handle.sketch.merge(&other.sketch) {
+        Ok(()) => GumbelStatus::Ok,
+        Err(error) => error.into(),
+    }
+}
+
+/// Serializes an estimator into `buffer` of `capacity` bytes and stores the length
+/// of the serialized estimator in `length`. If the buffer is too small, nothing is written
+/// to it and `GUMBEL_STATUS_BUFFER_TOO_SMALL` is returned, so that calling the function
+/// with a null buffer and zero capacity queries the required length.
+/// The hash seed is not serialized.
+///
+/// # Safety
+///
+/// `handle` must be a valid handle, `buffer` must point to `capacity` writable bytes
+/// or be null, and `length` must point to a writable `size_t`.
+#[no_mangle]
+pub unsafe extern "C" fn gumbel_serialize(handle: *const GumbelHandle, buffer: *mut u8, capacity: usize, length: *mut usize) -> GumbelStatus {
+    let (Some(handle), false) = (handle.as_ref(), length.is_null()) else {
+        return GumbelStatus::NullPointer;
+    };
+
+    let bytes = handle.sketch.to_bytes();
+    *length = bytes.len();
+    if buffer.is_null() || capacity < bytes.len() {
+        return GumbelStatus::BufferTooSmall;
+    }
+
+    ptr::copy_nonoverlapping(bytes.as_ptr(), buffer, bytes.len());
+    GumbelStatus::Ok
+}
+
+/// Deserializes an estimator written by `gumbel_serialize`, whose hash seed was `hash_seed`.
+///
+/// Returns null on failure, with the reason stored in `status` unless it is null.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes, and `status` must be null or point to
+/// a writable `GumbelStatus`.
+#[no_mangle]
+pub unsafe extern "C" fn gumbel_deserialize(data: *const u8, len: usize, hash_seed: u64, status: *mut GumbelStatus) -> *mut GumbelHandle {
+    if data.is_null() {
+        report(status, GumbelStatus::NullPointer);
+        return ptr::null_mut();
+    }
+
+    let result = GumbelSketch::from_bytes(slice::from_raw_parts(data, len), hasher(hash_seed))
+        .map(|sketch| GumbelHandle { sketch, hash_seed });
+
+    into_handle(result, status)
+}
@@ -0,0 +1,103 @@
+/* Exercises the C interface of the Gumbel estimators; run by `tests/c_api.rs`. */
+
+#include <math.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "gumbel_ffi.h"
+
+#define CHECK(condition)                                                   \
+    do {                                                                   \
+        if (!(condition)) {                                                \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__,         \
+                    __LINE__, #condition);                                 \
+            return 1;                                                      \
+        }                                                                  \
+    } while (0)
+
+static int close_to(double estimate, double expected, double tolerance) {
+    return fabs(estimate / expected - 1.0) < tolerance;
+}
+
+int main(void) {
+    GumbelStatus status = GUMBEL_STATUS_OTHER;
+
+    /* invalid precision */
+    CHECK(gumbel_ghll_new(20, 1, 0, &status) == NULL);
+    CHECK(status == GUMBEL_STATUS_INVALID_PRECISION);
+
+    /* adding and estimating */
+    GumbelHandle *first = gumbel_ghll_plus_new(12, 1, 0, &status);
+    CHECK(first != NULL && status == GUMBEL_STATUS_OK);
+    for (uint64_t i = 0; i < 50000; i++) {
+        CHECK(gumbel_add_u64(first, i) == GUMBEL_STATUS_OK);
+    }
+    CHECK(close_to(gumbel_estimate(first), 50000.0, 0.1));
+
+    /* adding bytes, where duplicates are not counted again */
+    GumbelHandle *second = gumbel_ghll_plus_new(12, 1, 0, NULL);
+    CHECK(second != NULL);
+    char key[32];
+    for (int round = 0; round < 2; round++) {
+        for (int i = 0; i < 20000; i++) {
+            int len = snprintf(key, sizeof key, "key-%d", i);
+            CHECK(gumbel_add_bytes(second, (const uint8_t *)key, (size_t)len) == GUMBEL_STATUS_OK);
+        }
+    }
+    CHECK(close_to(gumbel_estimate(second), 20000.0, 0.1));
+
+    /* merging */
+    CHECK(gumbel_merge(first, second) == GUMBEL_STATUS_OK);
+    CHECK(close_to(gumbel_estimate(first), 70000.0, 0.1));
+
+    GumbelHandle *other_precision = gumbel_ghll_plus_new(10, 1, 0, NULL);
+    CHECK(gumbel_merge(first, other_precision) == GUMBEL_STATUS_INCOMPATIBLE_MERGE);
+    GumbelHandle *other_hash = gumbel_ghll_plus_new(12, 1, 7, NULL);
+    CHECK(gumbel_merge(first, other_hash) == GUMBEL_STATUS_INCOMPATIBLE_MERGE);
+    CHECK(gumbel_merge(first, NULL) == GUMBEL_STATUS_NULL_POINTER);
+
+    /* merging an estimator with itself leaves it unchanged */
+    double before = gumbel_estimate(first);
+    CHECK(gumbel_merge(first, first) == GUMBEL_STATUS_OK);
+    CHECK(gumbel_estimate(first) == before);
+
+    /* serializing and deserializing */
+    size_t length = 0;
+    CHECK(gumbel_serialize(first, NULL, 0, &length) == GUMBEL_STATUS_BUFFER_TOO_SMALL);
+    CHECK(length > 0);
+    uint8_t *buffer = malloc(length);
+    CHECK(buffer != NULL);
+    CHECK(gumbel_serialize(first, buffer, length, &length) == GUMBEL_STATUS_OK);
+
+    GumbelHandle *restored = gumbel_deserialize(buffer, length, 0, &status);
+    CHECK(restored != NULL && status == GUMBEL_STATUS_OK);
+    CHECK(gumbel_estimate(restored) == gumbel_estimate(first));
+
+    CHECK(gumbel_deserialize(buffer, length - 1, 0, &status) == NULL);
+    CHECK(status == GUMBEL_STATUS_CORRUPT_DATA);
+    buffer[0] = 0xff;
+    CHECK(gumbel_deserialize(buffer, length, 0, &status) == NULL);
+    CHECK(status == GUMBEL_STATUS_UNSUPPORTED_VERSION);
+
+    /* the plain GHLL estimator */
+    GumbelHandle *ghll = gumbel_ghll_new(12, 1, 0, NULL);
+    CHECK(ghll != NULL);
+    for (uint64_t i = 0; i < 100000; i++) {
+        gumbel_add_u64(ghll, i);
+    }
+    CHECK(close_to(gumbel_estimate(ghll), 100000.0, 0.1));
+    CHECK(isnan(gumbel_estimate(NULL)));
+
+    free(buffer);
+    gumbel_free(first);
+    gumbel_free(second);
+    gumbel_free(other_precision);
+    gumbel_free(other_hash);
+    gumbel_free(restored);
+    gumbel_free(ghll);
+    gumbel_free(NULL);
+
+    printf("all checks passed\n");
+    return 0;
+}
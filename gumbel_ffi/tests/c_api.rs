@@ -0,0 +1,33 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// the directory of the libraries built by cargo, i.e. the parent of the `deps` directory
+// holding the test executable
+fn target_dir() -> PathBuf {
+    let exe = env::current_exe().unwrap();
+    exe.parent().and_then(Path::parent).unwrap().to_path_buf()
+}
+
+#[test]
+fn c_program() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let target_dir = target_dir();
+    let executable = target_dir.join("test_gumbel_c");
+
+    // compile the C test program against the static library, so that it runs without
+    // locating the shared one
+    let compiler = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let output = Command::new(compiler)
+        .arg(manifest_dir.join("tests").join("c").join("test_gumbel.c"))
+        .arg("-I").arg(manifest_dir.join("include"))
+        .arg(target_dir.join("libgumbel_ffi.a"))
+        .args(["-lm", "-lpthread", "-ldl", "-Wall", "-Wextra", "-Werror", "-std=c99", "-o"])
+        .arg(&executable)
+        .output()
+        .expect("failed to run the C compiler");
+    assert!(output.status.success(), "compilation failed:\n{}", String::from_utf8_lossy(&output.stderr));
+
+    let output = Command::new(&executable).output().unwrap();
+    assert!(output.status.success(), "the C test program failed:\n{}", String::from_utf8_lossy(&output.stderr));
+}
@@ -0,0 +1,181 @@
+use ahash::RandomState;
+use gumbel_estimation::{GumbelError, GumbelSketchBuilder, GHLL, GHLLPlus, GHLLReal};
+use numpy::PyReadonlyArray1;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+/// The precision used unless set otherwise
+const DEFAULT_PRECISION: u8 = GumbelSketchBuilder::DEFAULT_PRECISION;
+
+// the hash function indexed by a seed, built as in the comparison, so that the same seed
+// gives the same hash function in every process
+fn hasher(hash_seed: u64) -> RandomState {
+    RandomState::with_seeds(hash_seed, !hash_seed, hash_seed.rotate_left(32), !hash_seed.rotate_left(32))
+}
+
+fn to_py_err(error: GumbelError) -> PyErr {
+    PyValueError::new_err(error.to_string())
+}
+
+/// A value added to an estimator; negative integers are hashed as their two's complement,
+/// so that they match the values of signed numpy arrays
+#[derive(FromPyObject)]
+enum Key {
+    Unsigned(u64),
+    Signed(i64),
+    Str(String),
+    Bytes(Vec<u8>),
+}
+
+/// A numpy array of integers added to an estimator
+#[derive(FromPyObject)]
+enum Array<'py> {
+    Unsigned(PyReadonlyArray1<'py, u64>),
+    Signed(PyReadonlyArray1<'py, i64>),
+}
+
+// defines a Python class wrapping an estimator built by the given method of the builder
+macro_rules! estimator_class {
+    ($class:ident, $estimator:ident, $build:ident, $name:literal) => {
+        #[pyclass(name = $name, module = "gumbel_python")]
+        #[derive(Clone)]
+        pub struct $class {
+            estimator: $estimator<RandomState>,
+            hash_seed: u64,
+        }
+
+        #[pymethods]
+        impl $class {
+            /// Creates an estimator with `2^precision` registers; estimators can be merged
+            /// if they share the `seed` of their initial register values and the `hash_seed`
+            #[new]
+            #[pyo3(signature = (precision = DEFAULT_PRECISION, seed = None, hash_seed = 0))]
+            fn new(precision: u8, seed: Option<u64>, hash_seed: u64) -> PyResult<Self> {
+                let mut builder = GumbelSketchBuilder::new()
+                    .precision(precision)
+                    .hasher(hasher(hash_seed));
+                if let Some(seed) = seed {
+                    builder = builder.seed(seed);
+                }
+
+                let estimator = builder.$build().map_err(to_py_err)?;
+                Ok(Self { estimator, hash_seed })
+            }
+
+            /// Adds an integer, string or bytes value
+            fn add(&mut self, value: Key) {
+                match value {
+                    Key::Unsigned(value) => self.estimator.add(&value),
+                    Key::Signed(value) => self.estimator.add(&(value as u64)),
+                    Key::Str(value) => self.estimator.add(value.as_str()),
+                    Key::Bytes(value) => self.estimator.add(value.as_slice()),
+                }
+            }
+
+            /// Adds every value of a one-dimensional numpy array of 64-bit integers
+            fn add_array(&mut self, values: Array<'_>) {
+                match values {
+                    Array::Unsigned(values) => values.as_array()
+                        .iter()
+                        .for_each(|value| self.estimator.add(value)),
+                    Array::Signed(values) => values.as_array()
+                        .iter()
+                        .for_each(|&value| self.estimator.add(&(value as u64))),
+                }
+            }
+
+            /// Merges the `other` estimator into this one
+            fn merge(&mut self, other: PyRef<'_, Self>) -> PyResult<()> {
+                if self.hash_seed != other.hash_seed {
+                    return Err(PyValueError::new_err("cannot merge estimators of different hash seeds"));
+                }
+
+                self.estimator.merge(&other.estimator).map_err(to_py_err)
+            }
+
+            /// Estimates the cardinality with the default strategy of the estimator
+            fn count(&self) -> f64 {
+                self.estimator.count()
+            }
+
+            fn count_geo(&self) -> f64 {
+                self.estimator.count_geo()
+            }
+
+            fn count_har(&self) -> f64 {
+                self.estimator.count_har()
+            }
+
+            /// The number of bytes taken by the registers
+            fn memory_bytes(&self) -> usize {
+                self.estimator.memory_bytes()
+            }
+
+            /// Serializes the estimator; the hash seed is not serialized
+            fn serialize<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+                PyBytes::new(py, &self.estimator.to_bytes())
+            }
+
+            /// Deserializes an estimator written by `serialize`, which used the given hash seed
+            #[staticmethod]
+            #[pyo3(signature = (data, hash_seed = 0))]
+            fn deserialize(data: &[u8], hash_seed: u64) -> PyResult<Self> {
+                let estimator = $estimator::from_bytes(data, hasher(hash_seed)).map_err(to_py_err)?;
+                Ok(Self { estimator, hash_seed })
+            }
+        }
+    };
+}
+
+estimator_class!(PyGHLL, GHLL, build_ghll, "GHLL");
+estimator_class!(PyGHLLReal, GHLLReal, build_ghll_real, "GHLLReal");
+estimator_class!(PyGHLLPlus, GHLLPlus, build_ghll_plus, "GHLLPlus");
+
+/// The Gumbel cardinality estimators
+#[pymodule]
+fn gumbel_python(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyGHLL>()?;
+    module.add_class::<PyGHLLReal>()?;
+    module.add_class::<PyGHLLPlus>()?;
+    module.add("FORMAT_VERSION", gumbel_estimation::FORMAT_VERSION)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserializes_with_the_hash_seed() {
+        let mut estimator = PyGHLLPlus::new(10, Some(7), 3).unwrap();
+        for value in 0..1000 {
+            estimator.add(Key::Unsigned(value));
+        }
+        estimator.add(Key::Signed(-1));
+        estimator.add(Key::Str("key".to_owned()));
+
+        let bytes = estimator.estimator.to_bytes();
+        let restored = PyGHLLPlus::deserialize(&bytes, 3).unwrap();
+        assert_eq!(restored.hash_seed, 3);
+        assert_eq!(restored.count(), estimator.count());
+        assert_eq!(restored.estimator.to_bytes(), bytes);
+
+        // the estimators of other kinds and corrupt data are rejected
+        assert!(PyGHLLReal::deserialize(&bytes, 3).is_err());
+        assert!(PyGHLLPlus::deserialize(&bytes[..bytes.len() - 1], 3).is_err());
+    }
+
+    #[test]
+    fn real_round_trip() {
+        let mut estimator = PyGHLLReal::new(8, Some(7), 0).unwrap();
+        for value in 0..1000 {
+            estimator.add(Key::Unsigned(value));
+        }
+
+        let restored = PyGHLLReal::deserialize(&estimator.estimator.to_bytes(), 0).unwrap();
+        assert_eq!(restored.count_geo(), estimator.count_geo());
+        assert_eq!(restored.count_har(), estimator.count_har());
+    }
+}